@@ -0,0 +1,984 @@
+//! Original creator [exrok](https://github.com/exrok), no license specified.
+//! reimplemented just for testing and learning purposes
+
+use gol::mask::Mask;
+use gol::noise::Noise;
+use gol::pattern::Pattern;
+use gol::{BitGrid, Engine, Rule, Topology};
+use std::fmt;
+
+pub mod batch;
+
+type Cluster = u64;
+const CLUSTER_SIZE: usize = 62;
+/// The most and least significant bits of each cluster, used for the adjacent cells.
+const EDGE_MASK: Cluster = 0x8000_0000_0000_0001;
+
+/// Adds three words bit by bit, returning the low and high bits of the sums.
+pub(crate) fn bit_sum(a: u64, b: u64, c: u64) -> (u64, u64) {
+    (a ^ b ^ c, a & b | a & c | b & c)
+}
+
+/// computes the generation of `B3/S23` for the 64 bits of `cell` at once.
+/// `left` and `right` are the sums of the columns of three cells left and
+/// right of each bit as returned by `bit_sum`, `above` and `below` the cells
+/// of its own column.
+pub(crate) fn life_adder(
+    cell: u64,
+    above: u64,
+    below: u64,
+    left: (u64, u64),
+    right: (u64, u64),
+) -> u64 {
+    let (ax, ay) = bit_sum(left.0, above ^ below, right.0);
+    let (bx, by) = bit_sum(left.1, above & below, right.1);
+    // (three or alive) & two_or_three_mod4 & !more_than_three
+    (cell | ax) & (ay ^ bx) & !by
+}
+
+/// The game of life state represenation using collection of `Cluster`
+/// (`u64`), where each cluster represents state of 62 cells in a row.
+///
+#[derive(Debug, Clone)]
+pub struct LifeState {
+    width: usize,
+    height: usize,
+    pub rule: Rule,
+    pub topology: Topology,
+    grid: Box<[Cluster]>,
+    obstacles: Option<Obstacles>,
+    noise: Option<Noise>,
+}
+
+/// The walls and fixed cells as clusters laid out like the grid, applied
+/// after every tick with `cluster & and | or`.
+#[derive(Debug, Clone)]
+struct Obstacles {
+    mask: Mask,
+    /// Every bit set but the walls.
+    and: Box<[Cluster]>,
+    /// Only the fixed cells set.
+    or: Box<[Cluster]>,
+}
+
+impl LifeState {
+    pub fn new(width: usize, height: usize) -> Self {
+        let columns = width.div_ceil(CLUSTER_SIZE);
+        Self {
+            width,
+            height,
+            rule: Rule::default(),
+            topology: Topology::Bounded,
+            grid: vec![0; columns * height].into(),
+            obstacles: None,
+            noise: None,
+        }
+    }
+    pub fn is_alive(&self, x: usize, y: usize) -> bool {
+        let i = (x / CLUSTER_SIZE) * self.height + y;
+        let offset = (x % CLUSTER_SIZE) + 1;
+        ((self.grid[i] >> offset) & 0b1) == 1
+    }
+
+    pub fn set(&mut self, x: usize, y: usize) {
+        let i = (x / CLUSTER_SIZE) * self.height + y;
+        let offset = (x % CLUSTER_SIZE) + 1;
+        self.grid[i] |= 0b1 << offset;
+    }
+
+    /// The bits of the cells in the clusters of `column`, the last column
+    /// only has the cells up to the width.
+    fn cell_bits(&self, column: usize) -> Cluster {
+        let count = CLUSTER_SIZE.min(self.width - column * CLUSTER_SIZE);
+        ((1 << count) - 1) << 1
+    }
+
+    /// computes the generation of the grid in place.
+    pub fn tick(&mut self) {
+        let last = self.noise.is_some().then(|| self.grid.clone());
+        if self.rule.is_life() {
+            self.tick_life();
+        } else {
+            self.tick_lut();
+        }
+        if let Some(last) = last {
+            self.apply_noise(&last);
+        }
+        self.apply_obstacles();
+    }
+
+    /// Draws the noise for the clusters the tick made from `last`, a random
+    /// mask for each cluster, keeping the bits beyond the cells clear.
+    fn apply_noise(&mut self, last: &[Cluster]) {
+        if let Some(mut dice) = self.noise.as_mut().map(Noise::step) {
+            let cells: Vec<Cluster> = (0..self.grid.len() / self.height)
+                .map(|column| self.cell_bits(column))
+                .collect();
+            let columns = self.grid.chunks_exact_mut(self.height);
+            for ((clusters, last), cells) in columns.zip(last.chunks_exact(self.height)).zip(cells)
+            {
+                for (cluster, was) in clusters.iter_mut().zip(last) {
+                    *cluster = dice.cells(was & cells, *cluster & cells) & cells;
+                }
+            }
+        }
+    }
+
+    fn apply_obstacles(&mut self) {
+        if let Some(obstacles) = &self.obstacles {
+            let masks = obstacles.and.iter().zip(obstacles.or.iter());
+            for (cluster, (and, or)) in self.grid.iter_mut().zip(masks) {
+                *cluster = *cluster & and | or;
+            }
+        }
+    }
+
+    /// computes the generation of any rule through the rule's neighbourhood
+    /// lookup table, one cell at a time. Much slower than `tick_life`.
+    fn tick_lut(&mut self) {
+        let columns = self.grid.len() / self.height;
+        let tail_width = (self.width + CLUSTER_SIZE - 1) % CLUSTER_SIZE + 1;
+        let torus = self.topology == Topology::Torus;
+        let last = self.grid.clone();
+        let cell = |x: usize, y: usize| -> Cluster {
+            (last[(x / CLUSTER_SIZE) * self.height + y] >> (x % CLUSTER_SIZE + 1)) & 0b1
+        };
+        // cluster of the last generation with the adjacent cells of the
+        // neighbouring columns stored in the bits around the cells, dead
+        // outside the grid unless it wraps around.
+        let cluster = |column: usize, y: usize| -> Cluster {
+            let i = column * self.height + y;
+            let mut ret = last[i] & !EDGE_MASK;
+            if column > 0 {
+                ret |= (last[i - self.height] >> CLUSTER_SIZE) & 0b1;
+            } else if torus {
+                ret |= cell(self.width - 1, y);
+            }
+            if column + 1 < columns {
+                ret |= (last[i + self.height] << CLUSTER_SIZE) & EDGE_MASK;
+            } else if torus {
+                ret |= cell(0, y) << (tail_width + 1);
+            }
+            ret
+        };
+
+        for column in 0..columns {
+            let width = if column + 1 == columns {
+                tail_width
+            } else {
+                CLUSTER_SIZE
+            };
+            let mut above = if torus {
+                cluster(column, self.height - 1)
+            } else {
+                0
+            };
+            let mut curr = cluster(column, 0);
+            for y in 0..self.height {
+                let below = if y + 1 < self.height {
+                    cluster(column, y + 1)
+                } else if torus {
+                    cluster(column, 0)
+                } else {
+                    0
+                };
+                let mut next = 0;
+                for offset in 1..=width {
+                    let shift = offset - 1;
+                    let neighbourhood = ((above >> shift) & 0b111)
+                        | ((curr >> shift) & 0b111) << 3
+                        | ((below >> shift) & 0b111) << 6;
+                    if self.rule.alive(neighbourhood as usize) {
+                        next |= 0b1 << offset;
+                    }
+                }
+                self.grid[column * self.height + y] = next;
+                above = curr;
+                curr = below;
+            }
+        }
+    }
+
+    /// computes the generation of `B3/S23` with bitwise adders, 62 cells at once.
+    /// On a torus the first and last cells of the rows are also put in the
+    /// adjacent cell bits of the last and first columns, and the columns wrap
+    /// around vertically.
+    fn tick_life(&mut self) {
+        /// computes the generation of column. Assumes that the most and least significant
+        /// bits of the clusters store the state of the adjacent cells.
+        fn tick_column(column: &mut [Cluster], torus: bool) {
+            fn tick_cluster(cluster: &mut Cluster, above: Cluster, below: Cluster) {
+                let (ix, iy) = bit_sum(above, *cluster, below);
+                let left = (ix << 1, iy << 1);
+                let right = (ix >> 1, iy >> 1);
+                *cluster = life_adder(*cluster, above, below, left, right);
+            }
+
+            let (mut above, last_below) = match (column.first(), column.last()) {
+                (Some(&first), Some(&last)) if torus => (last, first),
+                _ => (0, 0),
+            };
+            let mut clusters = column.iter_mut();
+            let mut curr = if let Some(c) = clusters.next() {
+                c
+            } else {
+                return;
+            };
+
+            for below in clusters {
+                let tmp = *curr;
+                tick_cluster(curr, above, *below);
+                above = tmp;
+                curr = below;
+            }
+            tick_cluster(curr, above, last_below);
+        }
+
+        let edge_mask = EDGE_MASK;
+        //tail_mask is used to zero extra width in the last rowsumn
+        let tail_width = (self.width + CLUSTER_SIZE - 1) % CLUSTER_SIZE + 1;
+        let tail_mask = edge_mask | (!1u64 << tail_width);
+        let torus = self.topology == Topology::Torus;
+        // the cells across the left and right edges of each row
+        let (wrap_left, wrap_right): (Vec<Cluster>, Vec<Cluster>) = match torus {
+            true => (0..self.height)
+                .map(|y| {
+                    let cell = |x: usize| self.is_alive(x, y) as Cluster;
+                    (cell(self.width - 1), cell(0) << (tail_width + 1))
+                })
+                .unzip(),
+            false => (vec![0; self.height], vec![0; self.height]),
+        };
+        let mut columns = self.grid.chunks_exact_mut(self.height);
+        let mut prev = columns.next().unwrap();
+
+        // Store the next and prev cell of the adjacent clusters of each column into
+        // the temporary cells in each cluster. Once we have set&extracted the outer
+        // cells of each column we progress to the next state w/ tick_column.
+        if let Some(mut curr) = columns.next() {
+            for ((first, second), wrap) in prev.iter_mut().zip(curr.iter()).zip(&wrap_left) {
+                *first ^= ((second << CLUSTER_SIZE) ^ *first) & edge_mask;
+                *first |= wrap;
+            }
+
+            for next in columns {
+                for ((left, mid), right) in prev.iter().zip(curr.iter_mut()).zip(next.iter()) {
+                    *mid ^= (((left >> CLUSTER_SIZE) | (right << CLUSTER_SIZE)) ^ *mid) & edge_mask
+                }
+                tick_column(prev, torus);
+                prev = curr;
+                curr = next;
+            }
+
+            for ((left, last), wrap) in prev.iter().zip(curr.iter_mut()).zip(&wrap_right) {
+                *last ^= ((left >> CLUSTER_SIZE) ^ *last) & tail_mask;
+                *last |= wrap;
+            }
+            tick_column(curr, torus);
+        } else {
+            for ((f, left), right) in prev.iter_mut().zip(&wrap_left).zip(&wrap_right) {
+                //Update bounds on the single column
+                *f &= !tail_mask;
+                *f |= left | right;
+            }
+        }
+        tick_column(prev, torus);
+    }
+
+    /// Reads a plaintext board, `#` and `@` become the walls and fixed
+    /// cells of its mask, see `gol::mask`.
+    pub fn from_plaintext(s: &str) -> Result<Self, &'static str> {
+        let pattern = Pattern::from_plaintext(s)?;
+        if pattern.cells.iter().any(|&c| c > 1) {
+            return Err("Error: Cell state is not valid for the rule.");
+        }
+        let mut ret = LifeState::from(&pattern);
+        if let Some(mask) = pattern.mask {
+            ret.set_mask(mask)?;
+        }
+        Ok(ret)
+    }
+
+    /// Parses the macrocell format, see `gol::macrocell`. The rule is taken
+    /// from the header unless it is missing.
+    pub fn from_macrocell(s: &str) -> Result<Self, &'static str> {
+        let pattern = Pattern::from_macrocell(s)?;
+        let mut ret = LifeState::from(&pattern);
+        if let Some(rule) = &pattern.rule {
+            ret.rule = rule.parse()?;
+        }
+        Ok(ret)
+    }
+
+    pub fn to_macrocell(&self) -> String {
+        self.to_pattern()
+            .to_macrocell(&self.rule.to_string(), false)
+    }
+}
+
+impl PartialEq for LifeState {
+    fn eq(&self, other: &Self) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.rule == other.rule
+            && self.grid == other.grid
+    }
+}
+
+impl From<&str> for LifeState {
+    fn from(s: &str) -> Self {
+        let lines = s.split('\n');
+        let mut width: Option<usize> = None;
+        let mut height = 0;
+        let mut data: Vec<Vec<u8>> = Vec::new();
+        for line in lines {
+            let line_bools: Vec<u8> = line.chars().map(|c| if c == 'X' { 1 } else { 0 }).collect();
+            if !line_bools.is_empty() {
+                height += 1;
+                if let Some(width) = width {
+                    assert_eq!(line_bools.len(), width, "Line of different lenght");
+                } else {
+                    width = Some(line_bools.len());
+                }
+                data.push(line_bools);
+            }
+        }
+        let mut life = LifeState::new(width.unwrap(), height);
+        for (y, row) in data.iter().enumerate() {
+            for (x, cell) in row.iter().enumerate() {
+                if *cell == 1 {
+                    life.set(x, y);
+                }
+            }
+        }
+        life
+    }
+}
+
+impl From<&Pattern> for LifeState {
+    fn from(pattern: &Pattern) -> Self {
+        let mut life = LifeState::new(pattern.width, pattern.height);
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                if pattern.get(x, y) == 1 {
+                    life.set(x, y);
+                }
+            }
+        }
+        life
+    }
+}
+
+impl Engine for LifeState {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.is_alive(x, y).into()
+    }
+
+    fn set(&mut self, x: usize, y: usize, state: u8) {
+        let i = (x / CLUSTER_SIZE) * self.height + y;
+        let offset = (x % CLUSTER_SIZE) + 1;
+        self.grid[i] &= !(0b1 << offset);
+        self.grid[i] |= ((state != 0) as Cluster) << offset;
+    }
+
+    fn step(&mut self) {
+        self.tick();
+    }
+
+    fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    /// Counts the cells of each cluster, leaving out the bits past the
+    /// width that the last column gets from `tick_life`.
+    fn population(&self) -> usize {
+        self.grid
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c & self.cell_bits(i / self.height)).count_ones() as usize)
+            .sum()
+    }
+
+    /// Copies the 62 cells of each cluster at once.
+    fn to_bitgrid(&self) -> BitGrid {
+        let mut ret = BitGrid::new(self.width, self.height);
+        for (column, clusters) in self.grid.chunks_exact(self.height).enumerate() {
+            let x = column * CLUSTER_SIZE;
+            let count = CLUSTER_SIZE.min(self.width - x);
+            for (y, cluster) in clusters.iter().enumerate() {
+                ret.or_bits(x, y, cluster >> 1, count);
+            }
+        }
+        ret
+    }
+
+    /// The clusters as they are, only the bits of the cells.
+    fn save_cells(&self) -> Vec<u8> {
+        self.grid
+            .iter()
+            .enumerate()
+            .flat_map(|(i, c)| (c & self.cell_bits(i / self.height)).to_le_bytes())
+            .collect()
+    }
+
+    fn load_cells(&mut self, data: &[u8]) -> Result<(), &'static str> {
+        if data.len() != self.grid.len() * 8 {
+            return Err("Error: Checkpoint board doesn't match its size.");
+        }
+        for (i, bytes) in data.chunks_exact(8).enumerate() {
+            let cells = self.cell_bits(i / self.height);
+            self.grid[i] = Cluster::from_le_bytes(bytes.try_into().unwrap()) & cells;
+        }
+        Ok(())
+    }
+
+    fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    fn set_mask(&mut self, mask: Mask) -> Result<(), &'static str> {
+        mask.check(self.width, self.height)?;
+        let mut and = vec![!0; self.grid.len()].into_boxed_slice();
+        let mut or = vec![0; self.grid.len()].into_boxed_slice();
+        for (x, y, state) in mask.cells() {
+            let i = (x / CLUSTER_SIZE) * self.height + y;
+            let bit: Cluster = 0b1 << (x % CLUSTER_SIZE + 1);
+            match state {
+                0 => and[i] &= !bit,
+                _ => or[i] |= bit,
+            }
+        }
+        self.obstacles = Some(Obstacles { mask, and, or });
+        self.apply_obstacles();
+        Ok(())
+    }
+
+    fn mask(&self) -> Option<&Mask> {
+        self.obstacles.as_ref().map(|o| &o.mask)
+    }
+
+    fn set_noise(&mut self, noise: Noise) -> Result<(), &'static str> {
+        self.noise = Some(noise);
+        Ok(())
+    }
+
+    fn noise(&self) -> Option<&Noise> {
+        self.noise.as_ref()
+    }
+}
+
+impl fmt::Display for LifeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ret = String::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.is_alive(x, y) {
+                    ret.push('X');
+                } else {
+                    ret.push('.');
+                }
+            }
+            ret.push('\n');
+        }
+        write!(f, "{}", ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_str() {
+        let life = LifeState::from("...\n...\n...");
+
+        // No cells set
+        for y in 0..life.height {
+            for x in 0..life.width {
+                assert!(!life.is_alive(x, y))
+            }
+        }
+
+        let life = LifeState::from(".X.\n...\nX..");
+
+        assert!(!life.is_alive(0, 0));
+        assert!(life.is_alive(1, 0));
+        assert!(!life.is_alive(2, 0));
+        assert!(!life.is_alive(0, 1));
+        assert!(!life.is_alive(1, 1));
+        assert!(!life.is_alive(2, 1));
+        assert!(life.is_alive(0, 2));
+        assert!(!life.is_alive(1, 2));
+        assert!(!life.is_alive(2, 2));
+    }
+
+    #[test]
+    fn obstacles() {
+        // a blinker across the clusters with a wall above its centre
+        let mut life = LifeState::new(70, 3);
+        for x in 61..64 {
+            life.set(x, 1);
+        }
+        let mut mask = Mask::new(70, 3);
+        mask.walls.set(62, 0, true);
+        mask.fixed.set(0, 0, true);
+        assert!(life.set_mask(Mask::new(3, 70)).is_err());
+        life.set_mask(mask.clone()).unwrap();
+        assert!(life.is_alive(0, 0));
+        life.tick();
+        let alive: Vec<_> = (0..3)
+            .flat_map(|y| (0..70).map(move |x| (x, y)))
+            .filter(|&(x, y)| life.is_alive(x, y))
+            .collect();
+        assert_eq!(alive, [(0, 0), (62, 1), (62, 2)]);
+        assert_eq!(life.mask(), Some(&mask));
+    }
+
+    #[test]
+    fn noise() {
+        // every cell flips, but none beyond the width of the last column
+        let mut life = LifeState::new(70, 3);
+        life.set_noise("flip=1".parse().unwrap()).unwrap();
+        life.tick();
+        assert_eq!(life.population(), 210);
+
+        // without survivals the block dies
+        let mut block = LifeState::from("....\n.XX.\n.XX.\n....\n");
+        block.set_noise("survival=0".parse().unwrap()).unwrap();
+        block.tick();
+        assert_eq!(block.population(), 0);
+
+        // the same seed gives the same board
+        let noisy = |seed| {
+            let mut life = LifeState::new(100, 100);
+            let noise = format!("flip=0.1,seed={}", seed);
+            life.set_noise(noise.parse().unwrap()).unwrap();
+            life.tick();
+            life
+        };
+        assert_eq!(noisy(1), noisy(1));
+        assert_ne!(noisy(1), noisy(2));
+    }
+
+    #[test]
+    fn setter() {
+        let mut life = LifeState::new(5, 5);
+        life.set(2, 2);
+        assert!(life.is_alive(2, 2));
+        assert!(!life.is_alive(1, 2));
+    }
+
+    #[test]
+    fn von_neumann() {
+        let mut life = LifeState::from("...\n.X.\n...");
+        life.rule = "B1/S0V".parse().unwrap();
+        life.tick();
+        let mut expected = LifeState::from(".X.\nXXX\n.X.");
+        expected.rule = life.rule.clone();
+        assert_eq!(life, expected);
+    }
+
+    #[test]
+    fn hexagonal() {
+        let mut life = LifeState::from("...\n.X.\n...");
+        life.rule = "B1/S0H".parse().unwrap();
+        life.tick();
+        let mut expected = LifeState::from("XX.\nXXX\n.XX");
+        expected.rule = life.rule.clone();
+        assert_eq!(life, expected);
+    }
+
+    #[test]
+    fn rule_s2() {
+        // ...
+        // XXX
+        // ...
+        let init_life = LifeState::from("...\nXXX\n...");
+        let mut life = init_life.clone();
+
+        // .X.
+        // .X.
+        // .X.
+        let life2 = LifeState::from(".X.\n.X.\n.X.");
+        assert_ne!(life, life2);
+        life.tick();
+        println!("{}", life);
+
+        assert!(life.is_alive(1, 1));
+        assert_eq!(life, life2);
+
+        // with next iteration the life should return to previous state
+        life.tick();
+        assert_eq!(life, init_life);
+    }
+
+    #[test]
+    fn rule_s3() {
+        // ...
+        // XXX
+        // X..
+        let mut life = LifeState::from("...\nXXX\nX..");
+
+        // .X.
+        // XX.
+        // X..
+        let life2 = LifeState::from(".X.\nXX.\nX..");
+        life.tick();
+        assert!(life.is_alive(1, 1));
+        assert_eq!(life, life2);
+
+        // XX.
+        // XX.
+        // XX.
+        let life3 = LifeState::from("XX.\nXX.\nXX.");
+        life.tick();
+        assert!(life.is_alive(1, 1));
+        assert_eq!(life, life3);
+    }
+
+    #[test]
+    fn rule_b3() {
+        // X..
+        // ...
+        // X.X
+        let mut life = LifeState::from("X..\n...\nX.X");
+
+        // ...
+        // .X.
+        // ...
+        let life2 = LifeState::from("...\n.X.\n...");
+        life.tick();
+        assert!(life.is_alive(1, 1));
+        assert_eq!(life, life2);
+    }
+
+    /// Tests that the results outside of ruleset work
+    /// L0, L1, L4, L5, L6, L7, L8 -> D
+    /// D0-2, D4-8 -> D
+    #[test]
+    fn no_rule() {
+        // L0 -> D
+        // ...
+        // .X.
+        // ...
+        let mut life = LifeState::from("...\n.X.\n...");
+        life.tick();
+        assert!(!life.is_alive(1, 1));
+
+        // L1 -> D
+        // X..
+        // .X.
+        // ...
+        let mut life = LifeState::from("X..\n.X.\n...");
+        life.tick();
+        assert!(!life.is_alive(1, 1));
+
+        // L4 -> D
+        // XXX
+        // XX.
+        // ...
+        let mut life = LifeState::from("XXX\nXX.\n...");
+        life.tick();
+        assert!(!life.is_alive(1, 1));
+
+        // L5 -> D
+        // XXX
+        // XXX
+        // ...
+        let mut life = LifeState::from("XXX\nXXX\n...");
+        life.tick();
+        assert!(!life.is_alive(1, 1));
+
+        // L6 -> D
+        // XXX
+        // XXX
+        // X..
+        let mut life = LifeState::from("XXX\nXXX\nX..");
+        life.tick();
+        assert!(!life.is_alive(1, 1));
+
+        // L7 -> D
+        // XXX
+        // XXX
+        // XX.
+        let mut life = LifeState::from("XXX\nXXX\nXX.");
+        life.tick();
+        assert!(!life.is_alive(1, 1));
+
+        // L8 -> D
+        // XXX
+        // XXX
+        // XXX
+        let mut life = LifeState::from("XXX\nXXX\nXXX");
+        life.tick();
+        assert!(!life.is_alive(1, 1));
+
+        // Dead stays dead loop
+        // ...
+        // ...
+        // ...
+        let mut init_state = LifeState::from("...\n...\n...");
+
+        for i in 0..9 {
+            let x = i % 3;
+            let y = i / 3;
+            if !(x == 1 && y == 1) {
+                init_state.set(x, y);
+            }
+            println!("{}", init_state);
+            let mut life = init_state.clone();
+            life.tick();
+            // the cell should remain dead if i != 2 aka neighbours_count is != 3
+            if i != 2 {
+                assert!(!life.is_alive(1, 1));
+            } else {
+                // we can check the rule here, why not
+                assert!(life.is_alive(1, 1));
+            }
+        }
+    }
+
+    #[test]
+    fn lut_matches_life() {
+        // wider than one cluster, so the adjacent columns are involved
+        let (width, height) = (150, 40);
+        let mut life = LifeState::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                if (x * 7 + y * 13 + x * y) % 5 < 2 {
+                    life.set(x, y);
+                }
+            }
+        }
+        let mut lut = life.clone();
+        for _ in 0..10 {
+            life.tick_life();
+            lut.tick_lut();
+            for y in 0..height {
+                for x in 0..width {
+                    assert_eq!(life.is_alive(x, y), lut.is_alive(x, y), "{} {}", x, y);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hensel_rule() {
+        // X..
+        // X..
+        // ...
+        let mut life = LifeState::from("X..\nX..\n...");
+        life.rule = "B2/S".parse().unwrap();
+        life.tick();
+        assert!(life.is_alive(1, 0));
+        assert!(life.is_alive(1, 1));
+
+        // B2-a doesn't give birth to cells seeing a corner with adjacent edge
+        let mut life = LifeState::from("X..\nX..\n...");
+        life.rule = "B2-a/S".parse().unwrap();
+        let mut expected = LifeState::from("...\n...\n...");
+        expected.rule = life.rule.clone();
+        life.tick();
+        assert_eq!(life, expected);
+
+        // 2i above and 2c below the middle are still born
+        let mut life = LifeState::from("X.X\n...\n...");
+        life.rule = "B2-a/S".parse().unwrap();
+        life.tick();
+        assert!(life.is_alive(1, 0));
+        assert!(life.is_alive(1, 1));
+    }
+
+    #[test]
+    fn macrocell() {
+        let mut life =
+            LifeState::from_macrocell("[M2]\n#R B36/S23\n.*$..*$***$\n4 0 0 0 1\n").unwrap();
+        assert_eq!(life.rule, "B36/S23".parse().unwrap());
+        assert_eq!(life.to_string(), ".X.\n..X\nXXX\n");
+        life.tick();
+        assert_eq!(
+            LifeState::from_macrocell(&life.to_macrocell()).unwrap(),
+            life
+        );
+
+        // wider than a cluster
+        let mut life = LifeState::new(130, 3);
+        life.set(0, 1);
+        life.set(64, 0);
+        life.set(129, 2);
+        assert_eq!(
+            LifeState::from_macrocell(&life.to_macrocell()).unwrap(),
+            life
+        );
+    }
+
+    #[test]
+    fn torus() {
+        // the glider wraps around and is back after 4 generations per cell,
+        // across the clusters of the wide boards too
+        for size in [5, 62, 70] {
+            let mut life = LifeState::new(size, size);
+            life.topology = Topology::Torus;
+            for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+                life.set(x, y);
+            }
+            let start = life.clone();
+            for _ in 0..4 * size {
+                life.tick();
+            }
+            assert_eq!(life, start, "{}", size);
+        }
+    }
+
+    #[test]
+    fn torus_clusters() {
+        // the adders against the lookup table, across the cluster edges and
+        // on boards of a single row or column
+        let mut seed: u64 = 3;
+        for (width, height) in [
+            (1, 1),
+            (1, 5),
+            (3, 1),
+            (61, 4),
+            (62, 3),
+            (63, 3),
+            (124, 4),
+            (130, 7),
+        ] {
+            let mut life = LifeState::new(width, height);
+            life.topology = Topology::Torus;
+            for y in 0..height {
+                for x in 0..width {
+                    seed = seed
+                        .wrapping_mul(6364136223846793005)
+                        .wrapping_add(1442695040888963407);
+                    if seed >> 62 < 2 {
+                        life.set(x, y);
+                    }
+                }
+            }
+            let mut lut = life.clone();
+            for _ in 0..4 {
+                life.tick_life();
+                lut.tick_lut();
+                for y in 0..height {
+                    for x in 0..width {
+                        assert_eq!(
+                            life.is_alive(x, y),
+                            lut.is_alive(x, y),
+                            "{}x{}",
+                            width,
+                            height
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn torus_matches_simple_rule() {
+        // each cell against its wrapped neighbours counted one by one
+        let mut life = LifeState::new(70, 6);
+        life.topology = Topology::Torus;
+        let mut seed: u64 = 7;
+        for y in 0..6 {
+            for x in 0..70 {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                if seed >> 62 == 0 {
+                    life.set(x, y);
+                }
+            }
+        }
+        let last = life.clone();
+        life.tick();
+        for y in 0..6 {
+            for x in 0..70 {
+                let mut count = 0;
+                for (dx, dy) in gol::rule::MOORE {
+                    let nx = (x as i64 + dx).rem_euclid(70) as usize;
+                    let ny = (y as i64 + dy).rem_euclid(6) as usize;
+                    count += last.is_alive(nx, ny) as usize;
+                }
+                let alive = count == 3 || (count == 2 && last.is_alive(x, y));
+                assert_eq!(life.is_alive(x, y), alive, "{} {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn checkpoint() {
+        let mut life = LifeState::from(
+            "..........\n...XX.....\n..XX......\n...X......\n..........\n.......X..\n",
+        );
+        life.topology = Topology::Torus;
+        for _ in 0..7 {
+            life.tick();
+        }
+        let cells = life.save_cells();
+        assert_eq!(cells.len(), 6 * 8);
+        let mut resumed = LifeState::new(10, 6);
+        resumed.topology = Topology::Torus;
+        resumed.load_cells(&cells).unwrap();
+        assert_eq!(resumed, life);
+        for _ in 0..20 {
+            life.tick();
+            resumed.tick();
+        }
+        assert_eq!(resumed, life);
+        assert!(resumed.load_cells(&cells[1..]).is_err());
+    }
+
+    #[test]
+    fn population() {
+        // wider than a cluster, with cells in the last columns of both
+        let mut life = LifeState::new(130, 5);
+        for x in [0, 1, 2, 60, 61, 62, 127, 128, 129] {
+            life.set(x, 2);
+        }
+        for _ in 0..3 {
+            let count = (0..5)
+                .map(|y| (0..130).filter(|&x| life.is_alive(x, y)).count())
+                .sum::<usize>();
+            assert_eq!(Engine::population(&life), count);
+            let grid = life.to_bitgrid();
+            assert_eq!(grid.population(), count);
+            assert!((0..5).all(|y| (0..130).all(|x| grid.get(x, y) == life.is_alive(x, y))));
+            life.tick();
+        }
+    }
+
+    #[test]
+    fn population_tail() {
+        // a vertical blinker in the last column, the tick sets bits past it
+        for width in [3, 65] {
+            let mut life = LifeState::new(width, 3);
+            (0..3).for_each(|y| life.set(width - 1, y));
+            life.tick();
+            assert_eq!(Engine::population(&life), 2, "{}", width);
+            assert_eq!(life.to_bitgrid().population(), 2);
+            let mut resumed = LifeState::new(width, 3);
+            resumed.load_cells(&life.save_cells()).unwrap();
+            assert_eq!(Engine::population(&resumed), 2);
+        }
+    }
+
+    #[test]
+    fn plaintext_obstacles() {
+        let mut life = LifeState::from_plaintext(".X.@\n.X.#\n.X..\n").unwrap();
+        life.step();
+        assert_eq!(life.to_pattern().to_plaintext(), "..X@\nXX.#\n....\n");
+        assert!(LifeState::from_plaintext("..\n...\n").is_err());
+        assert!(LifeState::from_plaintext(".2\n").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Generates `life.h` in `OUT_DIR` from the `#[no_mangle]` functions, the
+//! `i32` constants and the opaque structs of `src/lib.rs`, with their doc
+//! comments up to the `# Safety` section. The build leaves the source tree
+//! alone, `make capi-header` copies the header to `include/life.h` and the
+//! tests check that the copy is up to date.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// The C type of a Rust type of the API.
+fn c_type(rust: &str) -> String {
+    let rust = rust.trim();
+    if let Some(pointee) = rust.strip_prefix("*const ") {
+        return format!("const {} *", c_type(pointee));
+    }
+    if let Some(pointee) = rust.strip_prefix("*mut ") {
+        return format!("{} *", c_type(pointee));
+    }
+    match rust {
+        "" | "()" => "void",
+        "usize" => "size_t",
+        "u8" => "uint8_t",
+        "i32" => "int32_t",
+        "u64" => "uint64_t",
+        "c_char" => "char",
+        name => name,
+    }
+    .to_string()
+}
+
+/// Declares the function from its signature, up to the `{` of its body.
+fn declaration(signature: &str) -> String {
+    let (head, rest) = signature.split_once('(').expect("no parameters");
+    let name = head.rsplit(' ').next().unwrap();
+    let (params, ret) = rest.split_once(')').expect("no parameters");
+    let ret = ret.trim_end_matches('{').trim();
+    let ret = c_type(ret.strip_prefix("->").unwrap_or(""));
+    let params: Vec<String> = params
+        .split(',')
+        .filter(|p| !p.trim().is_empty())
+        .map(|p| {
+            let (name, ty) = p.split_once(':').expect("parameter without type");
+            format!("{}{}", with_space(c_type(ty)), name.trim())
+        })
+        .collect();
+    let params = match params.is_empty() {
+        true => "void".to_string(),
+        false => params.join(", "),
+    };
+    format!("{}{}({});", with_space(ret), name, params)
+}
+
+/// Pointer types go right before the name, the others need a space.
+fn with_space(ty: String) -> String {
+    if ty.ends_with('*') {
+        ty
+    } else {
+        ty + " "
+    }
+}
+
+fn comment(doc: &[String]) -> String {
+    let mut ret = String::from("/*\n");
+    for line in doc {
+        ret += &format!(" *{}{}\n", if line.is_empty() { "" } else { " " }, line);
+    }
+    ret + " */\n"
+}
+
+fn header(source: &str) -> String {
+    let mut items = Vec::new();
+    let mut doc: Vec<String> = Vec::new();
+    let mut in_safety = false;
+    let mut lines = source.lines().map(str::trim);
+    while let Some(line) = lines.next() {
+        if let Some(text) = line.strip_prefix("///") {
+            let text = text.strip_prefix(' ').unwrap_or(text);
+            in_safety |= text.starts_with("# Safety");
+            if !in_safety {
+                doc.push(text.to_string());
+            }
+            continue;
+        }
+        if line.starts_with("#[") {
+            continue;
+        }
+        while doc.last().is_some_and(|l| l.is_empty()) {
+            doc.pop();
+        }
+        let item = if let Some(constant) = line.strip_prefix("pub const ") {
+            constant
+                .split_once(": i32 = ")
+                .map(|(name, value)| format!("#define {} {}", name, value.trim_end_matches(';')))
+        } else if let Some(name) = line.strip_prefix("pub struct ") {
+            let name = name.trim_end_matches(" {");
+            Some(format!("typedef struct {} {};", name, name))
+        } else if line.contains("extern \"C\" fn ") {
+            let mut signature = line.to_string();
+            while !signature.ends_with('{') {
+                signature += lines.next().expect("no function body");
+            }
+            Some(declaration(&signature))
+        } else {
+            None
+        };
+        if let Some(item) = item {
+            items.push(comment(&doc) + &item + "\n");
+        }
+        doc.clear();
+        in_safety = false;
+    }
+    format!(
+        "/* Generated by capi/build.rs from capi/src/lib.rs, don't edit. */\n\n\
+         #ifndef LIFE_H\n#define LIFE_H\n\n\
+         #include <stddef.h>\n#include <stdint.h>\n\n\
+         #ifdef __cplusplus\nextern \"C\" {{\n#endif\n\n\
+         {}\n\
+         #ifdef __cplusplus\n}}\n#endif\n\n\
+         #endif\n",
+        items.join("\n")
+    )
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    let source = fs::read_to_string("src/lib.rs").expect("couldn't read src/lib.rs");
+    let out = env::var("OUT_DIR").expect("no OUT_DIR");
+    fs::write(Path::new(&out).join("life.h"), header(&source)).expect("couldn't write life.h");
+}
@@ -0,0 +1,88 @@
+/* Generated by capi/build.rs from capi/src/lib.rs, don't edit. */
+
+#ifndef LIFE_H
+#define LIFE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/*
+ * Cells beyond the edges of the board are dead.
+ */
+#define LIFE_BOUNDED 0
+
+/*
+ * The board wraps around at its edges.
+ */
+#define LIFE_TORUS 1
+
+/*
+ * A board with its engine, only used through pointers.
+ */
+typedef struct LifeBoard LifeBoard;
+
+/*
+ * Creates a board of width by height cells from cells, a byte per cell
+ * row by row with 0 for dead, or an empty board if cells is NULL. rule and
+ * engine are as for `life run`, NULL for B3/S23 and the fastest engine for
+ * the rule, topology is LIFE_BOUNDED or LIFE_TORUS. Returns NULL on error.
+ */
+LifeBoard *life_new(size_t width, size_t height, const uint8_t *cells, const char *rule, const char *engine, int32_t topology);
+
+/*
+ * Frees the board, NULL is ignored.
+ */
+void life_free(LifeBoard *board);
+
+/*
+ * Width of the board, 0 for NULL.
+ */
+size_t life_width(const LifeBoard *board);
+
+/*
+ * Height of the board, 0 for NULL.
+ */
+size_t life_height(const LifeBoard *board);
+
+/*
+ * Number of cells which aren't dead, 0 for NULL.
+ */
+size_t life_population(const LifeBoard *board);
+
+/*
+ * Returns the state of the cell, or -1 if it is outside the board.
+ */
+int32_t life_get(const LifeBoard *board, size_t x, size_t y);
+
+/*
+ * Sets the state of the cell, which has to be lower than the number of
+ * states of the rule.
+ */
+int32_t life_set(LifeBoard *board, size_t x, size_t y, uint8_t state);
+
+/*
+ * Steps the board n generations.
+ */
+int32_t life_step(LifeBoard *board, uint64_t n);
+
+/*
+ * Copies the cells to buffer, a byte per cell row by row like for
+ * life_new. len is the size of buffer, at least width * height.
+ */
+int32_t life_read(const LifeBoard *board, uint8_t *buffer, size_t len);
+
+/*
+ * The message of the last error on this thread, empty if there was none.
+ * It is valid until the next call which fails.
+ */
+const char *life_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
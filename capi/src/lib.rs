@@ -0,0 +1,278 @@
+//! The engines behind a C API, for callers in C, or Python through ctypes,
+//! which can't link Rust. `build.rs` generates `include/life.h` from the
+//! declarations in this file, their doc comments up to `# Safety` included.
+//!
+//! Boards are handles made by `life_new` and freed by `life_free`. The
+//! functions returning `int32_t` return 0, or a cell state, on success and
+//! -1 on error, `life_last_error` has the message.
+
+use gol::{Engine, Pattern, Topology};
+use life::engines;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::ptr;
+use std::slice;
+
+/// Cells beyond the edges of the board are dead.
+pub const LIFE_BOUNDED: i32 = 0;
+
+/// The board wraps around at its edges.
+pub const LIFE_TORUS: i32 = 1;
+
+/// A board with its engine, only used through pointers.
+pub struct LifeBoard {
+    life: Box<dyn Engine + Send>,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+/// Records the error for `life_last_error` and returns `ret`.
+fn fail<T>(e: &str, ret: T) -> T {
+    // none of the messages contain a NUL
+    let e = CString::new(e).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = e);
+    ret
+}
+
+/// Reads a string argument, NULL is `None`.
+unsafe fn string<'a>(s: *const c_char) -> Result<Option<&'a str>, String> {
+    if s.is_null() {
+        return Ok(None);
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Ok(Some(s)),
+        Err(_) => Err("Error: The string isn't UTF-8.".to_string()),
+    }
+}
+
+unsafe fn board<'a>(board: *const LifeBoard) -> Result<&'a dyn Engine, String> {
+    match board.as_ref() {
+        Some(board) => Ok(&*board.life),
+        None => Err("Error: The board is NULL.".to_string()),
+    }
+}
+
+unsafe fn board_mut<'a>(board: *mut LifeBoard) -> Result<&'a mut dyn Engine, String> {
+    match board.as_mut() {
+        Some(board) => Ok(&mut *board.life),
+        None => Err("Error: The board is NULL.".to_string()),
+    }
+}
+
+/// Turns the result into 0 or -1.
+fn status(result: Result<(), String>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => fail(&e, -1),
+    }
+}
+
+unsafe fn new(
+    width: usize,
+    height: usize,
+    cells: *const u8,
+    rule: *const c_char,
+    engine: *const c_char,
+    topology: i32,
+) -> Result<LifeBoard, String> {
+    let topology = match topology {
+        LIFE_BOUNDED => Topology::Bounded,
+        LIFE_TORUS => Topology::Torus,
+        _ => return Err("Error: Unknown topology, expected bounded or torus.".to_string()),
+    };
+    let mut pattern = Pattern::try_new(width, height)?;
+    if !cells.is_null() {
+        let len = pattern.cells.len();
+        pattern
+            .cells
+            .copy_from_slice(slice::from_raw_parts(cells, len));
+    }
+    let life = engines::build(string(engine)?, &pattern, string(rule)?, topology)?;
+    Ok(LifeBoard { life })
+}
+
+/// Creates a board of width by height cells from cells, a byte per cell
+/// row by row with 0 for dead, or an empty board if cells is NULL. rule and
+/// engine are as for `life run`, NULL for B3/S23 and the fastest engine for
+/// the rule, topology is LIFE_BOUNDED or LIFE_TORUS. Returns NULL on error.
+///
+/// # Safety
+/// `cells` is NULL or points to `width * height` bytes, the strings are
+/// NULL or end with a NUL.
+#[no_mangle]
+pub unsafe extern "C" fn life_new(
+    width: usize,
+    height: usize,
+    cells: *const u8,
+    rule: *const c_char,
+    engine: *const c_char,
+    topology: i32,
+) -> *mut LifeBoard {
+    match new(width, height, cells, rule, engine, topology) {
+        Ok(board) => Box::into_raw(Box::new(board)),
+        Err(e) => fail(&e, ptr::null_mut()),
+    }
+}
+
+/// Frees the board, NULL is ignored.
+///
+/// # Safety
+/// `board` is NULL or was returned by `life_new` and not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn life_free(board: *mut LifeBoard) {
+    if !board.is_null() {
+        drop(Box::from_raw(board));
+    }
+}
+
+/// Width of the board, 0 for NULL.
+///
+/// # Safety
+/// `board` is NULL or a live board.
+#[no_mangle]
+pub unsafe extern "C" fn life_width(board: *const LifeBoard) -> usize {
+    self::board(board).map_or(0, |life| life.width())
+}
+
+/// Height of the board, 0 for NULL.
+///
+/// # Safety
+/// `board` is NULL or a live board.
+#[no_mangle]
+pub unsafe extern "C" fn life_height(board: *const LifeBoard) -> usize {
+    self::board(board).map_or(0, |life| life.height())
+}
+
+/// Number of cells which aren't dead, 0 for NULL.
+///
+/// # Safety
+/// `board` is NULL or a live board.
+#[no_mangle]
+pub unsafe extern "C" fn life_population(board: *const LifeBoard) -> usize {
+    self::board(board).map_or(0, |life| life.population())
+}
+
+/// Returns the state of the cell, or -1 if it is outside the board.
+///
+/// # Safety
+/// `board` is NULL or a live board.
+#[no_mangle]
+pub unsafe extern "C" fn life_get(board: *const LifeBoard, x: usize, y: usize) -> i32 {
+    let result = self::board(board).and_then(|life| {
+        if x < life.width() && y < life.height() {
+            Ok(life.get(x, y) as i32)
+        } else {
+            Err(format!("Error: Cell {} {} is outside the board.", x, y))
+        }
+    });
+    result.unwrap_or_else(|e| fail(&e, -1))
+}
+
+/// Sets the state of the cell, which has to be lower than the number of
+/// states of the rule.
+///
+/// # Safety
+/// `board` is NULL or a live board.
+#[no_mangle]
+pub unsafe extern "C" fn life_set(board: *mut LifeBoard, x: usize, y: usize, state: u8) -> i32 {
+    status(board_mut(board).and_then(|life| {
+        if x >= life.width() || y >= life.height() {
+            return Err(format!("Error: Cell {} {} is outside the board.", x, y));
+        }
+        if state as usize >= life.states() {
+            return Err("Error: Cell state is not valid for the rule.".to_string());
+        }
+        life.set(x, y, state);
+        Ok(())
+    }))
+}
+
+/// Steps the board n generations.
+///
+/// # Safety
+/// `board` is NULL or a live board.
+#[no_mangle]
+pub unsafe extern "C" fn life_step(board: *mut LifeBoard, n: u64) -> i32 {
+    status(board_mut(board).map(|life| {
+        for _ in 0..n {
+            life.step();
+        }
+    }))
+}
+
+/// Copies the cells to buffer, a byte per cell row by row like for
+/// life_new. len is the size of buffer, at least width * height.
+///
+/// # Safety
+/// `board` is NULL or a live board, `buffer` points to `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn life_read(board: *const LifeBoard, buffer: *mut u8, len: usize) -> i32 {
+    status(self::board(board).and_then(|life| {
+        let (width, height) = (life.width(), life.height());
+        if buffer.is_null() || len < width * height {
+            return Err("Error: The buffer is smaller than the board.".to_string());
+        }
+        let buffer = slice::from_raw_parts_mut(buffer, width * height);
+        for (i, cell) in buffer.iter_mut().enumerate() {
+            *cell = life.get(i % width, i / width);
+        }
+        Ok(())
+    }))
+}
+
+/// The message of the last error on this thread, empty if there was none.
+/// It is valid until the next call which fails.
+#[no_mangle]
+pub extern "C" fn life_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(life_last_error()) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn board() {
+        let blinker = [0, 1, 0, 0, 1, 0, 0, 1, 0u8];
+        unsafe {
+            let board = life_new(3, 3, blinker.as_ptr(), ptr::null(), ptr::null(), 0);
+            assert_eq!((life_width(board), life_height(board)), (3, 3));
+            assert_eq!(life_step(board, 1), 0);
+            assert_eq!(life_population(board), 3);
+            let mut buffer = [0; 9];
+            assert_eq!(life_read(board, buffer.as_mut_ptr(), 8), -1);
+            assert_eq!(life_read(board, buffer.as_mut_ptr(), 9), 0);
+            assert_eq!(buffer, [0, 0, 0, 1, 1, 1, 0, 0, 0]);
+            assert_eq!(life_set(board, 1, 0, 1), 0);
+            assert_eq!(life_get(board, 1, 0), 1);
+            assert_eq!(life_set(board, 0, 0, 2), -1);
+            assert_eq!(last_error(), "Error: Cell state is not valid for the rule.");
+            assert_eq!(life_get(board, 3, 0), -1);
+            life_free(board);
+
+            let rule = CString::new("B3/S23/C5").unwrap();
+            let engine = CString::new("bitboards").unwrap();
+            let board = life_new(3, 3, ptr::null(), rule.as_ptr(), engine.as_ptr(), 0);
+            assert!(board.is_null());
+            assert!(last_error().starts_with("Error: "));
+            let board = life_new(3, 3, blinker.as_ptr(), ptr::null(), ptr::null(), 2);
+            assert!(board.is_null());
+            for (width, height) in [(usize::MAX, 1), (1 << 20, 1 << 20), (usize::MAX, 2)] {
+                let board = life_new(width, height, ptr::null(), ptr::null(), ptr::null(), 0);
+                assert!(board.is_null());
+                assert_eq!(last_error(), "Error: The board is too large.");
+            }
+            assert_eq!(life_step(ptr::null_mut(), 1), -1);
+            assert_eq!(last_error(), "Error: The board is NULL.");
+            life_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Compiles `tests/life_test.c` against the generated header and the shared
+//! library, and runs it, and checks the header checked in under `include/`.
+
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn c_program() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // the shared library is built next to the test
+    let exe = env::current_exe().unwrap();
+    let lib = exe.parent().unwrap();
+    let program = Path::new(env!("CARGO_TARGET_TMPDIR")).join("life_test");
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let compiled = Command::new(&cc)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror"])
+        .arg("-I")
+        .arg(env!("OUT_DIR"))
+        .arg(dir.join("tests/life_test.c"))
+        .arg("-L")
+        .arg(lib)
+        .arg(format!("-Wl,-rpath,{}", lib.display()))
+        .arg("-lcapi")
+        .arg("-o")
+        .arg(&program)
+        .status();
+    match compiled {
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            eprintln!("No C compiler {}, skipping the C test.", cc);
+            return;
+        }
+        compiled => assert!(compiled.unwrap().success(), "{} failed", cc),
+    }
+    let status = Command::new(&program).status().unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn header() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let generated = include_str!(concat!(env!("OUT_DIR"), "/life.h"));
+    let checked_in = fs::read_to_string(dir.join("include/life.h")).unwrap();
+    assert!(
+        checked_in == generated,
+        "include/life.h is out of date, run make capi-header"
+    );
+}
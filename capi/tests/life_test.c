@@ -0,0 +1,62 @@
+/* Steps a glider through the C API, run by tests/c_api.rs. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "life.h"
+
+static int failures = 0;
+
+#define CHECK(condition)                                                    \
+    do {                                                                    \
+        if (!(condition)) {                                                 \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #condition); \
+            failures++;                                                     \
+        }                                                                   \
+    } while (0)
+
+int main(void) {
+    uint8_t cells[36] = {0};
+    uint8_t glider[5][2] = {{1, 0}, {2, 1}, {0, 2}, {1, 2}, {2, 2}};
+    for (int i = 0; i < 5; i++) {
+        cells[glider[i][1] * 6 + glider[i][0]] = 1;
+    }
+
+    LifeBoard *board = life_new(6, 6, cells, "B3/S23", NULL, LIFE_TORUS);
+    CHECK(board != NULL);
+    CHECK(life_width(board) == 6 && life_height(board) == 6);
+
+    /* after a full period of the torus it is back where it started */
+    CHECK(life_step(board, 24) == 0);
+    uint8_t read[36];
+    CHECK(life_read(board, read, sizeof read) == 0);
+    CHECK(memcmp(read, cells, sizeof cells) == 0);
+    CHECK(life_step(board, 4) == 0);
+    CHECK(life_get(board, 3, 3) == 1 && life_get(board, 1, 0) == 0);
+    CHECK(life_population(board) == 5);
+
+    CHECK(life_set(board, 5, 5, 1) == 0);
+    CHECK(life_get(board, 5, 5) == 1);
+    CHECK(life_set(board, 6, 0, 1) == -1);
+    CHECK(strstr(life_last_error(), "outside") != NULL);
+    CHECK(life_read(board, read, 35) == -1);
+    life_free(board);
+
+    /* a Generations rule has a dying state */
+    board = life_new(4, 1, (const uint8_t *)"\1\0\1\2", "B2/S/C3", "generations", LIFE_BOUNDED);
+    CHECK(board != NULL);
+    CHECK(life_step(board, 1) == 0);
+    CHECK(life_read(board, read, 4) == 0);
+    CHECK(memcmp(read, "\2\1\2\0", 4) == 0);
+    life_free(board);
+
+    CHECK(life_new(3, 3, NULL, "not a rule", NULL, LIFE_BOUNDED) == NULL);
+    CHECK(strlen(life_last_error()) > 0);
+    life_free(NULL);
+
+    if (failures > 0) {
+        fprintf(stderr, "%d checks failed\n", failures);
+        return 1;
+    }
+    return 0;
+}
@@ -0,0 +1,488 @@
+//! Generations rule family (`B/S/C`), e.g. Brian's Brain `B2/S/C3` or
+//! Star Wars `B2/S345/C4`. Cells that fail to survive don't die at once, but
+//! pass through `C - 2` refractory (dying) states first. Only live cells
+//! (state 1) are counted as neighbours.
+
+use gol::activity::Activity;
+use gol::mask::Mask;
+use gol::pattern::Pattern;
+use gol::{Engine, Topology};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Rule {
+    birth: [bool; 9],
+    survival: [bool; 9],
+    /// Number of states, including dead and alive. `2` is a regular life-like rule.
+    states: usize,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule::parse("B3/S23/C2").unwrap()
+    }
+}
+
+impl Rule {
+    /// Parses either `B2/S345/C4` or the Golly/MCell `345/2/4` (S/B/C) notation.
+    /// The `C` part is optional and defaults to 2 states.
+    pub fn parse(s: &str) -> Result<Rule, &'static str> {
+        fn counts(part: &str) -> Result<[bool; 9], &'static str> {
+            let mut ret = [false; 9];
+            for c in part.chars() {
+                match c.to_digit(10) {
+                    Some(n) if n <= 8 => ret[n as usize] = true,
+                    _ => return Err("Error: Invalid neighbour count in rule."),
+                }
+            }
+            Ok(ret)
+        }
+
+        fn states(part: &str) -> Result<usize, &'static str> {
+            match part.parse::<usize>() {
+                Ok(n) if (2..=256).contains(&n) => Ok(n),
+                _ => Err("Error: Number of states has to be between 2 and 256."),
+            }
+        }
+
+        let parts: Vec<&str> = s.trim().split('/').collect();
+        if parts.len() < 2 || parts.len() > 3 {
+            return Err("Error: Expected rule in B/S/C or S/B/C notation.");
+        }
+        let prefixed = parts
+            .iter()
+            .all(|p| p.starts_with(|c: char| c.is_ascii_alphabetic()));
+        let mut rule = Rule {
+            birth: [false; 9],
+            survival: [false; 9],
+            states: 2,
+        };
+        if prefixed {
+            for part in parts {
+                let (prefix, rest) = part.split_at(1);
+                match prefix {
+                    "B" | "b" => rule.birth = counts(rest)?,
+                    "S" | "s" => rule.survival = counts(rest)?,
+                    "C" | "c" | "G" | "g" => rule.states = states(rest)?,
+                    _ => return Err("Error: Unknown rule part, expected B, S or C."),
+                }
+            }
+        } else {
+            rule.survival = counts(parts[0])?;
+            rule.birth = counts(parts[1])?;
+            if let Some(part) = parts.get(2) {
+                rule.states = states(part)?;
+            }
+        }
+        Ok(rule)
+    }
+
+    /// Returns the state a cell in `state` with `count` live neighbours moves to.
+    pub fn apply(&self, state: u8, count: u8) -> u8 {
+        match state {
+            0 if self.birth[count as usize] => 1,
+            0 => 0,
+            1 if self.survival[count as usize] => 1,
+            _ if state as usize + 1 >= self.states => 0,
+            _ => state + 1,
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = |counts: &[bool; 9]| -> String {
+            (0..9)
+                .filter(|&n| counts[n])
+                .map(|n| char::from(b'0' + n as u8))
+                .collect()
+        };
+        write!(
+            f,
+            "B{}/S{}/C{}",
+            digits(&self.birth),
+            digits(&self.survival),
+            self.states
+        )
+    }
+}
+
+/// Double buffered state padded with always dead frame, same as in `opt_02`,
+/// but stored in one `Vec` per buffer. `width` and `height` are the real
+/// dimensions without the frame.
+#[derive(Debug, Clone)]
+pub struct LifeState {
+    width: usize,
+    height: usize,
+    pub rule: Rule,
+    pub topology: Topology,
+    last: Vec<u8>,
+    current: Vec<u8>,
+    activity: Option<Activity>,
+    mask: Option<Mask>,
+}
+
+impl LifeState {
+    pub fn new(width: usize, height: usize, rule: Rule) -> Self {
+        let size = (width + 2) * (height + 2);
+        LifeState {
+            width,
+            height,
+            rule,
+            topology: Topology::Bounded,
+            last: vec![0; size],
+            current: vec![0; size],
+            activity: None,
+            mask: None,
+        }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        (y + 1) * (self.width + 2) + x + 1
+    }
+
+    fn neighbours_count(&self, i: usize) -> u8 {
+        // This is safe, because of the padding introduced
+        let stride = self.width + 2;
+        [
+            i - stride - 1,
+            i - stride,
+            i - stride + 1,
+            i - 1,
+            i + 1,
+            i + stride - 1,
+            i + stride,
+            i + stride + 1,
+        ]
+        .iter()
+        .map(|&n| (self.last[n] == 1) as u8)
+        .sum()
+    }
+
+    /// Fills the frame with the cells beyond the edges, dead or the opposite
+    /// edge of the board on a torus.
+    fn fill_frame(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let stride = width + 2;
+        let torus = self.topology == Topology::Torus;
+        for y in 1..=height {
+            let row = y * stride;
+            self.last[row] = if torus { self.last[row + width] } else { 0 };
+            self.last[row + width + 1] = if torus { self.last[row + 1] } else { 0 };
+        }
+        for x in 0..stride {
+            let (top, bottom) = (x, (height + 1) * stride + x);
+            self.last[top] = if torus { self.last[bottom - stride] } else { 0 };
+            self.last[bottom] = if torus { self.last[top + stride] } else { 0 };
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.fill_frame();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = self.index(x, y);
+                let count = self.neighbours_count(i);
+                self.current[i] = self.rule.apply(self.last[i], count);
+                if let Some(activity) = &mut self.activity {
+                    // counted in the state the mask keeps the cell in
+                    let masked = self.mask.as_ref().and_then(|m| m.get(x, y));
+                    let alive = masked.unwrap_or(self.current[i]) == 1;
+                    activity.update(y * self.width + x, self.last[i] == 1, alive);
+                }
+            }
+        }
+        std::mem::swap(&mut self.last, &mut self.current);
+        self.apply_mask();
+    }
+
+    pub fn from_pattern(pattern: &Pattern, rule: Rule) -> Result<Self, &'static str> {
+        let mut ret = LifeState::new(pattern.width, pattern.height, rule);
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                let state = pattern.get(x, y);
+                if state as usize >= ret.rule.states {
+                    return Err("Error: Cell state is not valid for the rule.");
+                }
+                ret.set(x, y, state);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Reads a plaintext board with the states above 1 as digits and
+    /// letters, see `gol::pattern`, under the default rule. `#` and `@`
+    /// become the walls and fixed cells of its mask.
+    pub fn from_plaintext(s: &str) -> Result<Self, &'static str> {
+        let pattern = Pattern::from_plaintext(s)?;
+        let mut ret = LifeState::new(pattern.width, pattern.height, Rule::default());
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                ret.set(x, y, pattern.get(x, y));
+            }
+        }
+        if let Some(mask) = pattern.mask {
+            ret.set_mask(mask)?;
+        }
+        Ok(ret)
+    }
+
+    /// Parses RLE with the multi-state letters, see `gol::pattern`. The rule
+    /// from the header is used unless it is missing.
+    pub fn from_rle(s: &str) -> Result<Self, &'static str> {
+        let pattern = Pattern::from_rle(s)?;
+        let rule = match &pattern.rule {
+            Some(rule) => Rule::parse(rule)?,
+            None => Rule::default(),
+        };
+        LifeState::from_pattern(&pattern, rule)
+    }
+
+    pub fn to_rle(&self) -> String {
+        self.to_pattern()
+            .to_rle(&self.rule.to_string(), self.rule.states > 2)
+    }
+
+    /// Forces the walls and fixed cells, see `gol::mask`.
+    fn apply_mask(&mut self) {
+        if let Some(mask) = &self.mask {
+            for (x, y, state) in mask.cells() {
+                let i = self.index(x, y);
+                self.last[i] = state;
+            }
+        }
+    }
+
+    /// Parses the macrocell format, see `gol::macrocell`. The rule is taken
+    /// from the header the same way as for RLE.
+    pub fn from_macrocell(s: &str) -> Result<Self, &'static str> {
+        let pattern = Pattern::from_macrocell(s)?;
+        let rule = match &pattern.rule {
+            Some(rule) => Rule::parse(rule)?,
+            None => Rule::default(),
+        };
+        LifeState::from_pattern(&pattern, rule)
+    }
+
+    pub fn to_macrocell(&self) -> String {
+        self.to_pattern()
+            .to_macrocell(&self.rule.to_string(), self.rule.states > 2)
+    }
+}
+
+impl PartialEq for LifeState {
+    fn eq(&self, other: &Self) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.rule == other.rule
+            && self.topology == other.topology
+            && self.last == other.last
+    }
+}
+
+/// Plaintext format extended with the dying states, `.` is dead, `X` is alive
+/// and `2`-`9`, `a`-`z` are the dying states 2 to 35.
+impl Engine for LifeState {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.last[self.index(x, y)]
+    }
+
+    fn set(&mut self, x: usize, y: usize, state: u8) {
+        let i = self.index(x, y);
+        self.last[i] = state;
+    }
+
+    fn step(&mut self) {
+        self.next();
+    }
+
+    fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    fn states(&self) -> usize {
+        self.rule.states
+    }
+
+    fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    fn track_activity(&mut self) -> Result<(), &'static str> {
+        let (width, height) = (self.width, self.height);
+        self.activity = Some(Activity::new(width, height, |x, y| self.get(x, y) == 1));
+        Ok(())
+    }
+
+    fn activity(&self) -> Option<&Activity> {
+        self.activity.as_ref()
+    }
+
+    fn set_mask(&mut self, mask: Mask) -> Result<(), &'static str> {
+        mask.check(self.width(), self.height())?;
+        self.mask = Some(mask);
+        self.apply_mask();
+        Ok(())
+    }
+
+    fn mask(&self) -> Option<&Mask> {
+        self.mask.as_ref()
+    }
+}
+
+impl fmt::Display for LifeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_pattern().to_plaintext())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rule_parse() {
+        let brain = Rule::parse("B2/S/C3").unwrap();
+        assert!(brain.birth[2]);
+        assert!(!brain.birth[3]);
+        assert!(brain.survival.iter().all(|s| !s));
+        assert_eq!(brain.states, 3);
+        assert_eq!(Rule::parse("/2/3").unwrap(), brain);
+
+        let star_wars = Rule::parse("345/2/4").unwrap();
+        assert_eq!(star_wars.to_string(), "B2/S345/C4");
+        assert_eq!(Rule::parse("B2/S345/C4").unwrap(), star_wars);
+
+        assert_eq!(Rule::parse("B3/S23").unwrap(), Rule::default());
+        assert!(Rule::parse("B9/S23/C3").is_err());
+        assert!(Rule::parse("B3/S23/C1").is_err());
+        assert!(Rule::parse("B3").is_err());
+    }
+
+    #[test]
+    fn rule_apply() {
+        let rule = Rule::parse("B2/S3/C4").unwrap();
+        assert_eq!(rule.apply(0, 2), 1); // birth
+        assert_eq!(rule.apply(0, 3), 0);
+        assert_eq!(rule.apply(1, 3), 1); // survival
+        assert_eq!(rule.apply(1, 2), 2); // starts dying
+        assert_eq!(rule.apply(2, 2), 3); // dying ignores neighbours
+        assert_eq!(rule.apply(3, 3), 0); // last dying state
+    }
+
+    #[test]
+    fn text_format() {
+        let input = ".X2\nb9.\n";
+        let life = LifeState::from_plaintext(input).unwrap();
+        assert_eq!(life.get(1, 0), 1);
+        assert_eq!(life.get(2, 0), 2);
+        assert_eq!(life.get(0, 1), 11);
+        assert_eq!(life.get(1, 1), 9);
+        assert_eq!(life.to_string(), input);
+    }
+
+    #[test]
+    fn life_equivalent() {
+        // with two states the engine behaves like B3/S23 life
+        let mut life = LifeState::from_plaintext("...\nXXX\nX..").unwrap();
+        life.next();
+        assert_eq!(life, LifeState::from_plaintext(".X.\nXX.\nX..").unwrap());
+        life.next();
+        assert_eq!(life, LifeState::from_plaintext("XX.\nXX.\nXX.").unwrap());
+    }
+
+    #[test]
+    fn brians_brain() {
+        // ....
+        // .XX.
+        // .22.
+        // ....
+        // moves one cell up each generation
+        let mut life = LifeState::from_plaintext("....\n....\n.XX.\n.22.\n....").unwrap();
+        life.rule = Rule::parse("B2/S/C3").unwrap();
+        life.next();
+        assert_eq!(life.to_string(), "....\n.XX.\n.22.\n....\n....\n");
+    }
+
+    #[test]
+    fn rle_roundtrip() {
+        let rle = "x = 5, y = 3, rule = B2/S345/C4\n.2AB2$\n3.C!\n";
+        let life = LifeState::from_rle(rle).unwrap();
+        assert_eq!(life.rule, Rule::parse("345/2/4").unwrap());
+        assert_eq!(life.to_string(), ".XX2.\n.....\n...3.\n");
+        assert_eq!(
+            life.to_rle(),
+            "x = 5, y = 3, rule = B2/S345/C4\n.2AB2$3.C!\n"
+        );
+        assert_eq!(LifeState::from_rle(&life.to_rle()).unwrap(), life);
+
+        // two state rules use the b/o letters
+        let life = LifeState::from_plaintext(".X\nX.").unwrap();
+        assert_eq!(life.to_rle(), "x = 2, y = 2, rule = B3/S23/C2\nbo$o!\n");
+    }
+
+    #[test]
+    fn rle_multi_letter_states() {
+        let mut life = LifeState::new(3, 1, Rule::parse("B2/S/C256").unwrap());
+        life.set(0, 0, 25);
+        life.set(1, 0, 48);
+        life.set(2, 0, 255);
+        let rle = life.to_rle();
+        assert_eq!(rle, "x = 3, y = 1, rule = B2/S/C256\npApXyO!\n");
+        assert_eq!(LifeState::from_rle(&rle).unwrap(), life);
+    }
+
+    #[test]
+    fn macrocell_roundtrip() {
+        let mut life = LifeState::from_plaintext("....\n.XX.\n.22.\n").unwrap();
+        life.rule = Rule::parse("B2/S/C3").unwrap();
+        let mc = life.to_macrocell();
+        assert!(mc.contains("#R B2/S/C3"));
+        assert_eq!(LifeState::from_macrocell(&mc).unwrap(), life);
+
+        // two states use the 8x8 leaves
+        let life = LifeState::from_plaintext(".X\nX.").unwrap();
+        assert!(life.to_macrocell().contains(".*$*$"));
+        assert_eq!(
+            LifeState::from_macrocell(&life.to_macrocell()).unwrap(),
+            life
+        );
+    }
+
+    #[test]
+    fn rle_errors() {
+        assert!(LifeState::from_rle("x = 2, y = 1\n3o!").is_err());
+        assert!(LifeState::from_rle("x = 2, y = 1, rule = B3/S23\nB!").is_err());
+        assert!(LifeState::from_rle("y = 1\no!").is_err());
+        assert!(LifeState::from_plaintext("..\n...\n").is_err());
+    }
+
+    #[test]
+    fn torus() {
+        // Brian's Brain spaceship leaves at the top and comes back at the bottom
+        let start = "....\n....\n.XX.\n.22.\n....\n";
+        let mut life = LifeState::from_plaintext(start).unwrap();
+        life.rule = Rule::parse("B2/S/C3").unwrap();
+        life.topology = Topology::Torus;
+        for i in 1..=5 {
+            life.next();
+            assert_eq!(life.to_string() == start, i == 5);
+        }
+    }
+
+    #[test]
+    fn plaintext_obstacles() {
+        let mut life = LifeState::from_plaintext(".X.@\n.X.#\n.X..\n").unwrap();
+        life.step();
+        assert_eq!(life.to_pattern().to_plaintext(), "..X@\nXX.#\n....\n");
+        assert!(LifeState::from_plaintext("..\n...\n").is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use generations::{LifeState, Rule};
+use gol::macrocell;
+use gol::pattern::{self, PLAINTEXT_MAX_STATES};
+use gol::Engine;
+use std::env;
+use std::fs;
+
+fn main() -> Result<(), &'static str> {
+    // args check
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 3 && args.len() != 4 {
+        println!("Usage: ./{} initial_state iterations [rule]", &args[0]);
+        return Err("Error: Expected 2 or 3 arguments.");
+    }
+
+    // iterations extraction
+    let iterations = args[2].parse::<usize>();
+    if iterations.is_err() {
+        return Err("Error: Couldn't parse iterations argument. Expected usize.");
+    }
+    // file open
+    let input = match fs::read_to_string(&args[1]) {
+        Ok(s) => s,
+        Err(_) => return Err("Error: Couldn't open file"),
+    };
+    // create init state, the rule argument takes precedence over RLE header
+    let mc = macrocell::is_macrocell(&input);
+    let rle = !mc && pattern::is_rle(&input);
+    let mut life = if mc {
+        LifeState::from_macrocell(&input)?
+    } else if rle {
+        LifeState::from_rle(&input)?
+    } else {
+        LifeState::from_plaintext(&input)?
+    };
+    if let Some(rule) = args.get(3) {
+        life.rule = Rule::parse(rule)?;
+    }
+    if !rle && !mc && life.states() > PLAINTEXT_MAX_STATES {
+        return Err("Error: Plaintext supports at most 36 states, use RLE input.");
+    }
+    // loop
+    for _ in 0..iterations.unwrap() {
+        life.next();
+    }
+    // print result in the same format as the input
+    if mc {
+        print!("{}", life.to_macrocell());
+    } else if rle {
+        print!("{}", life.to_rle());
+    } else {
+        print!("{}", life);
+    }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+//! How long each cell has been alive and how often it changed, counted by
+//! the byte engines during their rule pass once `Engine::track_activity` is
+//! called, and drawn as heat maps.
+//!
+//! A cell is alive in state 1, so the dying states of the Generations rules
+//! count as dead. Both counters saturate at `u16::MAX`.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Which counter a heat map shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    /// Generations the cell has been alive in a row, 0 when dead.
+    Age,
+    /// Births and deaths of the cell since the counting started.
+    Activity,
+}
+
+impl FromStr for Layer {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "age" => Ok(Layer::Age),
+            "activity" => Ok(Layer::Activity),
+            _ => Err("Error: Unknown heat map, expected age or activity."),
+        }
+    }
+}
+
+/// Background colours of the ANSI heat map from cold to hot, dark red to
+/// yellow to white in the 256 colour palette. 0 is black.
+const RAMP: [u8; 12] = [52, 88, 124, 160, 196, 202, 208, 214, 220, 226, 228, 231];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Activity {
+    pub width: usize,
+    pub height: usize,
+    /// Row major like `Pattern::cells`.
+    pub age: Vec<u16>,
+    pub changes: Vec<u16>,
+}
+
+impl Activity {
+    /// Starts counting on the board, the live cells with age 1.
+    pub fn new(width: usize, height: usize, alive: impl Fn(usize, usize) -> bool) -> Self {
+        let age = (0..width * height)
+            .map(|i| alive(i % width, i / width) as u16)
+            .collect();
+        Activity {
+            width,
+            height,
+            age,
+            changes: vec![0; width * height],
+        }
+    }
+
+    /// Counts the step of the cell at index `i` from `was` to `alive`.
+    #[inline]
+    pub fn update(&mut self, i: usize, was: bool, alive: bool) {
+        self.age[i] = if alive {
+            self.age[i].saturating_add(1)
+        } else {
+            0
+        };
+        if was != alive {
+            self.changes[i] = self.changes[i].saturating_add(1);
+        }
+    }
+
+    pub fn layer(&self, layer: Layer) -> &[u16] {
+        match layer {
+            Layer::Age => &self.age,
+            Layer::Activity => &self.changes,
+        }
+    }
+
+    /// The layer as a plain (`P2`) PGM image, scaled so the highest value
+    /// is white.
+    pub fn to_pgm(&self, layer: Layer) -> String {
+        let values = self.layer(layer);
+        let max = values.iter().copied().max().unwrap_or(0).max(1);
+        let mut ret = format!("P2\n{} {}\n{}\n", self.width, self.height, max);
+        for row in values.chunks(self.width.max(1)) {
+            // at most 70 characters a line
+            for line in row.chunks(11) {
+                let line: Vec<String> = line.iter().map(u16::to_string).collect();
+                ret.push_str(&line.join(" "));
+                ret.push('\n');
+            }
+        }
+        ret
+    }
+
+    /// The layer drawn with ANSI 256 colour backgrounds, two spaces a cell,
+    /// black for 0 and then from dark red to white up to the highest value.
+    pub fn to_ansi(&self, layer: Layer) -> String {
+        let values = self.layer(layer);
+        let max = values.iter().copied().max().unwrap_or(0) as usize;
+        let mut ret = String::new();
+        for row in values.chunks(self.width.max(1)) {
+            for &value in row {
+                let colour = match value as usize {
+                    0 => 16,
+                    v => RAMP[(v - 1) * (RAMP.len() - 1) / (max - 1).max(1)],
+                };
+                write!(ret, "\x1b[48;5;{}m  ", colour).unwrap();
+            }
+            ret.push_str("\x1b[0m\n");
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn counters() {
+        let mut activity = Activity::new(3, 1, |x, _| x == 0);
+        assert_eq!(activity.age, [1, 0, 0]);
+        // the first cell stays alive, the second one is born and dies
+        for alive in [true, false, true] {
+            activity.update(0, true, true);
+            activity.update(1, !alive, alive);
+        }
+        assert_eq!(activity.age, [4, 1, 0]);
+        assert_eq!(activity.changes, [0, 3, 0]);
+        activity.age[0] = u16::MAX;
+        activity.update(0, true, true);
+        assert_eq!(activity.age[0], u16::MAX);
+
+        assert_eq!(activity.to_pgm(Layer::Activity), "P2\n3 1\n3\n0 3 0\n");
+        let ansi = activity.to_ansi(Layer::Age);
+        assert_eq!(
+            ansi,
+            "\x1b[48;5;231m  \x1b[48;5;52m  \x1b[48;5;16m  \x1b[0m\n"
+        );
+        assert_eq!("activity".parse(), Ok(Layer::Activity));
+        assert!("heat".parse::<Layer>().is_err());
+    }
+
+    #[test]
+    fn long_rows() {
+        let activity = Activity::new(30, 2, |_, _| true);
+        let pgm = activity.to_pgm(Layer::Age);
+        assert!(pgm.lines().all(|l| l.len() <= 70));
+        assert_eq!(pgm.lines().count(), 3 + 2 * 3);
+    }
+}
@@ -0,0 +1,163 @@
+//! Two state boards packed into rows of `u64` words, for the operations
+//! which look at many cells at once (searching, diffs, masks).
+
+use crate::pattern::Pattern;
+
+/// Cell `x` of a row is bit `x % 64` of its word `x / 64`, the bits past the
+/// width are always dead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitGrid {
+    pub width: usize,
+    pub height: usize,
+    /// Words per row.
+    stride: usize,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    pub fn new(width: usize, height: usize) -> Self {
+        let stride = width.div_ceil(64);
+        BitGrid {
+            width,
+            height,
+            stride,
+            words: vec![0; stride * height],
+        }
+    }
+
+    /// Every state other than dead is alive.
+    pub fn from_pattern(pattern: &Pattern) -> Self {
+        let mut ret = BitGrid::new(pattern.width, pattern.height);
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                if pattern.get(x, y) != 0 {
+                    ret.set(x, y, true);
+                }
+            }
+        }
+        ret
+    }
+
+    pub fn to_pattern(&self) -> Pattern {
+        let mut ret = Pattern::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                ret.set(x, y, self.get(x, y) as u8);
+            }
+        }
+        ret
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        self.words[y * self.stride + x / 64] >> (x % 64) & 1 == 1
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, alive: bool) {
+        let word = &mut self.words[y * self.stride + x / 64];
+        *word &= !(1 << (x % 64));
+        *word |= (alive as u64) << (x % 64);
+    }
+
+    /// The words of row `y`.
+    pub fn row(&self, y: usize) -> &[u64] {
+        &self.words[y * self.stride..(y + 1) * self.stride]
+    }
+
+    pub fn row_mut(&mut self, y: usize) -> &mut [u64] {
+        &mut self.words[y * self.stride..(y + 1) * self.stride]
+    }
+
+    /// Sets the `count` cells of row `y` from `x` on to the lowest bits of
+    /// `bits` which are alive, without clearing the others.
+    pub fn or_bits(&mut self, x: usize, y: usize, bits: u64, count: usize) {
+        let bits = if count < 64 {
+            bits & ((1 << count) - 1)
+        } else {
+            bits
+        };
+        let (i, offset) = (y * self.stride + x / 64, x % 64);
+        self.words[i] |= bits << offset;
+        if offset > 0 && offset + count > 64 {
+            self.words[i + 1] |= bits >> (64 - offset);
+        }
+    }
+
+    /// The 64 cells of row `y` from `x` on as the bits of a word, the cells
+    /// outside the board are dead.
+    pub fn window(&self, x: i64, y: i64) -> u64 {
+        if y < 0 || y >= self.height as i64 || x >= self.width as i64 || x <= -64 {
+            return 0;
+        }
+        let row = self.row(y as usize);
+        let word = |i: i64| -> u64 {
+            if i < 0 || i >= self.stride as i64 {
+                0
+            } else {
+                row[i as usize]
+            }
+        };
+        let (i, offset) = (x.div_euclid(64), x.rem_euclid(64) as u32);
+        if offset == 0 {
+            word(i)
+        } else {
+            word(i) >> offset | word(i + 1) << (64 - offset)
+        }
+    }
+
+    /// The grid with each word combined with the one of `other`, a grid of
+    /// the same size, the bits past the width have to stay dead.
+    pub fn combine(&self, other: &BitGrid, f: impl Fn(u64, u64) -> u64) -> BitGrid {
+        assert_eq!((self.width, self.height), (other.width, other.height));
+        BitGrid {
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            words: self
+                .words
+                .iter()
+                .zip(&other.words)
+                .map(|(&a, &b)| f(a, b))
+                .collect(),
+        }
+    }
+
+    pub fn population(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bits() {
+        let mut grid = BitGrid::new(130, 3);
+        for x in [0, 63, 64, 127, 129] {
+            grid.set(x, 1, true);
+        }
+        assert!(grid.get(63, 1) && grid.get(64, 1) && !grid.get(65, 1));
+        assert_eq!(grid.row(1), &[1 | 1 << 63, 1 | 1 << 63, 0b10]);
+        assert_eq!(grid.window(63, 1), 0b11);
+        assert_eq!(grid.window(64, 1), 1 | 1 << 63);
+        assert_eq!(grid.window(-1, 1), 0b10);
+        assert_eq!(grid.window(-64, 1), 0);
+        assert_eq!(grid.window(127, 1), 0b101);
+        assert_eq!(grid.window(0, 3), 0);
+        assert_eq!(grid.population(), 5);
+
+        grid.or_bits(60, 2, 0xFF, 6);
+        assert_eq!(grid.window(60, 2), 0x3F);
+        assert_eq!(
+            grid.to_pattern().cells.iter().filter(|&&c| c == 1).count(),
+            11
+        );
+        assert_eq!(BitGrid::from_pattern(&grid.to_pattern()), grid);
+        grid.set(0, 1, false);
+        assert!(!grid.get(0, 1));
+
+        let other = grid.combine(&BitGrid::new(130, 3), |a, b| a ^ b);
+        assert_eq!(other, grid);
+        assert_eq!(grid.combine(&other, |a, b| a & !b).population(), 0);
+    }
+}
@@ -0,0 +1,332 @@
+//! Checkpoints of long runs, a small versioned binary format. All numbers are
+//! little endian.
+//!
+//! ```text
+//! magic       8 bytes  "GOLCKPT\0"
+//! version     u16      VERSION
+//! topology    u8       0 bounded, 1 torus
+//! generation  u64
+//! width       u64
+//! height      u64
+//! engine      u16 length + UTF-8 name
+//! rule        u16 length + UTF-8 rule
+//! cells       u64 length + the engine's board, see `Engine::save_cells`
+//! mask        u64 length + the walls and then the fixed cells packed by
+//!             `pack_bits`, 0 without obstacles
+//! table       u64 length + UTF-8 text of the rule table, 0 for the other
+//!             engines
+//! checksum    u32      CRC-32 of everything above
+//! ```
+
+use crate::bitgrid::BitGrid;
+use crate::mask::Mask;
+use crate::topology::Topology;
+
+pub const MAGIC: &[u8; 8] = b"GOLCKPT\0";
+/// Version of the format written, older versions are read too once there
+/// are any.
+pub const VERSION: u16 = 1;
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// The CRC-32 used by zip and PNG.
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut c = !0u32;
+    for &b in data {
+        c = CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub generation: u64,
+    /// Name of the engine which saved the cells, only it can load them.
+    pub engine: String,
+    pub rule: String,
+    pub topology: Topology,
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<u8>,
+    /// The walls and fixed cells, see `gol::mask`.
+    pub mask: Option<Mask>,
+    /// The rule table the `ruletable` engine ran, so the run doesn't depend
+    /// on the `.rule` file `rule` names.
+    pub table: Option<String>,
+}
+
+/// Reads the fields one after another, failing on truncated data.
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], &'static str> {
+        if self.data.len() < n {
+            return Err("Error: Checkpoint is truncated.");
+        }
+        let (ret, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(ret)
+    }
+
+    fn u8(&mut self) -> Result<u8, &'static str> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, &'static str> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, &'static str> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, &'static str> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "Error: Invalid checkpoint text.")
+    }
+}
+
+impl Checkpoint {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut ret = Vec::with_capacity(self.cells.len() + 64);
+        ret.extend_from_slice(MAGIC);
+        ret.extend_from_slice(&VERSION.to_le_bytes());
+        ret.push(match self.topology {
+            Topology::Bounded => 0,
+            Topology::Torus => 1,
+        });
+        ret.extend_from_slice(&self.generation.to_le_bytes());
+        ret.extend_from_slice(&(self.width as u64).to_le_bytes());
+        ret.extend_from_slice(&(self.height as u64).to_le_bytes());
+        for s in [&self.engine, &self.rule] {
+            ret.extend_from_slice(&(s.len() as u16).to_le_bytes());
+            ret.extend_from_slice(s.as_bytes());
+        }
+        ret.extend_from_slice(&(self.cells.len() as u64).to_le_bytes());
+        ret.extend_from_slice(&self.cells);
+        let mask = match &self.mask {
+            Some(mask) => {
+                let cells = |grid: &BitGrid| {
+                    (0..self.width * self.height)
+                        .map(|i| grid.get(i % self.width, i / self.width))
+                        .collect::<Vec<_>>()
+                };
+                pack_bits(cells(&mask.walls).into_iter().chain(cells(&mask.fixed)))
+            }
+            None => Vec::new(),
+        };
+        ret.extend_from_slice(&(mask.len() as u64).to_le_bytes());
+        ret.extend_from_slice(&mask);
+        let table = self.table.as_deref().unwrap_or("");
+        ret.extend_from_slice(&(table.len() as u64).to_le_bytes());
+        ret.extend_from_slice(table.as_bytes());
+        let checksum = crc32(&ret);
+        ret.extend_from_slice(&checksum.to_le_bytes());
+        ret
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, &'static str> {
+        if data.len() < MAGIC.len() + 4 || &data[..MAGIC.len()] != MAGIC {
+            return Err("Error: Not a checkpoint file.");
+        }
+        let (body, checksum) = data.split_at(data.len() - 4);
+        if crc32(body).to_le_bytes() != checksum {
+            return Err("Error: Checkpoint checksum mismatch, the file is damaged.");
+        }
+        let mut r = Reader {
+            data: &body[MAGIC.len()..],
+        };
+        if r.u16()? != VERSION {
+            return Err("Error: Unsupported checkpoint version.");
+        }
+        let topology = match r.u8()? {
+            0 => Topology::Bounded,
+            1 => Topology::Torus,
+            _ => return Err("Error: Invalid checkpoint topology."),
+        };
+        let generation = r.u64()?;
+        let width = r.u64()? as usize;
+        let height = r.u64()? as usize;
+        if width.checked_mul(height).is_none() {
+            return Err("Error: Invalid checkpoint board size.");
+        }
+        let engine = r.string()?;
+        let rule = r.string()?;
+        let len = r.u64()? as usize;
+        let cells = r.take(len)?.to_vec();
+        let mask = match r.u64()? as usize {
+            0 => None,
+            len => Some(read_mask(r.take(len)?, width, height)?),
+        };
+        let table = match r.u64()? as usize {
+            0 => None,
+            len => Some(
+                String::from_utf8(r.take(len)?.to_vec())
+                    .map_err(|_| "Error: Invalid checkpoint text.")?,
+            ),
+        };
+        if !r.data.is_empty() {
+            return Err("Error: Unexpected data after the checkpoint.");
+        }
+        Ok(Checkpoint {
+            generation,
+            engine,
+            rule,
+            topology,
+            width,
+            height,
+            cells,
+            mask,
+            table,
+        })
+    }
+}
+
+fn read_mask(data: &[u8], width: usize, height: usize) -> Result<Mask, &'static str> {
+    let size = width * height;
+    if Some(data.len()) != size.checked_mul(2).map(|bits| bits.div_ceil(8)) {
+        return Err("Error: Checkpoint obstacles don't match the size of the board.");
+    }
+    let mut mask = Mask::new(width, height);
+    for i in 0..size {
+        let (x, y) = (i % width, i / width);
+        mask.walls.set(x, y, unpack_bit(data, i));
+        mask.fixed.set(x, y, unpack_bit(data, size + i));
+    }
+    Ok(mask)
+}
+
+/// Packs two state cells, one bit per cell with the lowest bit first.
+pub fn pack_bits(cells: impl Iterator<Item = bool>) -> Vec<u8> {
+    let mut ret = Vec::new();
+    for (i, cell) in cells.enumerate() {
+        if i % 8 == 0 {
+            ret.push(0);
+        }
+        *ret.last_mut().unwrap() |= (cell as u8) << (i % 8);
+    }
+    ret
+}
+
+/// Returns the `i`-th cell packed by `pack_bits`.
+pub fn unpack_bit(data: &[u8], i: usize) -> bool {
+    data[i / 8] >> (i % 8) & 1 == 1
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn checkpoint() -> Checkpoint {
+        Checkpoint {
+            generation: 1_000_000,
+            engine: "bitboards".to_string(),
+            rule: "B36/S23".to_string(),
+            topology: Topology::Torus,
+            width: 3,
+            height: 2,
+            cells: pack_bits([true, false, true, false, true, true].into_iter()),
+            mask: None,
+            table: None,
+        }
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let bytes = checkpoint().to_bytes();
+        assert_eq!(&bytes[..8], MAGIC);
+        assert_eq!(Checkpoint::from_bytes(&bytes), Ok(checkpoint()));
+        let cells = &checkpoint().cells;
+        assert_eq!(cells, &[0b110101]);
+        assert!(unpack_bit(cells, 0));
+        assert!(!unpack_bit(cells, 3));
+    }
+
+    #[test]
+    fn obstacles() {
+        let mut mask = Mask::new(3, 2);
+        mask.walls.set(1, 0, true);
+        mask.fixed.set(2, 1, true);
+        let with_mask = Checkpoint {
+            mask: Some(mask),
+            ..checkpoint()
+        };
+        let bytes = with_mask.to_bytes();
+        assert_eq!(Checkpoint::from_bytes(&bytes), Ok(with_mask));
+    }
+
+    #[test]
+    fn table() {
+        let with_table = Checkpoint {
+            engine: "ruletable".to_string(),
+            rule: "rules/Test.rule".to_string(),
+            table: Some("@RULE Test\n@TABLE\nn_states:2\n".to_string()),
+            ..checkpoint()
+        };
+        let bytes = with_table.to_bytes();
+        assert_eq!(Checkpoint::from_bytes(&bytes), Ok(with_table));
+    }
+
+    #[test]
+    fn sizes() {
+        // a width and height whose cells overflow, with a valid checksum
+        let mut bytes = checkpoint().to_bytes();
+        bytes.truncate(bytes.len() - 4);
+        bytes[19..27].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        bytes[27..35].copy_from_slice(&(1u64 << 40).to_le_bytes());
+        let checksum = crc32(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        assert_eq!(
+            Checkpoint::from_bytes(&bytes),
+            Err("Error: Invalid checkpoint board size.")
+        );
+    }
+
+    #[test]
+    fn damaged() {
+        let bytes = checkpoint().to_bytes();
+        for i in [0, 9, 12, 40, bytes.len() - 1] {
+            let mut damaged = bytes.clone();
+            damaged[i] ^= 0x10;
+            assert!(Checkpoint::from_bytes(&damaged).is_err(), "{}", i);
+        }
+        assert!(Checkpoint::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Checkpoint::from_bytes(b"GOLCKPT").is_err());
+
+        // a newer version with a valid checksum
+        let mut newer = bytes[..bytes.len() - 4].to_vec();
+        newer[8] = 2;
+        let checksum = crc32(&newer);
+        newer.extend_from_slice(&checksum.to_le_bytes());
+        assert_eq!(
+            Checkpoint::from_bytes(&newer),
+            Err("Error: Unsupported checkpoint version.")
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! What changed between two boards, from the XOR of their words: the changed
+//! cells which are alive afterwards were born, the others died.
+
+use crate::bitgrid::BitGrid;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diff {
+    pub births: BitGrid,
+    pub deaths: BitGrid,
+    /// Cells alive on both boards.
+    pub survivors: BitGrid,
+}
+
+impl Diff {
+    pub fn new(before: &BitGrid, after: &BitGrid) -> Result<Self, &'static str> {
+        if (before.width, before.height) != (after.width, after.height) {
+            return Err("Error: The boards have different sizes.");
+        }
+        let changed = before.combine(after, |a, b| a ^ b);
+        Ok(Diff {
+            births: changed.combine(after, |c, a| c & a),
+            deaths: changed.combine(before, |c, b| c & b),
+            survivors: before.combine(after, |a, b| a & b),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.births.population() == 0 && self.deaths.population() == 0
+    }
+
+    /// `+ x y` for each birth and `- x y` for each death, a line each in the
+    /// order of the rows.
+    pub fn to_list(&self) -> String {
+        let mut ret = String::new();
+        for y in 0..self.births.height {
+            let words = self.births.row(y).iter().zip(self.deaths.row(y));
+            for (i, (&births, &deaths)) in words.enumerate() {
+                let mut changed = births | deaths;
+                while changed != 0 {
+                    let bit = changed.trailing_zeros();
+                    let sign = if births >> bit & 1 == 1 { '+' } else { '-' };
+                    ret.push_str(&format!("{} {} {}\n", sign, i * 64 + bit as usize, y));
+                    changed &= changed - 1;
+                }
+            }
+        }
+        ret
+    }
+
+    /// The board after the change in plaintext with the births as `+` and
+    /// the deaths as `-`.
+    pub fn to_grid(&self) -> String {
+        let (width, height) = (self.births.width, self.births.height);
+        let mut ret = String::with_capacity((width + 1) * height);
+        for y in 0..height {
+            for x in 0..width {
+                ret.push(if self.births.get(x, y) {
+                    '+'
+                } else if self.deaths.get(x, y) {
+                    '-'
+                } else if self.survivors.get(x, y) {
+                    'X'
+                } else {
+                    '.'
+                });
+            }
+            ret.push('\n');
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pattern::Pattern;
+
+    fn grid(s: &str) -> BitGrid {
+        BitGrid::from_pattern(&Pattern::from_plaintext(s).unwrap())
+    }
+
+    #[test]
+    fn blinker() {
+        let diff = Diff::new(&grid("...\nXXX\n...\n"), &grid(".X.\n.X.\n.X.\n")).unwrap();
+        assert_eq!(diff.to_list(), "+ 1 0\n- 0 1\n- 2 1\n+ 1 2\n");
+        assert_eq!(diff.to_grid(), ".+.\n-X-\n.+.\n");
+        assert!(!diff.is_empty());
+        let same = Diff::new(&grid("X.\n"), &grid("X.\n")).unwrap();
+        assert!(same.is_empty());
+        assert_eq!(same.to_list(), "");
+        assert!(Diff::new(&grid("X.\n"), &grid("X\n")).is_err());
+
+        // words past the first one
+        let mut before = BitGrid::new(200, 1);
+        let mut after = before.clone();
+        before.set(150, 0, true);
+        after.set(64, 0, true);
+        assert_eq!(
+            Diff::new(&before, &after).unwrap().to_list(),
+            "+ 64 0\n- 150 0\n"
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! Wolfram's elementary cellular automata, a row of two state cells where
+//! each cell's next state is the bit of the rule (0 to 255) numbered by its
+//! left neighbour, itself and its right neighbour read as a binary number.
+//!
+//! The row is stored 64 cells to a word like the rows of `BitGrid`, and a
+//! step computes the words of the left and right neighbours with shifts and
+//! then the rule for 64 cells at once from its eight neighbourhoods, the way
+//! `bitboards` steps its clusters.
+
+use crate::pattern::Pattern;
+use std::str::FromStr;
+
+/// What lies beyond the ends of the row.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Boundary {
+    /// Dead cells.
+    #[default]
+    Dead,
+    /// Live cells.
+    Alive,
+    /// The other end of the row, a ring.
+    Wrap,
+    /// The end cell itself, as if mirrored there.
+    Reflect,
+}
+
+impl FromStr for Boundary {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dead" => Ok(Boundary::Dead),
+            "alive" => Ok(Boundary::Alive),
+            "wrap" => Ok(Boundary::Wrap),
+            "reflect" => Ok(Boundary::Reflect),
+            _ => Err("Error: Unknown boundary, expected dead, alive, wrap or reflect."),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Elementary {
+    pub rule: u8,
+    pub boundary: Boundary,
+    width: usize,
+    /// Cell `x` is bit `x % 64` of word `x / 64`, the bits beyond the width
+    /// stay clear.
+    words: Vec<u64>,
+}
+
+impl Elementary {
+    /// A dead row.
+    pub fn new(rule: u8, width: usize) -> Self {
+        Elementary {
+            rule,
+            boundary: Boundary::Dead,
+            width,
+            words: vec![0; width.div_ceil(64)],
+        }
+    }
+
+    /// The first row of the pattern, every state but dead counts as alive.
+    pub fn from_pattern(rule: u8, pattern: &Pattern) -> Self {
+        let mut ret = Elementary::new(rule, pattern.width);
+        for x in 0..pattern.width {
+            ret.set(x, pattern.height > 0 && pattern.get(x, 0) != 0);
+        }
+        ret
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get(&self, x: usize) -> bool {
+        self.words[x / 64] >> (x % 64) & 1 == 1
+    }
+
+    pub fn set(&mut self, x: usize, alive: bool) {
+        let word = &mut self.words[x / 64];
+        *word = *word & !(1 << (x % 64)) | (alive as u64) << (x % 64);
+    }
+
+    /// The cells beyond the left and right ends.
+    fn ends(&self) -> (u64, u64) {
+        let (first, last) = (self.get(0) as u64, self.get(self.width - 1) as u64);
+        match self.boundary {
+            Boundary::Dead => (0, 0),
+            Boundary::Alive => (1, 1),
+            Boundary::Wrap => (last, first),
+            Boundary::Reflect => (first, last),
+        }
+    }
+
+    /// computes the next generation.
+    pub fn step(&mut self) {
+        if self.width == 0 {
+            return;
+        }
+        let (left_end, right_end) = self.ends();
+        let words = &self.words;
+        let n = words.len();
+        let tail = (self.width - 1) % 64;
+        let mut next = vec![0; n];
+        for (i, next) in next.iter_mut().enumerate() {
+            let centre = words[i];
+            // the neighbours of each cell moved to its bit
+            let left = centre << 1 | if i > 0 { words[i - 1] >> 63 } else { left_end };
+            let mut right = centre >> 1 | words.get(i + 1).map_or(0, |w| w << 63);
+            if i + 1 == n {
+                right |= right_end << tail;
+            }
+            let mut ret = 0;
+            for neighbourhood in 0..8 {
+                if self.rule >> neighbourhood & 1 == 0 {
+                    continue;
+                }
+                let pick = |word: u64, bit: u8| {
+                    if neighbourhood >> bit & 1 == 1 {
+                        word
+                    } else {
+                        !word
+                    }
+                };
+                ret |= pick(left, 2) & pick(centre, 1) & pick(right, 0);
+            }
+            *next = ret;
+        }
+        next[n - 1] &= !0 >> (63 - tail);
+        self.words = next;
+    }
+
+    /// The row followed by the next `generations` ones, a row each, with
+    /// Golly's name of the rule, e.g. `W110`. Fails if the board would have
+    /// more than `pattern::MAX_CELLS` cells.
+    pub fn run(&mut self, generations: usize) -> Result<Pattern, &'static str> {
+        let height = generations
+            .checked_add(1)
+            .ok_or("Error: The board is too large.")?;
+        let mut ret = Pattern::try_new(self.width, height)?;
+        ret.rule = Some(format!("W{}", self.rule));
+        for y in 0..=generations {
+            if y > 0 {
+                self.step();
+            }
+            for x in 0..self.width {
+                ret.set(x, y, self.get(x) as u8);
+            }
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::random::Random;
+
+    /// One cell at a time, straight from the definition.
+    fn naive(row: &[bool], rule: u8, boundary: Boundary) -> Vec<bool> {
+        let n = row.len();
+        let cell = |x: isize| -> bool {
+            if (0..n as isize).contains(&x) {
+                return row[x as usize];
+            }
+            let (inside, other) = if x < 0 { (0, n - 1) } else { (n - 1, 0) };
+            match boundary {
+                Boundary::Dead => false,
+                Boundary::Alive => true,
+                Boundary::Wrap => row[other],
+                Boundary::Reflect => row[inside],
+            }
+        };
+        (0..n as isize)
+            .map(|x| {
+                let i = (cell(x - 1) as u8) << 2 | (cell(x) as u8) << 1 | cell(x + 1) as u8;
+                rule >> i & 1 == 1
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rules() {
+        let mut random = Random::new(11);
+        for width in [1, 2, 63, 64, 65, 130] {
+            for boundary in ["dead", "alive", "wrap", "reflect"] {
+                for rule in [30, 90, 110, 184, 255, 1, random.below(256) as u8] {
+                    let mut row: Vec<bool> = (0..width).map(|_| random.chance(0.5)).collect();
+                    let mut ca = Elementary::new(rule, width);
+                    ca.boundary = boundary.parse().unwrap();
+                    (0..width).for_each(|x| ca.set(x, row[x]));
+                    for _ in 0..5 {
+                        row = naive(&row, rule, ca.boundary);
+                        ca.step();
+                        assert!(
+                            (0..width).all(|x| ca.get(x) == row[x]),
+                            "{} {}",
+                            rule,
+                            width
+                        );
+                    }
+                }
+            }
+        }
+        assert!("open".parse::<Boundary>().is_err());
+    }
+
+    #[test]
+    fn sierpinski() {
+        let mut ca = Elementary::new(90, 7);
+        ca.set(3, true);
+        let pattern = ca.run(3).unwrap();
+        assert_eq!(
+            pattern.to_plaintext(),
+            "...X...\n..X.X..\n.X...X.\nX.X.X.X\n"
+        );
+        let row = Pattern::from_plaintext("..X\n...\n").unwrap();
+        let mut ca = Elementary::from_pattern(30, &row);
+        let pattern = ca.run(1).unwrap();
+        assert_eq!(pattern.to_plaintext(), "..X\n.XX\n");
+        assert_eq!(pattern.rule.as_deref(), Some("W30"));
+        assert!(ca.run(usize::MAX).is_err());
+        assert!(ca.run(1 << 40).is_err());
+    }
+}
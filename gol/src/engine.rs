@@ -0,0 +1,136 @@
+//! The interface every engine implements, so the tools built around them
+//! don't need to know how a board is stored or stepped.
+
+use crate::activity::Activity;
+use crate::bitgrid::BitGrid;
+use crate::checkpoint;
+use crate::mask::Mask;
+use crate::noise::Noise;
+use crate::pattern::Pattern;
+use crate::topology::Topology;
+
+pub trait Engine {
+    fn width(&self) -> usize;
+
+    fn height(&self) -> usize;
+
+    /// Returns the state of the cell, 0 is dead.
+    fn get(&self, x: usize, y: usize) -> u8;
+
+    /// Sets the state of the cell, it has to be lower than `states`.
+    fn set(&mut self, x: usize, y: usize, state: u8);
+
+    /// Computes the next generation.
+    fn step(&mut self);
+
+    /// The rule as written to RLE and macrocell headers.
+    fn rule(&self) -> String;
+
+    /// Number of cell states of the rule, including dead.
+    fn states(&self) -> usize {
+        2
+    }
+
+    fn topology(&self) -> Topology;
+
+    /// Starts counting the age and activity of the cells from the current
+    /// board, for the engines which can, see `gol::activity`. Cells changed
+    /// with `set` keep their counters until the next step.
+    fn track_activity(&mut self) -> Result<(), &'static str> {
+        Err("Error: Only the opt01, opt02, generations and ruletable engines track activity.")
+    }
+
+    /// The counters once `track_activity` was called.
+    fn activity(&self) -> Option<&Activity> {
+        None
+    }
+
+    /// Keeps the walls of the mask dead and its fixed cells alive, from now
+    /// on and after every step.
+    fn set_mask(&mut self, _mask: Mask) -> Result<(), &'static str> {
+        Err("Error: The engine doesn't support obstacles.")
+    }
+
+    fn mask(&self) -> Option<&Mask> {
+        None
+    }
+
+    /// Makes the following steps stochastic, see `gol::noise`, for the two
+    /// state engines. The obstacles of the mask still win over the noise.
+    fn set_noise(&mut self, _noise: Noise) -> Result<(), &'static str> {
+        Err("Error: Only the simple, opt01, opt02 and bitboards engines support noise.")
+    }
+
+    fn noise(&self) -> Option<&Noise> {
+        None
+    }
+
+    /// Number of cells which aren't dead.
+    fn population(&self) -> usize {
+        (0..self.height())
+            .map(|y| (0..self.width()).filter(|&x| self.get(x, y) != 0).count())
+            .sum()
+    }
+
+    /// The board for a checkpoint, by default one bit per cell with two
+    /// states and a byte per cell otherwise, row by row.
+    fn save_cells(&self) -> Vec<u8> {
+        let (width, height) = (self.width(), self.height());
+        let cells = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)));
+        if self.states() == 2 {
+            checkpoint::pack_bits(cells.map(|(x, y)| self.get(x, y) != 0))
+        } else {
+            cells.map(|(x, y)| self.get(x, y)).collect()
+        }
+    }
+
+    /// Restores the board saved by `save_cells` of a board of the same size.
+    fn load_cells(&mut self, data: &[u8]) -> Result<(), &'static str> {
+        let (width, height) = (self.width(), self.height());
+        let two_states = self.states() == 2;
+        let len = width * height;
+        if data.len() != if two_states { len.div_ceil(8) } else { len } {
+            return Err("Error: Checkpoint board doesn't match its size.");
+        }
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                let state = if two_states {
+                    checkpoint::unpack_bit(data, i) as u8
+                } else {
+                    data[i]
+                };
+                if state as usize >= self.states() {
+                    return Err("Error: Cell state is not valid for the rule.");
+                }
+                self.set(x, y, state);
+            }
+        }
+        Ok(())
+    }
+
+    /// The live cells as packed rows, every state other than dead counts.
+    fn to_bitgrid(&self) -> BitGrid {
+        let mut ret = BitGrid::new(self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if self.get(x, y) != 0 {
+                    ret.set(x, y, true);
+                }
+            }
+        }
+        ret
+    }
+
+    fn to_pattern(&self) -> Pattern {
+        let mut ret = Pattern::new(self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                ret.set(x, y, self.get(x, y));
+            }
+        }
+        ret.rule = Some(self.rule());
+        ret.mask = self.mask().cloned();
+        ret
+    }
+}
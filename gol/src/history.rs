@@ -0,0 +1,264 @@
+//! Past generations of a run, for going back to any of them. Boards are kept
+//! as saved by `Engine::save_cells`, a full keyframe every so many
+//! generations and otherwise the XOR against the previous generation, with
+//! the runs of unchanged bytes left out. Two state engines save a bit per
+//! cell, so a delta costs little more than the cells which changed.
+//!
+//! A delta is a sequence of entries, each one the number of unchanged bytes
+//! to skip, the number of changed bytes, both as LEB128, and those bytes.
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Frame {
+    Keyframe(Vec<u8>),
+    Delta(Vec<u8>),
+}
+
+impl Frame {
+    fn len(&self) -> usize {
+        match self {
+            Frame::Keyframe(cells) | Frame::Delta(cells) => cells.len(),
+        }
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+fn read_varint(data: &[u8], i: &mut usize) -> usize {
+    let mut ret = 0;
+    let mut shift = 0;
+    loop {
+        let byte = data[*i];
+        *i += 1;
+        ret |= ((byte & 0x7f) as usize) << shift;
+        if byte < 0x80 {
+            return ret;
+        }
+        shift += 7;
+    }
+}
+
+/// The delta turning `old` into `new`, boards of the same length.
+fn delta(old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut ret = Vec::new();
+    let mut i = 0;
+    while i < new.len() {
+        let start = i;
+        while i < new.len() && old[i] == new[i] {
+            i += 1;
+        }
+        if i == new.len() {
+            break;
+        }
+        let changed = i;
+        // a short run of unchanged bytes is cheaper as part of the changes
+        loop {
+            while i < new.len() && old[i] != new[i] {
+                i += 1;
+            }
+            let same = (i..new.len().min(i + 3))
+                .take_while(|&j| old[j] == new[j])
+                .count();
+            if same == 3 || i + same == new.len() {
+                break;
+            }
+            i += same;
+        }
+        write_varint(&mut ret, changed - start);
+        write_varint(&mut ret, i - changed);
+        ret.extend(
+            old[changed..i]
+                .iter()
+                .zip(&new[changed..i])
+                .map(|(a, b)| a ^ b),
+        );
+    }
+    ret
+}
+
+/// Applies the delta to the board, both ways.
+fn apply(delta: &[u8], cells: &mut [u8]) {
+    let (mut i, mut pos) = (0, 0);
+    while i < delta.len() {
+        pos += read_varint(delta, &mut i);
+        let len = read_varint(delta, &mut i);
+        for (cell, d) in cells[pos..pos + len].iter_mut().zip(&delta[i..i + len]) {
+            *cell ^= d;
+        }
+        i += len;
+        pos += len;
+    }
+}
+
+/// The generations of a run from some generation on, within a memory budget.
+#[derive(Debug, Clone)]
+pub struct History {
+    /// A keyframe is kept for the generations which are multiples of it.
+    keyframe_every: u64,
+    /// Bytes the frames may use, once they use more the oldest keyframe and
+    /// its deltas are dropped. The newest keyframe is always kept.
+    budget: usize,
+    /// Generation of the first frame, which is a keyframe.
+    first: u64,
+    frames: VecDeque<Frame>,
+    /// The newest board, for the next delta.
+    last: Vec<u8>,
+    memory: usize,
+}
+
+impl History {
+    /// Starts with the board of the generation.
+    pub fn new(generation: u64, cells: Vec<u8>, keyframe_every: u64, budget: usize) -> Self {
+        History {
+            keyframe_every: keyframe_every.max(1),
+            budget,
+            first: generation,
+            memory: cells.len(),
+            frames: VecDeque::from([Frame::Keyframe(cells.clone())]),
+            last: cells,
+        }
+    }
+
+    /// Generation of the oldest board kept.
+    pub fn first(&self) -> u64 {
+        self.first
+    }
+
+    /// Generation of the newest board.
+    pub fn last(&self) -> u64 {
+        self.first + self.frames.len() as u64 - 1
+    }
+
+    /// Bytes used by the frames.
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    /// Adds the board of the generation after the last one.
+    pub fn push(&mut self, cells: Vec<u8>) {
+        let frame = if (self.last() + 1).is_multiple_of(self.keyframe_every) {
+            Frame::Keyframe(cells.clone())
+        } else {
+            Frame::Delta(delta(&self.last, &cells))
+        };
+        self.memory += frame.len();
+        self.frames.push_back(frame);
+        self.last = cells;
+        while self.memory > self.budget {
+            // the length of the oldest keyframe and its deltas
+            let Some(len) = self
+                .frames
+                .iter()
+                .skip(1)
+                .position(|f| matches!(f, Frame::Keyframe(_)))
+            else {
+                break;
+            };
+            for frame in self.frames.drain(..len + 1) {
+                self.memory -= frame.len();
+            }
+            self.first += len as u64 + 1;
+        }
+    }
+
+    /// The board of the generation, `None` if it was dropped or is yet to
+    /// come.
+    pub fn get(&self, generation: u64) -> Option<Vec<u8>> {
+        if generation < self.first || generation > self.last() {
+            return None;
+        }
+        if generation == self.last() {
+            return Some(self.last.clone());
+        }
+        let index = (generation - self.first) as usize;
+        let key = (0..=index)
+            .rev()
+            .find(|&i| matches!(self.frames[i], Frame::Keyframe(_)))
+            .unwrap();
+        let Frame::Keyframe(ref cells) = self.frames[key] else {
+            unreachable!()
+        };
+        let mut ret = cells.clone();
+        for frame in self.frames.range(key + 1..=index) {
+            if let Frame::Delta(delta) = frame {
+                apply(delta, &mut ret);
+            }
+        }
+        Some(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::random::Random;
+
+    #[test]
+    fn deltas() {
+        let old = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11];
+        let new = [0, 1, 7, 3, 4, 5, 6, 7, 8, 0, 10, 1];
+        let d = delta(&old, &new);
+        // the unchanged byte at 10 is part of the last change
+        assert_eq!(d, [2, 1, 2 ^ 7, 6, 3, 9, 0, 11 ^ 1]);
+        let mut cells = old;
+        apply(&d, &mut cells);
+        assert_eq!(cells, new);
+        assert!(delta(&old, &old).is_empty());
+
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 300);
+        assert_eq!(buf, [0xac, 0x02]);
+        assert_eq!(read_varint(&buf, &mut 0), 300);
+    }
+
+    #[test]
+    fn random_access() {
+        let mut random = Random::new(5);
+        let mut boards = vec![vec![0u8; 100]];
+        let mut history = History::new(3, boards[0].clone(), 8, usize::MAX);
+        for _ in 0..50 {
+            let mut next = boards.last().unwrap().clone();
+            for _ in 0..random.below(10) {
+                next[random.below(100) as usize] ^= random.next_u64() as u8;
+            }
+            history.push(next.clone());
+            boards.push(next);
+        }
+        assert_eq!((history.first(), history.last()), (3, 53));
+        for (i, board) in boards.iter().enumerate() {
+            assert_eq!(history.get(3 + i as u64).as_ref(), Some(board));
+        }
+        assert_eq!(history.get(2), None);
+        assert_eq!(history.get(54), None);
+        assert!(history.memory() < 100 * 51 / 2);
+    }
+
+    #[test]
+    fn budget() {
+        // keyframes at 0, 4, 8, ... of 10 bytes, deltas of 3 bytes
+        let mut history = History::new(0, vec![0; 10], 4, 40);
+        for i in 1..=13u8 {
+            let mut board = vec![0; 10];
+            board[0] = i;
+            history.push(board);
+        }
+        // 12 is the last keyframe, 8 to 11 take 19 bytes, 12 and 13 13 bytes
+        assert_eq!((history.first(), history.last()), (8, 13));
+        assert_eq!(history.memory(), 32);
+        assert_eq!(history.get(9).unwrap()[0], 9);
+        assert_eq!(history.get(7), None);
+
+        let mut tiny = History::new(0, vec![0; 10], 4, 1);
+        for _ in 0..6 {
+            tiny.push(vec![1; 10]);
+        }
+        assert_eq!((tiny.first(), tiny.last()), (4, 6));
+    }
+}
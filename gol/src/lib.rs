@@ -0,0 +1,32 @@
+//! Shared pieces of the game of life implementations, so the individual
+//! engines only need to care about how they store and step the board.
+
+pub mod activity;
+pub mod bitgrid;
+pub mod checkpoint;
+pub mod diff;
+pub mod elementary;
+pub mod engine;
+pub mod history;
+pub mod macrocell;
+pub mod mask;
+pub mod noise;
+pub mod observer;
+pub mod pattern;
+pub mod predecessor;
+pub mod random;
+pub mod rule;
+pub mod sat;
+pub mod search;
+pub mod table;
+pub mod topology;
+pub mod transform;
+
+pub use bitgrid::BitGrid;
+pub use checkpoint::Checkpoint;
+pub use engine::Engine;
+pub use history::History;
+pub use pattern::Pattern;
+pub use rule::Rule;
+pub use table::Table;
+pub use topology::Topology;
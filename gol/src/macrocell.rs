@@ -0,0 +1,444 @@
+//! Golly's [macrocell](https://conwaylife.com/wiki/Macrocell) format, a
+//! quadtree with every distinct node written only once, so big repetitive
+//! patterns stay small.
+//!
+//! ```text
+//! [M2] (gol)
+//! #R B3/S23
+//! #C x = 3, y = 3
+//! .*$..*$***$
+//! 4 0 0 0 1
+//! ```
+//!
+//! Every line after the header is a node, referenced by its 1-based line
+//! number, 0 being the empty node. Two state patterns have 8x8 leaves drawn
+//! with `.`, `*` and `$` ending the rows, multi-state ones have level 1 nodes
+//! `1 nw ne sw se` with the states of the four cells. Bigger nodes are
+//! `level nw ne sw se` with the indices of their quarters. The last node is
+//! the root, centred on the origin.
+//!
+//! The format doesn't know the size of the board, so it is written in a
+//! `#C x = .., y = ..` comment with the board's top left corner at the
+//! origin. Without the comment the board is the bounding box of the cells.
+
+use crate::pattern::Pattern;
+use std::collections::HashMap;
+
+/// Whether the text starts with the macrocell header.
+pub fn is_macrocell(s: &str) -> bool {
+    s.starts_with("[M2]")
+}
+
+enum Node {
+    /// Rows of an 8x8 leaf, bit `x` of a row is the cell in column `x`.
+    Leaf([u8; 8]),
+    /// States of the nw, ne, sw and se cells.
+    Level1([u8; 4]),
+    Inner(u32, [usize; 4]),
+}
+
+impl Node {
+    fn level(&self) -> u32 {
+        match self {
+            Node::Leaf(_) => 3,
+            Node::Level1(_) => 1,
+            Node::Inner(level, _) => *level,
+        }
+    }
+}
+
+/// Calls `f` with the Golly coordinates and state of every live cell of the
+/// node at `index` with its top left corner at `(x, y)`.
+fn walk(nodes: &[Node], index: usize, x: i64, y: i64, f: &mut impl FnMut(i64, i64, u8)) {
+    if index == 0 {
+        return;
+    }
+    match &nodes[index - 1] {
+        Node::Leaf(rows) => {
+            for (dy, row) in rows.iter().enumerate() {
+                for dx in 0..8 {
+                    if row & (1 << dx) != 0 {
+                        f(x + dx, y + dy as i64, 1);
+                    }
+                }
+            }
+        }
+        Node::Level1(states) => {
+            for (i, &state) in states.iter().enumerate() {
+                if state != 0 {
+                    f(x + (i % 2) as i64, y + (i / 2) as i64, state);
+                }
+            }
+        }
+        Node::Inner(level, children) => {
+            let half = 1 << (level - 1);
+            for (i, &child) in children.iter().enumerate() {
+                walk(
+                    nodes,
+                    child,
+                    x + (i % 2) as i64 * half,
+                    y + (i / 2) as i64 * half,
+                    f,
+                );
+            }
+        }
+    }
+}
+
+/// The bounding box of the live cells of every node, relative to its top
+/// left corner, computed once for each node rather than for each time it is
+/// used, so huge repetitive patterns are measured without expanding them.
+/// The children come before their parents, so one pass does it.
+fn bounding_boxes(nodes: &[Node]) -> Vec<Option<(i64, i64, i64, i64)>> {
+    let mut ret: Vec<Option<(i64, i64, i64, i64)>> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let mut bounds = None;
+        let mut add = |(left, top, right, bottom): (i64, i64, i64, i64)| {
+            let (l, t, r, b) = bounds.unwrap_or((left, top, right, bottom));
+            bounds = Some((l.min(left), t.min(top), r.max(right), b.max(bottom)));
+        };
+        match node {
+            Node::Leaf(_) | Node::Level1(_) => {
+                walk(nodes, ret.len() + 1, 0, 0, &mut |x, y, _| add((x, y, x, y)))
+            }
+            Node::Inner(level, children) => {
+                let half = 1 << (level - 1);
+                for (i, &child) in children.iter().enumerate() {
+                    let (dx, dy) = ((i % 2) as i64 * half, (i / 2) as i64 * half);
+                    if let Some((l, t, r, b)) = child.checked_sub(1).and_then(|c| ret[c]) {
+                        add((l + dx, t + dy, r + dx, b + dy));
+                    }
+                }
+            }
+        }
+        ret.push(bounds);
+    }
+    ret
+}
+
+impl Pattern {
+    pub fn from_macrocell(s: &str) -> Result<Self, &'static str> {
+        let mut lines = s.lines();
+        if !lines.next().is_some_and(is_macrocell) {
+            return Err("Error: Missing macrocell header.");
+        }
+        let mut rule = None;
+        let mut size = None;
+        let mut nodes: Vec<Node> = Vec::new();
+        for line in lines.map(str::trim) {
+            if let Some(r) = line.strip_prefix("#R") {
+                rule = Some(r.trim().to_string());
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix("#C") {
+                let fields: HashMap<&str, usize> = comment
+                    .split(',')
+                    .filter_map(|f| f.split_once('='))
+                    .filter_map(|(k, v)| Some((k.trim(), v.trim().parse().ok()?)))
+                    .collect();
+                if let (Some(&w), Some(&h)) = (fields.get("x"), fields.get("y")) {
+                    size = Some((w, h));
+                }
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let node = if line.starts_with(['.', '*', '$']) {
+                let mut rows = [0u8; 8];
+                let (mut x, mut y) = (0, 0);
+                for c in line.chars() {
+                    match c {
+                        '$' => {
+                            x = 0;
+                            y += 1;
+                            continue;
+                        }
+                        '.' | '*' if x >= 8 || y >= 8 => {
+                            return Err("Error: Macrocell leaf is bigger than 8x8.")
+                        }
+                        '*' => rows[y] |= 1 << x,
+                        '.' => {}
+                        _ => return Err("Error: Invalid character in macrocell leaf."),
+                    }
+                    x += 1;
+                }
+                Node::Leaf(rows)
+            } else {
+                let numbers = line
+                    .split_whitespace()
+                    .map(|n| n.parse::<usize>())
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|_| "Error: Invalid macrocell node.")?;
+                match numbers[..] {
+                    [1, nw, ne, sw, se] if [nw, ne, sw, se].iter().all(|&s| s < 256) => {
+                        Node::Level1([nw as u8, ne as u8, sw as u8, se as u8])
+                    }
+                    [level, nw, ne, sw, se] if (2..63).contains(&level) => {
+                        let children = [nw, ne, sw, se];
+                        for &child in &children {
+                            if child > nodes.len() {
+                                return Err("Error: Macrocell node refers to a later node.");
+                            }
+                            if child != 0 && nodes[child - 1].level() != level as u32 - 1 {
+                                return Err("Error: Macrocell node with a child of wrong level.");
+                            }
+                        }
+                        Node::Inner(level as u32, children)
+                    }
+                    _ => return Err("Error: Invalid macrocell node."),
+                }
+            };
+            nodes.push(node);
+        }
+
+        let root = nodes.len();
+        if root == 0 {
+            return Err("Error: Macrocell pattern without nodes.");
+        }
+        let origin = -(1i64 << (nodes[root - 1].level() - 1));
+        let bounds = bounding_boxes(&nodes)[root - 1]
+            .map(|(l, t, r, b)| (l + origin, t + origin, r + origin, b + origin));
+        // the bounding box is the board unless the size was given
+        let (left, top, width, height) = match (size, bounds) {
+            (Some((w, h)), _) => (0, 0, w, h),
+            (None, Some((l, t, r, b))) => (l, t, (r - l + 1) as usize, (b - t + 1) as usize),
+            (None, None) => return Err("Error: Empty macrocell pattern without the board size."),
+        };
+        if width == 0 || height == 0 {
+            return Err("Error: Invalid macrocell board size.");
+        }
+        if let Some((l, t, r, b)) = bounds {
+            if l < left || t < top || r - left >= width as i64 || b - top >= height as i64 {
+                return Err("Error: Macrocell pattern is bigger than its board.");
+            }
+        }
+
+        let mut ret = Pattern::try_new(width, height)?;
+        ret.rule = rule;
+        walk(&nodes, root, origin, origin, &mut |x, y, state| {
+            ret.set((x - left) as usize, (y - top) as usize, state);
+        });
+        Ok(ret)
+    }
+
+    /// Writes the pattern with `rule` in the header, `multi_state` selects
+    /// the level 1 nodes over the 8x8 leaves.
+    pub fn to_macrocell(&self, rule: &str, multi_state: bool) -> String {
+        struct Writer<'a> {
+            pattern: &'a Pattern,
+            leaf_level: u32,
+            /// Index of every node already written, by its line.
+            indices: HashMap<String, usize>,
+            lines: Vec<String>,
+        }
+
+        impl Writer<'_> {
+            fn get(&self, x: usize, y: usize) -> u8 {
+                if x < self.pattern.width && y < self.pattern.height {
+                    self.pattern.get(x, y)
+                } else {
+                    0
+                }
+            }
+
+            /// Writes the node of `level` with its top left corner at `(x, y)`
+            /// and returns its index.
+            fn node(&mut self, level: u32, x: usize, y: usize) -> usize {
+                if x >= self.pattern.width || y >= self.pattern.height {
+                    return 0;
+                }
+                let line = if level == self.leaf_level && level == 3 {
+                    let mut line = String::new();
+                    let mut empty_rows = 0;
+                    for dy in 0..8 {
+                        let end = (0..8).rev().find(|&dx| self.get(x + dx, y + dy) != 0);
+                        if let Some(end) = end {
+                            line.push_str(&"$".repeat(empty_rows));
+                            empty_rows = 0;
+                            for dx in 0..=end {
+                                line.push(if self.get(x + dx, y + dy) != 0 {
+                                    '*'
+                                } else {
+                                    '.'
+                                });
+                            }
+                        }
+                        empty_rows += 1;
+                    }
+                    if line.is_empty() {
+                        return 0;
+                    }
+                    line.push('$');
+                    line
+                } else if level == 1 {
+                    let states = [
+                        self.get(x, y),
+                        self.get(x + 1, y),
+                        self.get(x, y + 1),
+                        self.get(x + 1, y + 1),
+                    ];
+                    if states == [0; 4] {
+                        return 0;
+                    }
+                    format!("1 {} {} {} {}", states[0], states[1], states[2], states[3])
+                } else {
+                    let half = 1 << (level - 1);
+                    let children = [
+                        self.node(level - 1, x, y),
+                        self.node(level - 1, x + half, y),
+                        self.node(level - 1, x, y + half),
+                        self.node(level - 1, x + half, y + half),
+                    ];
+                    if children == [0; 4] {
+                        return 0;
+                    }
+                    format!(
+                        "{} {} {} {} {}",
+                        level, children[0], children[1], children[2], children[3]
+                    )
+                };
+                if let Some(&index) = self.indices.get(&line) {
+                    return index;
+                }
+                self.lines.push(line.clone());
+                self.indices.insert(line, self.lines.len());
+                self.lines.len()
+            }
+        }
+
+        let leaf_level = if multi_state { 1 } else { 3 };
+        // the board fills the south east quarter of the root
+        let side = self.width.max(self.height).max(1).next_power_of_two();
+        let level = (side.trailing_zeros() + 1).max(leaf_level + 1);
+        let mut writer = Writer {
+            pattern: self,
+            leaf_level,
+            indices: HashMap::new(),
+            lines: Vec::new(),
+        };
+        let se = writer.node(level - 1, 0, 0);
+        if se == 0 {
+            // an empty root still has to be written
+            writer.lines.push(format!("{} 0 0 0 0", level));
+        } else {
+            writer.lines.push(format!("{} 0 0 0 {}", level, se));
+        }
+
+        let mut ret = format!(
+            "[M2] (gol)\n#R {}\n#C x = {}, y = {}\n",
+            rule, self.width, self.height
+        );
+        for line in writer.lines {
+            ret.push_str(&line);
+            ret.push('\n');
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glider() {
+        let pattern = Pattern::from_plaintext(".X.\n..X\nXXX\n").unwrap();
+        let mc = pattern.to_macrocell("B3/S23", false);
+        assert!(is_macrocell(&mc));
+        assert_eq!(
+            mc,
+            "[M2] (gol)\n#R B3/S23\n#C x = 3, y = 3\n.*$..*$***$\n4 0 0 0 1\n"
+        );
+        let read = Pattern::from_macrocell(&mc).unwrap();
+        assert_eq!(read.rule.as_deref(), Some("B3/S23"));
+        assert_eq!(read.cells, pattern.cells);
+    }
+
+    #[test]
+    fn golly_file() {
+        // as saved by Golly, centred on the origin and without the board size
+        let mc = "[M2] (golly 4.2)\n#R B3/S23\n#G 12\n$$$$$$$..*$\n*$.*$*$\n4 1 0 0 2\n";
+        let read = Pattern::from_macrocell(mc).unwrap();
+        assert_eq!(read.rule.as_deref(), Some("B3/S23"));
+        assert_eq!(
+            read.to_plaintext(),
+            "X.......\n......X.\n.......X\n......X.\n"
+        );
+    }
+
+    #[test]
+    fn repetitive() {
+        // the same block over and over is stored once per level
+        let mut pattern = Pattern::new(512, 512);
+        for y in (0..512).step_by(8) {
+            for x in (0..512).step_by(8) {
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    pattern.set(x + dx, y + dy, 1);
+                }
+            }
+        }
+        let mc = pattern.to_macrocell("B3/S23", false);
+        assert!(mc.lines().count() < 15, "{}", mc);
+        assert_eq!(
+            Pattern::from_macrocell(&mc).unwrap(),
+            Pattern {
+                rule: Some("B3/S23".to_string()),
+                ..pattern
+            }
+        );
+    }
+
+    #[test]
+    fn huge() {
+        // a glider deep in a node of level 60, and then 2^120 copies of it,
+        // measured without walking every copy
+        let mut mc = String::from("[M2]\n.*$..*$***$\n");
+        for level in 4..=60 {
+            mc.push_str(&format!("{} {} 0 0 0\n", level, level - 3));
+        }
+        let read = Pattern::from_macrocell(&mc).unwrap();
+        assert_eq!(read.to_plaintext(), ".X.\n..X\nXXX\n");
+
+        let mut mc = String::from("[M2]\n.*$..*$***$\n");
+        for level in 4..=60 {
+            let n = level - 3;
+            mc.push_str(&format!("{} {} {} {} {}\n", level, n, n, n, n));
+        }
+        assert_eq!(
+            Pattern::from_macrocell(&mc),
+            Err("Error: The board is too large.")
+        );
+    }
+
+    #[test]
+    fn multi_state() {
+        let pattern = Pattern::from_plaintext("2X.\n..3\n...\n").unwrap();
+        let mc = pattern.to_macrocell("B2/S345/C4", true);
+        assert_eq!(
+            mc,
+            "[M2] (gol)\n#R B2/S345/C4\n#C x = 3, y = 3\n1 2 1 0 0\n1 0 0 3 0\n2 1 2 0 0\n3 0 0 0 3\n"
+        );
+        assert_eq!(Pattern::from_macrocell(&mc).unwrap().cells, pattern.cells);
+    }
+
+    #[test]
+    fn empty() {
+        let pattern = Pattern::new(5, 2);
+        let mc = pattern.to_macrocell("B3/S23", false);
+        assert_eq!(Pattern::from_macrocell(&mc).unwrap().cells, pattern.cells);
+        assert!(Pattern::from_macrocell("[M2]\n4 0 0 0 0\n").is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Pattern::from_macrocell("x = 1, y = 1\no!").is_err());
+        assert!(Pattern::from_macrocell("[M2]\n").is_err());
+        assert!(Pattern::from_macrocell("[M2]\n*********$\n").is_err());
+        assert!(Pattern::from_macrocell("[M2]\n$$$$$$$$*$\n").is_err());
+        assert!(Pattern::from_macrocell("[M2]\n4 2 0 0 0\n").is_err());
+        assert!(Pattern::from_macrocell("[M2]\n*$\n5 1 0 0 0\n").is_err());
+        assert!(Pattern::from_macrocell("[M2]\n#C x = 1, y = 1\n**$\n").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Obstacles on the board, walls which are always dead and fixed cells which
+//! are always alive. The engines force them after every step, like the dead
+//! cells beyond the edges of a bounded board, so a wall behaves as an edge of
+//! any shape. In the plaintext format they are `#` and `@`.
+
+use crate::bitgrid::BitGrid;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mask {
+    pub walls: BitGrid,
+    pub fixed: BitGrid,
+}
+
+/// Indices of the set bits of the word, lowest first.
+fn ones(mut word: u64) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        (word != 0).then(|| {
+            let bit = word.trailing_zeros() as usize;
+            word &= word - 1;
+            bit
+        })
+    })
+}
+
+impl Mask {
+    pub fn new(width: usize, height: usize) -> Self {
+        Mask {
+            walls: BitGrid::new(width, height),
+            fixed: BitGrid::new(width, height),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.walls.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.walls.height
+    }
+
+    /// Fails unless the mask is the size of the board.
+    pub fn check(&self, width: usize, height: usize) -> Result<(), &'static str> {
+        if (self.width(), self.height()) != (width, height) {
+            return Err("Error: The obstacles don't match the size of the board.");
+        }
+        Ok(())
+    }
+
+    /// The state the cell is kept in, `None` if it isn't masked.
+    pub fn get(&self, x: usize, y: usize) -> Option<u8> {
+        if self.fixed.get(x, y) {
+            Some(1)
+        } else if self.walls.get(x, y) {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Each masked cell with the state it is kept in, the walls first.
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize, u8)> + '_ {
+        [(&self.walls, 0), (&self.fixed, 1)]
+            .into_iter()
+            .flat_map(move |(grid, state)| {
+                (0..grid.height).flat_map(move |y| {
+                    grid.row(y).iter().enumerate().flat_map(move |(i, &word)| {
+                        ones(word).map(move |bit| (i * 64 + bit, y, state))
+                    })
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cells() {
+        let mut mask = Mask::new(70, 2);
+        mask.walls.set(0, 0, true);
+        mask.walls.set(65, 1, true);
+        mask.fixed.set(3, 1, true);
+        let cells: Vec<_> = mask.cells().collect();
+        assert_eq!(cells, [(0, 0, 0), (65, 1, 0), (3, 1, 1)]);
+        assert_eq!(mask.get(3, 1), Some(1));
+        assert_eq!(mask.get(65, 1), Some(0));
+        assert_eq!(mask.get(1, 1), None);
+        assert!(mask.check(70, 2).is_ok());
+        assert!(mask.check(2, 70).is_err());
+    }
+}
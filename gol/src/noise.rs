@@ -0,0 +1,206 @@
+//! Stochastic variants of the two state rules. After the rule each birth
+//! happens with the `birth` probability and each survival with the
+//! `survival` one, and then every cell flips with the `flip` probability.
+//!
+//! The numbers of a step are drawn from the seed and the generation only, so
+//! a run is the same every time and continues the same way from a
+//! checkpoint. Probabilities are rounded to multiples of 2^-16, which lets
+//! `Chance::mask` draw 64 cells at once with a few bitwise operations.
+
+use crate::random::Random;
+use std::str::FromStr;
+
+const ONE: u32 = 1 << 16;
+
+/// A probability in steps of 2^-16.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chance(u32);
+
+impl Chance {
+    pub const NEVER: Chance = Chance(0);
+    pub const ALWAYS: Chance = Chance(ONE);
+
+    pub fn new(p: f64) -> Result<Self, &'static str> {
+        if !(0.0..=1.0).contains(&p) {
+            return Err("Error: A probability has to be between 0 and 1.");
+        }
+        Ok(Chance((p * ONE as f64).round() as u32))
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / ONE as f64
+    }
+
+    /// True with the probability, without drawing a number for 0 and 1.
+    pub fn roll(self, random: &mut Random) -> bool {
+        match self.0 {
+            0 => false,
+            ONE => true,
+            p => ((random.next_u64() >> 48) as u32) < p,
+        }
+    }
+
+    /// A word with each bit set with the probability. Going from the lowest
+    /// bit of the probability up, a set bit ORs a random word in and a clear
+    /// one ANDs it, each halving the chance of the bits so far.
+    pub fn mask(self, random: &mut Random) -> u64 {
+        match self.0 {
+            0 => 0,
+            ONE => !0,
+            p => (p.trailing_zeros()..16).fold(0, |ret, bit| {
+                let r = random.next_u64();
+                match p >> bit & 1 {
+                    1 => ret | r,
+                    _ => ret & r,
+                }
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Noise {
+    pub birth: Chance,
+    pub survival: Chance,
+    pub flip: Chance,
+    pub seed: u64,
+    /// Generation of the next step.
+    pub generation: u64,
+}
+
+impl Default for Noise {
+    /// No noise at all.
+    fn default() -> Self {
+        Noise {
+            birth: Chance::ALWAYS,
+            survival: Chance::ALWAYS,
+            flip: Chance::NEVER,
+            seed: 0,
+            generation: 0,
+        }
+    }
+}
+
+/// Parses `key=value` pairs separated by commas, with the keys `birth`,
+/// `survival`, `flip` and `seed`, e.g. `flip=0.001,seed=7`.
+impl FromStr for Noise {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret = Noise::default();
+        for pair in s.split(',') {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or("Error: Expected key=value pairs for the noise.")?;
+            let chance = || match value.parse() {
+                Ok(p) => Chance::new(p),
+                Err(_) => Err("Error: Couldn't parse the probability."),
+            };
+            match key {
+                "birth" => ret.birth = chance()?,
+                "survival" => ret.survival = chance()?,
+                "flip" => ret.flip = chance()?,
+                "seed" => ret.seed = value.parse().map_err(|_| "Error: Invalid seed.")?,
+                _ => return Err("Error: Unknown noise, expected birth, survival, flip or seed."),
+            }
+        }
+        Ok(ret)
+    }
+}
+
+impl Noise {
+    /// The dice of the next step.
+    pub fn step(&mut self) -> Dice {
+        let seed = self.seed ^ self.generation.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        self.generation += 1;
+        Dice {
+            noise: *self,
+            random: Random::new(seed),
+        }
+    }
+}
+
+/// Draws the noise of one step, cell by cell or 64 cells at once.
+pub struct Dice {
+    noise: Noise,
+    random: Random,
+}
+
+impl Dice {
+    /// The state of a cell which was alive in `was` and which the rule
+    /// makes `alive`.
+    pub fn cell(&mut self, was: bool, alive: bool) -> bool {
+        let r = &mut self.random;
+        let alive = match (was, alive) {
+            (false, true) => self.noise.birth.roll(r),
+            (true, true) => self.noise.survival.roll(r),
+            _ => false,
+        };
+        alive ^ self.noise.flip.roll(r)
+    }
+
+    /// The same for a word of cells, also setting bits outside the board.
+    pub fn cells(&mut self, was: u64, alive: u64) -> u64 {
+        let r = &mut self.random;
+        let births = alive & !was & self.noise.birth.mask(r);
+        let survivals = alive & was & self.noise.survival.mask(r);
+        (births | survivals) ^ self.noise.flip.mask(r)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn chances() {
+        let mut random = Random::new(1);
+        assert_eq!(Chance::new(0.25), Ok(Chance(ONE / 4)));
+        assert!(Chance::new(1.5).is_err());
+        assert_eq!(Chance::ALWAYS.mask(&mut random), !0);
+        assert_eq!(Chance::NEVER.mask(&mut random), 0);
+        for p in [0.001, 0.25, 0.6, 0.9] {
+            let chance = Chance::new(p).unwrap();
+            let bits: u32 = (0..2000)
+                .map(|_| chance.mask(&mut random).count_ones())
+                .sum();
+            let rolls = (0..128_000).filter(|_| chance.roll(&mut random)).count();
+            for n in [bits as f64, rolls as f64] {
+                assert!((n / 128_000.0 - p).abs() < 0.01, "{} {}", p, n);
+            }
+        }
+    }
+
+    #[test]
+    fn noise() {
+        let noise: Noise = "flip=0.5,survival=0,seed=7".parse().unwrap();
+        assert_eq!(noise.flip.to_f64(), 0.5);
+        assert_eq!(noise.birth, Chance::ALWAYS);
+        assert_eq!(noise.seed, 7);
+        assert!("flip".parse::<Noise>().is_err());
+        assert!("death=0.5".parse::<Noise>().is_err());
+
+        // only the births are kept
+        let mut quiet = Noise {
+            survival: Chance::NEVER,
+            ..Noise::default()
+        };
+        let mut dice = quiet.step();
+        assert_eq!(dice.cells(0b0110, 0b1100), 0b1000);
+        assert!(!dice.cell(true, true));
+        assert!(dice.cell(false, true));
+
+        // the same seed and generation draw the same numbers
+        let (mut a, mut b) = (
+            noise,
+            Noise {
+                generation: 1,
+                ..noise
+            },
+        );
+        a.step();
+        assert_eq!(a.step().cells(0, 0), b.step().cells(0, 0));
+        let mut c = noise;
+        assert_ne!(a.step().cells(0, 0), c.step().cells(0, 0));
+    }
+}
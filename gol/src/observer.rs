@@ -0,0 +1,159 @@
+//! Hooks into the stepping loop, for programs embedding an engine which want
+//! to react while it runs: stop once the board is empty, log milestones or
+//! hand the boards to a UI.
+
+use crate::engine::Engine;
+
+/// What the loop does after an observer has seen a generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Continue,
+    Stop,
+}
+
+pub trait Observer {
+    /// Called after each step with the board and its generation.
+    fn observe(&mut self, life: &dyn Engine, generation: u64) -> Control;
+}
+
+impl<F: FnMut(&dyn Engine, u64) -> Control> Observer for F {
+    fn observe(&mut self, life: &dyn Engine, generation: u64) -> Control {
+        self(life, generation)
+    }
+}
+
+/// All of the observers see every generation, the loop stops when any of
+/// them asks for it.
+impl Observer for Vec<Box<dyn Observer>> {
+    fn observe(&mut self, life: &dyn Engine, generation: u64) -> Control {
+        let mut ret = Control::Continue;
+        for observer in self.iter_mut() {
+            if observer.observe(life, generation) == Control::Stop {
+                ret = Control::Stop;
+            }
+        }
+        ret
+    }
+}
+
+/// Stops once every cell is dead.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UntilEmpty;
+
+impl Observer for UntilEmpty {
+    fn observe(&mut self, life: &dyn Engine, _: u64) -> Control {
+        if life.population() == 0 {
+            Control::Stop
+        } else {
+            Control::Continue
+        }
+    }
+}
+
+/// Steps the board from `generation` up to `end` and returns the generation
+/// it got to, earlier than `end` if the observer stopped it.
+pub fn run(life: &mut dyn Engine, generation: u64, end: u64, observer: &mut dyn Observer) -> u64 {
+    for generation in generation + 1..=end {
+        life.step();
+        if observer.observe(life, generation) == Control::Stop {
+            return generation;
+        }
+    }
+    end.max(generation)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pattern::Pattern;
+    use crate::rule::Rule;
+    use crate::topology::Topology;
+
+    /// A small engine for the tests, the cells outside are dead.
+    struct Board {
+        pattern: Pattern,
+        rule: Rule,
+    }
+
+    impl Engine for Board {
+        fn width(&self) -> usize {
+            self.pattern.width
+        }
+
+        fn height(&self) -> usize {
+            self.pattern.height
+        }
+
+        fn get(&self, x: usize, y: usize) -> u8 {
+            self.pattern.get(x, y)
+        }
+
+        fn set(&mut self, x: usize, y: usize, state: u8) {
+            self.pattern.set(x, y, state)
+        }
+
+        fn step(&mut self) {
+            let (w, h) = (self.width() as i64, self.height() as i64);
+            let mut next = self.pattern.clone();
+            for y in 0..h {
+                for x in 0..w {
+                    let index = (0..9)
+                        .filter(|b| {
+                            let (nx, ny) = (x + b % 3 - 1, y + b / 3 - 1);
+                            (0..w).contains(&nx)
+                                && (0..h).contains(&ny)
+                                && self.pattern.get(nx as usize, ny as usize) != 0
+                        })
+                        .fold(0, |i, b| i | 1 << b);
+                    next.set(x as usize, y as usize, self.rule.alive(index) as u8);
+                }
+            }
+            self.pattern = next;
+        }
+
+        fn rule(&self) -> String {
+            self.rule.to_string()
+        }
+
+        fn topology(&self) -> Topology {
+            Topology::Bounded
+        }
+    }
+
+    fn board(s: &str) -> Board {
+        Board {
+            pattern: Pattern::from_plaintext(s).unwrap(),
+            rule: Rule::default(),
+        }
+    }
+
+    #[test]
+    fn early_stop() {
+        // a domino dies in one step
+        let mut life = board("....\n.XX.\n....\n");
+        assert_eq!(run(&mut life, 0, 100, &mut UntilEmpty), 1);
+
+        let mut life = board(".....\n..X..\n..X..\n..X..\n.....\n");
+        let mut seen = Vec::new();
+        let mut log = |life: &dyn Engine, generation: u64| {
+            seen.push((generation, life.get(1, 2)));
+            Control::Continue
+        };
+        assert_eq!(run(&mut life, 10, 13, &mut log), 13);
+        assert_eq!(seen, [(11, 1), (12, 0), (13, 1)]);
+        assert_eq!(run(&mut life, 13, 13, &mut UntilEmpty), 13);
+
+        // the blinker never dies, the milestone observer stops it
+        let mut observers: Vec<Box<dyn Observer>> = vec![
+            Box::new(UntilEmpty),
+            Box::new(|_: &dyn Engine, generation: u64| {
+                if generation == 5 {
+                    Control::Stop
+                } else {
+                    Control::Continue
+                }
+            }),
+        ];
+        assert_eq!(run(&mut life, 0, 100, &mut observers), 5);
+    }
+}
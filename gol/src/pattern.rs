@@ -0,0 +1,386 @@
+//! Reading and writing boards with one byte per cell, for the engines that
+//! have more than two states.
+//!
+//! The plaintext format is the one of the project extended with the extra
+//! states, `.` is dead, `X` is alive (state 1) and `2`-`9`, `a`-`z` are the
+//! states 2 to 35. `#` is a wall and `@` a fixed live cell, see `gol::mask`,
+//! only the plaintext format keeps them. RLE uses the multi-state letters,
+//! `.` is dead, `A` state 1 up to `X` state 24, then `pA`-`pX`, `qA`-`qX`
+//! and so on up to `yO` (state 255). Two state patterns use the usual `b`
+//! and `o`.
+
+use crate::mask::Mask;
+
+/// Highest number of states that can be written in the plaintext format.
+pub const PLAINTEXT_MAX_STATES: usize = 36;
+
+/// Most cells of a board whose size comes from a header, 8192x8192. Text
+/// that small can't claim gigabytes, or a size whose cell count overflows.
+pub const MAX_CELLS: usize = 1 << 26;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    /// Row major cell states, 0 is dead.
+    pub cells: Vec<u8>,
+    /// Rulestring from the RLE header, if there was one.
+    pub rule: Option<String>,
+    /// The obstacles, if there are any.
+    pub mask: Option<Mask>,
+}
+
+/// Whether the text looks like RLE rather than plaintext, which can't start
+/// with a comment like `#C` or the `x = ` header. A plaintext row can start
+/// with a wall.
+pub fn is_rle(s: &str) -> bool {
+    let first = s.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let mut chars = first.chars();
+    let comment = chars.next() == Some('#')
+        && matches!(chars.next(), Some('C' | 'c' | 'N' | 'O' | 'P' | 'R' | 'r'))
+        && matches!(chars.next(), None | Some(' '));
+    comment || first.starts_with("x ") || first.starts_with("x=")
+}
+
+/// Splits the text at its empty lines, into the boards of a batch or the
+/// slices of a 3D board.
+pub fn split_boards(s: &str) -> Vec<String> {
+    let mut ret = Vec::new();
+    let mut board = String::new();
+    for line in s.lines().chain([""]) {
+        if !line.trim().is_empty() {
+            board.push_str(line);
+            board.push('\n');
+        } else if !board.is_empty() {
+            ret.push(std::mem::take(&mut board));
+        }
+    }
+    ret
+}
+
+impl Pattern {
+    pub fn new(width: usize, height: usize) -> Self {
+        Pattern {
+            width,
+            height,
+            cells: vec![0; width * height],
+            rule: None,
+            mask: None,
+        }
+    }
+
+    /// A dead board like `new`, for sizes read from a file, failing with
+    /// more than `MAX_CELLS` cells.
+    pub fn try_new(width: usize, height: usize) -> Result<Self, &'static str> {
+        match width.checked_mul(height) {
+            Some(cells) if cells <= MAX_CELLS => Ok(Pattern::new(width, height)),
+            _ => Err("Error: The board is too large."),
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, state: u8) {
+        self.cells[y * self.width + x] = state;
+    }
+
+    pub fn from_plaintext(s: &str) -> Result<Self, &'static str> {
+        let rows: Vec<&str> = s.lines().filter(|l| !l.is_empty()).collect();
+        let width = rows.first().map_or(0, |r| r.len());
+        let mut ret = Pattern::new(width, rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err("Error: Line of different length.");
+            }
+            for (x, c) in row.chars().enumerate() {
+                if c == '#' || c == '@' {
+                    let (width, height) = (ret.width, ret.height);
+                    let mask = ret.mask.get_or_insert_with(|| Mask::new(width, height));
+                    let grid = if c == '#' {
+                        &mut mask.walls
+                    } else {
+                        &mut mask.fixed
+                    };
+                    grid.set(x, y, true);
+                }
+                let state = match c {
+                    'X' | '@' => 1,
+                    '2'..='9' => c as u8 - b'0',
+                    'a'..='z' => c as u8 - b'a' + 10,
+                    _ => 0,
+                };
+                ret.set(x, y, state);
+            }
+        }
+        Ok(ret)
+    }
+
+    /// States above `PLAINTEXT_MAX_STATES` can't be represented and are
+    /// written as `?`.
+    pub fn to_plaintext(&self) -> String {
+        let mut ret = String::with_capacity((self.width + 1) * self.height);
+        for (y, row) in self.cells.chunks(self.width.max(1)).enumerate() {
+            for (x, &state) in row.iter().enumerate() {
+                let masked = self.mask.as_ref().and_then(|m| m.get(x, y));
+                ret.push(match (masked, state) {
+                    (Some(0), _) => '#',
+                    (Some(_), _) => '@',
+                    (None, 0) => '.',
+                    (None, 1) => 'X',
+                    (None, n @ 2..=9) => char::from(b'0' + n),
+                    (None, n) if (n as usize) < PLAINTEXT_MAX_STATES => char::from(b'a' + n - 10),
+                    _ => '?',
+                });
+            }
+            ret.push('\n');
+        }
+        ret
+    }
+
+    pub fn from_rle(s: &str) -> Result<Self, &'static str> {
+        let mut lines = s.lines().filter(|l| !l.starts_with('#'));
+        let header = lines.next().ok_or("Error: Missing RLE header.")?;
+        let mut width = None;
+        let mut height = None;
+        let mut rule = None;
+        for field in header.split(',') {
+            let (key, value) = field.split_once('=').ok_or("Error: Invalid RLE header.")?;
+            match key.trim() {
+                "x" => width = value.trim().parse::<usize>().ok(),
+                "y" => height = value.trim().parse::<usize>().ok(),
+                "rule" => rule = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+        let (width, height) = match (width, height) {
+            (Some(w), Some(h)) if w > 0 && h > 0 => (w, h),
+            _ => return Err("Error: Invalid RLE header dimensions."),
+        };
+
+        let mut ret = Pattern::try_new(width, height)?;
+        ret.rule = rule;
+        let (mut x, mut y): (usize, usize) = (0, 0);
+        let mut run: usize = 0;
+        let mut prefix: Option<u8> = None;
+        'body: for line in lines {
+            for c in line.bytes() {
+                let state = match c {
+                    b'0'..=b'9' => {
+                        run = run.saturating_mul(10).saturating_add((c - b'0') as usize);
+                        continue;
+                    }
+                    b'p'..=b'y' => {
+                        prefix = Some(c - b'p' + 1);
+                        continue;
+                    }
+                    b'!' => break 'body,
+                    b'$' => {
+                        y = y.saturating_add(run.max(1));
+                        x = 0;
+                        run = 0;
+                        continue;
+                    }
+                    b'.' | b'b' => 0,
+                    b'o' => 1,
+                    b'A'..=b'X' => {
+                        let state =
+                            prefix.take().unwrap_or(0) as usize * 24 + (c - b'A') as usize + 1;
+                        if state > 255 {
+                            return Err("Error: Invalid RLE cell state.");
+                        }
+                        state as u8
+                    }
+                    c if c.is_ascii_whitespace() => continue,
+                    _ => return Err("Error: Invalid character in RLE body."),
+                };
+                let count = run.max(1);
+                run = 0;
+                if count > width - x || y >= height {
+                    return Err("Error: RLE pattern is bigger than its header.");
+                }
+                for _ in 0..count {
+                    ret.set(x, y, state);
+                    x += 1;
+                }
+            }
+        }
+        Ok(ret)
+    }
+
+    /// Writes the pattern with `rule` in the header, `multi_state` selects the
+    /// letters over `b` and `o`.
+    pub fn to_rle(&self, rule: &str, multi_state: bool) -> String {
+        fn token(state: u8, multi_state: bool) -> String {
+            match state {
+                0 if !multi_state => "b".to_string(),
+                1 if !multi_state => "o".to_string(),
+                0 => ".".to_string(),
+                1..=24 => char::from(b'A' + state - 1).to_string(),
+                _ => {
+                    let prefix = char::from(b'p' + (state - 25) / 24);
+                    let letter = char::from(b'A' + (state - 25) % 24);
+                    format!("{}{}", prefix, letter)
+                }
+            }
+        }
+
+        fn push_run(body: &mut Vec<String>, count: usize, token: &str) {
+            if count > 1 {
+                body.push(format!("{}{}", count, token));
+            } else if count == 1 {
+                body.push(token.to_string());
+            }
+        }
+
+        let mut body: Vec<String> = Vec::new();
+        // row the written body ends at
+        let mut row = 0;
+        for y in 0..self.height {
+            // trailing dead cells of a row are never written
+            let end = (0..self.width)
+                .rev()
+                .find(|&x| self.get(x, y) != 0)
+                .map_or(0, |x| x + 1);
+            if end == 0 {
+                continue;
+            }
+            push_run(&mut body, y - row, "$");
+            row = y;
+            let mut x = 0;
+            while x < end {
+                let state = self.get(x, y);
+                let start = x;
+                while x < end && self.get(x, y) == state {
+                    x += 1;
+                }
+                push_run(&mut body, x - start, &token(state, multi_state));
+            }
+        }
+        body.push("!".to_string());
+
+        let mut ret = format!("x = {}, y = {}, rule = {}\n", self.width, self.height, rule);
+        let mut line_len = 0;
+        for item in body {
+            if line_len + item.len() > 70 {
+                ret.push('\n');
+                line_len = 0;
+            }
+            line_len += item.len();
+            ret.push_str(&item);
+        }
+        ret.push('\n');
+        ret
+    }
+
+    /// Writes the pattern as a plain (`P1`) PBM image, 1 for every live
+    /// state, with the lines kept within 70 characters.
+    pub fn to_pbm(&self) -> String {
+        let mut ret = format!("P1\n{} {}\n", self.width, self.height);
+        for row in self.cells.chunks(self.width.max(1)) {
+            for line in row.chunks(70) {
+                ret.extend(line.iter().map(|&c| if c == 0 { '0' } else { '1' }));
+                ret.push('\n');
+            }
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn plaintext() {
+        let input = ".X2\nb9.\n";
+        let pattern = Pattern::from_plaintext(input).unwrap();
+        assert_eq!(pattern.cells, vec![0, 1, 2, 11, 9, 0]);
+        assert_eq!(pattern.to_plaintext(), input);
+        assert!(Pattern::from_plaintext("..\n...").is_err());
+        assert_eq!(split_boards("\n.X\n \n\nX.\nXX"), [".X\n", "X.\nXX\n"]);
+
+        let mut pattern = Pattern::new(2, 1);
+        pattern.set(1, 0, 36);
+        assert_eq!(pattern.to_plaintext(), ".?\n");
+    }
+
+    #[test]
+    fn obstacles() {
+        let input = "#X.\n.@#\n";
+        let pattern = Pattern::from_plaintext(input).unwrap();
+        assert_eq!(pattern.cells, vec![0, 1, 0, 0, 1, 0]);
+        let mask = pattern.mask.as_ref().unwrap();
+        let cells: Vec<_> = mask.cells().collect();
+        assert_eq!(cells, [(0, 0, 0), (2, 1, 0), (1, 1, 1)]);
+        assert_eq!(pattern.to_plaintext(), input);
+        assert!(!is_rle(input));
+        assert!(is_rle("#C a wall\nx = 1, y = 1\no!\n"));
+        assert_eq!(Pattern::from_plaintext(".X\n").unwrap().mask, None);
+    }
+
+    #[test]
+    fn rle() {
+        let rle = "#C comment\nx = 5, y = 3, rule = B2/S345/C4\n.2AB2$\n3.C!\n";
+        assert!(is_rle(rle));
+        let pattern = Pattern::from_rle(rle).unwrap();
+        assert_eq!(pattern.rule.as_deref(), Some("B2/S345/C4"));
+        assert_eq!(pattern.to_plaintext(), ".XX2.\n.....\n...3.\n");
+        assert_eq!(
+            pattern.to_rle("B2/S345/C4", true),
+            "x = 5, y = 3, rule = B2/S345/C4\n.2AB2$3.C!\n"
+        );
+
+        let pattern = Pattern::from_rle("x = 3, y = 2\nb2o$o!").unwrap();
+        assert_eq!(pattern.rule, None);
+        assert_eq!(
+            pattern.to_rle("B3/S23", false),
+            "x = 3, y = 2, rule = B3/S23\nb2o$o!\n"
+        );
+        assert!(!is_rle(".X.\n..."));
+
+        // leading empty rows
+        let pattern = Pattern::from_plaintext("...\n...\n.X.\n").unwrap();
+        let rle = pattern.to_rle("B3/S23", false);
+        assert_eq!(rle, "x = 3, y = 3, rule = B3/S23\n2$bo!\n");
+        assert_eq!(Pattern::from_rle(&rle).unwrap().cells, pattern.cells);
+    }
+
+    #[test]
+    fn rle_sizes() {
+        for header in [
+            "x = 100000000, y = 100000000",
+            "x = 4294967296, y = 4294967296",
+        ] {
+            assert!(Pattern::from_rle(&format!("{}\no!\n", header)).is_err());
+        }
+        assert!(Pattern::from_rle("x = 2, y = 1\n99999999999999999999999o!\n").is_err());
+        assert!(Pattern::from_rle("x = 2, y = 1\n99999999999999999999999$o!\n").is_err());
+        assert!(Pattern::try_new(8192, 8192).is_ok());
+        assert!(Pattern::try_new(8192, 8193).is_err());
+        assert!(Pattern::try_new(usize::MAX, 2).is_err());
+    }
+
+    #[test]
+    fn rle_long_lines() {
+        let mut pattern = Pattern::new(100, 1);
+        for x in (0..100).step_by(2) {
+            pattern.set(x, 0, 1);
+        }
+        let rle = pattern.to_rle("B3/S23", false);
+        assert!(rle.lines().all(|l| l.len() <= 70));
+        assert_eq!(Pattern::from_rle(&rle).unwrap().cells, pattern.cells);
+    }
+
+    #[test]
+    fn pbm() {
+        let pattern = Pattern::from_plaintext(".X2\nX..\n").unwrap();
+        assert_eq!(pattern.to_pbm(), "P1\n3 2\n011\n100\n");
+        let wide = Pattern::new(75, 1).to_pbm();
+        assert_eq!(
+            wide.lines().map(str::len).collect::<Vec<_>>(),
+            [2, 4, 70, 5]
+        );
+    }
+}
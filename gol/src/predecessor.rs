@@ -0,0 +1,299 @@
+//! Running a two state rule backwards: one generation encoded as CNF, so a
+//! SAT solver finds a parent of the target board or proves there is none.
+//!
+//! Cell `(x, y)` of the parent is variable `y * width + x + 1`. For each
+//! cell of the target the neighbourhoods giving the wrong state are ruled out
+//! by clauses. They come from the prime implicants of those neighbourhoods,
+//! computed once per state from the rule table, so any rule of [`Rule`] works
+//! and the unused bits of the smaller neighbourhoods drop out by themselves.
+
+use crate::pattern::Pattern;
+use crate::rule::Rule;
+use crate::sat::{self, Cnf};
+use crate::topology::Topology;
+use std::collections::HashSet;
+
+/// A set of neighbourhood indices, the bits outside `care` can be anything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Cube {
+    bits: usize,
+    care: usize,
+}
+
+impl Cube {
+    fn contains(&self, index: usize) -> bool {
+        index & self.care == self.bits
+    }
+}
+
+/// Cubes covering exactly the neighbourhoods for which the rule gives the
+/// other state than `alive`, made of prime implicants picked greedily.
+fn forbidden(rule: &Rule, alive: bool) -> Vec<Cube> {
+    // Quine-McCluskey, merging cubes which differ in one cared for bit
+    let mut cubes: HashSet<Cube> = (0..512)
+        .filter(|&i| rule.alive(i) != alive)
+        .map(|bits| Cube { bits, care: 511 })
+        .collect();
+    let mut primes: Vec<Cube> = Vec::new();
+    while !cubes.is_empty() {
+        let mut merged = HashSet::new();
+        let mut next = HashSet::new();
+        for cube in &cubes {
+            for bit in (0..9).map(|b| 1 << b) {
+                let other = Cube {
+                    bits: cube.bits | bit,
+                    care: cube.care,
+                };
+                if cube.care & bit != 0 && cube.bits & bit == 0 && cubes.contains(&other) {
+                    merged.insert(*cube);
+                    merged.insert(other);
+                    next.insert(Cube {
+                        bits: cube.bits,
+                        care: cube.care & !bit,
+                    });
+                }
+            }
+        }
+        primes.extend(cubes.difference(&merged));
+        cubes = next;
+    }
+    primes.sort_by_key(|p| (p.care, p.bits));
+
+    // greedy cover, the largest number of neighbourhoods left first
+    let bitset = |cube: &Cube| -> [u64; 8] {
+        let mut ret = [0u64; 8];
+        for i in (0..512).filter(|&i| cube.contains(i)) {
+            ret[i / 64] |= 1 << (i % 64);
+        }
+        ret
+    };
+    let covers: Vec<[u64; 8]> = primes.iter().map(bitset).collect();
+    let mut left = [0u64; 8];
+    for cover in &covers {
+        for (l, c) in left.iter_mut().zip(cover) {
+            *l |= c;
+        }
+    }
+    let mut ret = Vec::new();
+    while left.iter().any(|&l| l != 0) {
+        let count = |cover: &[u64; 8]| -> u32 {
+            cover
+                .iter()
+                .zip(&left)
+                .map(|(c, l)| (c & l).count_ones())
+                .sum()
+        };
+        let best = (0..primes.len())
+            .max_by_key(|&i| count(&covers[i]))
+            .unwrap();
+        for (l, c) in left.iter_mut().zip(&covers[best]) {
+            *l &= !c;
+        }
+        ret.push(primes[best]);
+    }
+    ret
+}
+
+/// A cell in a formula, known or a variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Dead,
+    Alive,
+    Var(i32),
+}
+
+/// The clauses of a rule, for any number of targets.
+pub struct Encoder {
+    /// Neighbourhoods ruled out for a dead and for a living next state.
+    cubes: [Vec<Cube>; 2],
+}
+
+impl Encoder {
+    pub fn new(rule: &Rule) -> Self {
+        Encoder {
+            cubes: [forbidden(rule, false), forbidden(rule, true)],
+        }
+    }
+
+    /// Adds the clauses making `next` the state the rule gives the cell with
+    /// the neighbourhood, in the bit order of the neighbourhood index.
+    pub fn transition(&self, cnf: &mut Cnf, neighbourhood: &[Cell; 9], next: Cell) {
+        for alive in [false, true] {
+            // the literal of the clause which holds when next isn't alive
+            let condition = match next {
+                Cell::Var(var) if alive => Some(-var),
+                Cell::Var(var) => Some(var),
+                Cell::Alive if !alive => continue,
+                Cell::Dead if alive => continue,
+                _ => None,
+            };
+            'cubes: for cube in &self.cubes[alive as usize] {
+                // not all of the bits as in the cube
+                let mut clause: Vec<i32> = condition.into_iter().collect();
+                for (bit, cell) in neighbourhood.iter().enumerate() {
+                    if cube.care >> bit & 1 == 0 {
+                        continue;
+                    }
+                    let set = cube.bits >> bit & 1 == 1;
+                    let lit = match *cell {
+                        Cell::Var(var) if set => -var,
+                        Cell::Var(var) => var,
+                        Cell::Dead if set => continue 'cubes,
+                        Cell::Alive if !set => continue 'cubes,
+                        _ => continue,
+                    };
+                    // on a small torus the same cell is several neighbours
+                    if clause.contains(&-lit) {
+                        continue 'cubes;
+                    }
+                    if !clause.contains(&lit) {
+                        clause.push(lit);
+                    }
+                }
+                cnf.add(clause);
+            }
+        }
+    }
+
+    /// The formula satisfied by the parents of the target, living cells are
+    /// the ones in a state other than 0. Outside a bounded board cells are
+    /// dead.
+    pub fn encode(&self, target: &Pattern, topology: Topology) -> Cnf {
+        let (width, height) = (target.width, target.height);
+        let mut cnf = Cnf::new(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut neighbourhood = [Cell::Dead; 9];
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let nx = topology.offset(x, dx, width);
+                        let ny = topology.offset(y, dy, height);
+                        if let (Some(nx), Some(ny)) = (nx, ny) {
+                            let bit = ((dy + 1) * 3 + dx + 1) as usize;
+                            neighbourhood[bit] = Cell::Var((ny * width + nx + 1) as i32);
+                        }
+                    }
+                }
+                let next = if target.get(x, y) != 0 {
+                    Cell::Alive
+                } else {
+                    Cell::Dead
+                };
+                self.transition(&mut cnf, &neighbourhood, next);
+            }
+        }
+        cnf
+    }
+
+    /// A board which becomes the target in one generation, `None` if the
+    /// target has no parent of its size, as for a Garden of Eden.
+    pub fn predecessor(&self, target: &Pattern, topology: Topology) -> Option<Pattern> {
+        let model = sat::solve(&self.encode(target, topology))?;
+        Some(decode(&model, target.width, target.height))
+    }
+}
+
+/// The parent of the size of the target in a model of [`Encoder::encode`].
+pub fn decode(model: &[bool], width: usize, height: usize) -> Pattern {
+    let mut ret = Pattern::new(width, height);
+    for (cell, &alive) in ret.cells.iter_mut().zip(model) {
+        *cell = alive as u8;
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn step(pattern: &Pattern, rule: &Rule, topology: Topology) -> Pattern {
+        let mut ret = Pattern::new(pattern.width, pattern.height);
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                let mut index = 0;
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let nx = topology.offset(x, dx, pattern.width);
+                        let ny = topology.offset(y, dy, pattern.height);
+                        if let (Some(nx), Some(ny)) = (nx, ny) {
+                            if pattern.get(nx, ny) != 0 {
+                                index |= 1 << ((dy + 1) * 3 + dx + 1);
+                            }
+                        }
+                    }
+                }
+                ret.set(x, y, rule.alive(index) as u8);
+            }
+        }
+        ret
+    }
+
+    /// Every board of the size against the children of all of them.
+    fn exhaustive(width: usize, height: usize, rule: &str, topology: Topology) {
+        let rule: Rule = rule.parse().unwrap();
+        let cells = width * height;
+        let board = |bits: usize| {
+            let mut ret = Pattern::new(width, height);
+            for (i, cell) in ret.cells.iter_mut().enumerate() {
+                *cell = (bits >> i & 1) as u8;
+            }
+            ret
+        };
+        let mut children = vec![false; 1 << cells];
+        for bits in 0..1 << cells {
+            let child = step(&board(bits), &rule, topology);
+            let index: usize = (0..cells).map(|i| (child.cells[i] as usize) << i).sum();
+            children[index] = true;
+        }
+        let encoder = Encoder::new(&rule);
+        for (bits, &child) in children.iter().enumerate() {
+            let target = board(bits);
+            match encoder.predecessor(&target, topology) {
+                Some(parent) => assert_eq!(step(&parent, &rule, topology), target),
+                None => assert!(!child, "{:?}", target),
+            }
+        }
+    }
+
+    #[test]
+    fn life() {
+        exhaustive(3, 3, "B3/S23", Topology::Bounded);
+        exhaustive(4, 3, "B3/S23", Topology::Bounded);
+        exhaustive(3, 3, "B3/S23", Topology::Torus);
+        // the left and right neighbours are the same cell
+        exhaustive(2, 4, "B3/S23", Topology::Torus);
+    }
+
+    #[test]
+    fn other_rules() {
+        exhaustive(3, 3, "B36/S23", Topology::Bounded);
+        exhaustive(3, 3, "B2-a/S12", Topology::Bounded);
+        exhaustive(3, 3, "B2/S1V", Topology::Bounded);
+        exhaustive(3, 3, "B0/S8", Topology::Torus);
+    }
+
+    #[test]
+    fn larger() {
+        let rule = Rule::default();
+        let encoder = Encoder::new(&rule);
+        let target = Pattern::from_plaintext(
+            "........\n\
+             ..X.....\n\
+             ...X....\n\
+             .XXX....\n\
+             ........\n\
+             .....XX.\n\
+             .....XX.\n\
+             ........\n",
+        )
+        .unwrap();
+        for topology in [Topology::Bounded, Topology::Torus] {
+            let parent = encoder.predecessor(&target, topology).unwrap();
+            assert_eq!(step(&parent, &rule, topology), target);
+        }
+        // nothing leads to a full box
+        let full = Pattern::from_plaintext("XXX\nXXX\nXXX\n").unwrap();
+        assert_eq!(encoder.predecessor(&full, Topology::Bounded), None);
+        assert_eq!(encoder.encode(&full, Topology::Bounded).vars, 9);
+    }
+}
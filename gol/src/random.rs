@@ -0,0 +1,94 @@
+//! A small seeded random number generator, xoshiro256** seeded through
+//! SplitMix64. The same seed gives the same numbers on every platform, which
+//! is all the soups and stochastic rules need.
+
+/// One step of SplitMix64, used to spread the seed over the state.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Random {
+    s: [u64; 4],
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        let mut state = seed;
+        Random {
+            s: [(); 4].map(|_| splitmix64(&mut state)),
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let ret = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        ret
+    }
+
+    /// Uniform in `[0, 1)`, with 53 random bits.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+
+    /// True with probability `p`, always for 1 and never for 0.
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+
+    /// Uniform in `0..n`, `n` has to be positive.
+    pub fn below(&mut self, n: u64) -> u64 {
+        // rejecting the incomplete last range keeps it unbiased
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let r = self.next_u64();
+            if r < zone {
+                return r % n;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reference() {
+        let mut state = 0;
+        assert_eq!(splitmix64(&mut state), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(splitmix64(&mut state), 0x6E78_9E6A_A1B9_65F4);
+        // xoshiro256** from the state 1, 2, 3, 4 of the reference code
+        let mut random = Random { s: [1, 2, 3, 4] };
+        let first: Vec<u64> = (0..3).map(|_| random.next_u64()).collect();
+        assert_eq!(first, [11520, 0, 1509978240]);
+    }
+
+    #[test]
+    fn deterministic() {
+        let numbers = |seed| {
+            let mut random = Random::new(seed);
+            (0..8).map(|_| random.next_u64()).collect::<Vec<_>>()
+        };
+        assert_eq!(numbers(42), numbers(42));
+        assert_ne!(numbers(42), numbers(43));
+
+        let mut random = Random::new(7);
+        let hits = (0..10000).filter(|_| random.chance(0.25)).count();
+        assert!((2300..2700).contains(&hits), "{}", hits);
+        assert!(!random.chance(0.0));
+        assert!(random.chance(1.0));
+        assert!((0..1000).all(|_| random.below(3) < 3));
+    }
+}
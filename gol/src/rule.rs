@@ -0,0 +1,498 @@
+//! Rulestrings compiled into a lookup table indexed by the whole 3x3
+//! neighbourhood, so outer totalistic (`B3/S23`) and isotropic non-totalistic
+//! rules in Hensel notation (`B2-a/S12`) are handled the same way.
+//!
+//! The neighbourhood index has bit `(dy + 1) * 3 + (dx + 1)` set when the
+//! cell at offset `(dx, dy)` is alive, so bit 0 is the north west neighbour,
+//! bit 4 the cell itself and bit 8 the south east neighbour. Rules on the
+//! smaller von Neumann and hexagonal neighbourhoods ignore the extra bits, so
+//! engines can always pass the whole 3x3 neighbourhood.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Bit of the cell itself in the neighbourhood index.
+pub const CENTRE: usize = 1 << 4;
+/// All the neighbour bits of the neighbourhood index.
+pub const NEIGHBOURS: usize = 0b111_101_111;
+
+/// Hensel letters valid for each neighbour count, in canonical order.
+const LETTERS: [&str; 9] = [
+    "",
+    "ce",
+    "cekain",
+    "cekainyqjr",
+    "cekainyqjrtwz",
+    "cekainyqjr",
+    "cekain",
+    "ce",
+    "",
+];
+
+/// Returns the bit of the neighbourhood index for the cell at offset `(dx, dy)`.
+pub const fn bit(dx: i64, dy: i64) -> usize {
+    1 << ((dy + 1) * 3 + dx + 1)
+}
+
+/// Offsets of the eight surrounding cells.
+pub const MOORE: [(i64, i64); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+/// Offsets of the four orthogonally adjacent cells.
+pub const VON_NEUMANN: [(i64, i64); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+/// Offsets of the six cells of a hexagonal grid emulated on the square one,
+/// the north east and south west corners are ignored. Shown as hexagons every
+/// row is shifted by half a cell to the left against the row above, see
+/// [`shear`].
+pub const HEXAGONAL: [(i64, i64); 6] = [(-1, -1), (0, -1), (-1, 0), (1, 0), (0, 1), (1, 1)];
+
+/// Which of the surrounding cells count as neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Neighbourhood {
+    #[default]
+    Moore,
+    /// `V` suffix of the rulestring.
+    VonNeumann,
+    /// `H` suffix of the rulestring.
+    Hexagonal,
+}
+
+impl Neighbourhood {
+    pub fn offsets(&self) -> &'static [(i64, i64)] {
+        match self {
+            Neighbourhood::Moore => &MOORE,
+            Neighbourhood::VonNeumann => &VON_NEUMANN,
+            Neighbourhood::Hexagonal => &HEXAGONAL,
+        }
+    }
+
+    /// The neighbour bits of the neighbourhood index.
+    pub fn mask(&self) -> usize {
+        self.offsets().iter().map(|&(dx, dy)| bit(dx, dy)).sum()
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            Neighbourhood::Moore => "",
+            Neighbourhood::VonNeumann => "V",
+            Neighbourhood::Hexagonal => "H",
+        }
+    }
+}
+
+/// Renders plaintext output (`.`/`X` lines) of a hexagonal rule as a
+/// hexagonal grid, cells are separated by spaces and every row is indented by
+/// half a cell more than the row below.
+pub fn shear(plain: &str) -> String {
+    let rows: Vec<&str> = plain.lines().filter(|l| !l.is_empty()).collect();
+    let mut ret = String::new();
+    for (y, row) in rows.iter().enumerate() {
+        ret.push_str(&" ".repeat(rows.len() - 1 - y));
+        let cells: Vec<String> = row.chars().map(String::from).collect();
+        ret.push_str(&cells.join(" "));
+        ret.push('\n');
+    }
+    ret
+}
+
+/// One neighbourhood configuration of the given Hensel letter. Counts above
+/// four are the complements of their `8 - count` counterparts.
+fn representative(count: usize, letter: char) -> usize {
+    if count > 4 {
+        return NEIGHBOURS ^ representative(8 - count, letter);
+    }
+    let (letters, masks): (&str, &[usize]) = match count {
+        1 => ("ce", &[1, 2]),
+        2 => ("ceaikn", &[5, 10, 3, 40, 33, 68]),
+        3 => ("ceaiknjqry", &[69, 42, 11, 7, 98, 13, 14, 70, 41, 97]),
+        _ => (
+            "ceaiknjqrytwz",
+            &[325, 170, 15, 45, 99, 71, 106, 102, 43, 101, 105, 78, 108],
+        ),
+    };
+    masks[letters.find(letter).unwrap()]
+}
+
+/// All rotations and reflections of a neighbourhood.
+fn symmetries(neighbourhood: usize) -> [usize; 8] {
+    let transform = |f: fn(i64, i64) -> (i64, i64)| {
+        let mut ret = 0;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                if neighbourhood & bit(dx, dy) != 0 {
+                    let (tx, ty) = f(dx, dy);
+                    ret |= bit(tx, ty);
+                }
+            }
+        }
+        ret
+    };
+    [
+        transform(|x, y| (x, y)),
+        transform(|x, y| (-y, x)),
+        transform(|x, y| (-x, -y)),
+        transform(|x, y| (y, -x)),
+        transform(|x, y| (-x, y)),
+        transform(|x, y| (x, -y)),
+        transform(|x, y| (y, x)),
+        transform(|x, y| (-y, -x)),
+    ]
+}
+
+/// Returns the Hensel letter of the neighbour configuration, `None` for 0 and
+/// 8 neighbours which have just one configuration.
+pub fn letter(neighbourhood: usize) -> Option<char> {
+    let neighbourhood = neighbourhood & NEIGHBOURS;
+    let count = neighbourhood.count_ones() as usize;
+    LETTERS[count]
+        .chars()
+        .find(|&l| symmetries(representative(count, l)).contains(&neighbourhood))
+}
+
+/// Cellular automaton rule as a lookup table of the next state for each of
+/// the 512 neighbourhoods.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Rule {
+    neighbourhood: Neighbourhood,
+    table: [bool; 512],
+}
+
+impl Rule {
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    /// Whether the cell with given neighbourhood index is alive next generation.
+    #[inline(always)]
+    pub fn alive(&self, neighbourhood: usize) -> bool {
+        self.table[neighbourhood]
+    }
+
+    /// Whether this is the standard `B3/S23`, for engines with a dedicated
+    /// implementation of it.
+    pub fn is_life(&self) -> bool {
+        *self == Rule::default()
+    }
+
+    /// Parses one half of the rulestring, e.g. `2-a3` into the table.
+    fn parse_transitions(&mut self, s: &str, centre: usize) -> Result<(), &'static str> {
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            let mask = self.neighbourhood.mask();
+            let count = match c.to_digit(10) {
+                Some(n) if n <= mask.count_ones() => n as usize,
+                _ => return Err("Error: Invalid neighbour count in rule."),
+            };
+            let negate = chars.next_if_eq(&'-').is_some();
+            let mut letters = String::new();
+            while let Some(l) = chars.next_if(|c| c.is_ascii_lowercase()) {
+                if self.neighbourhood != Neighbourhood::Moore {
+                    return Err("Error: Hensel letters need the Moore neighbourhood.");
+                }
+                if !LETTERS[count].contains(l) {
+                    return Err("Error: Invalid Hensel letter for the neighbour count.");
+                }
+                letters.push(l);
+            }
+            if negate && letters.is_empty() {
+                return Err("Error: Expected Hensel letters after '-'.");
+            }
+            for neighbourhood in 0..512 {
+                if neighbourhood & CENTRE != 0
+                    || (neighbourhood & mask).count_ones() as usize != count
+                {
+                    continue;
+                }
+                let included = match letter(neighbourhood) {
+                    Some(l) if !letters.is_empty() => letters.contains(l) != negate,
+                    _ => true,
+                };
+                if included {
+                    self.table[neighbourhood | centre] = true;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes one half of the rulestring in canonical form, negated letters
+    /// are used when they are shorter.
+    fn fmt_transitions(&self, f: &mut fmt::Formatter<'_>, centre: usize) -> fmt::Result {
+        if self.neighbourhood != Neighbourhood::Moore {
+            // any `count` neighbours of the neighbourhood will do
+            let mut neighbourhood = 0;
+            for (count, (dx, dy)) in self.neighbourhood.offsets().iter().enumerate() {
+                if self.table[neighbourhood | centre] {
+                    write!(f, "{}", count)?;
+                }
+                neighbourhood |= bit(*dx, *dy);
+            }
+            if self.table[neighbourhood | centre] {
+                write!(f, "{}", self.neighbourhood.offsets().len())?;
+            }
+            return Ok(());
+        }
+        for (count, letters) in LETTERS.iter().enumerate() {
+            if letters.is_empty() {
+                let neighbourhood = if count == 0 { 0 } else { NEIGHBOURS };
+                if self.table[neighbourhood | centre] {
+                    write!(f, "{}", count)?;
+                }
+                continue;
+            }
+            let (present, absent): (String, String) = letters
+                .chars()
+                .partition(|&l| self.table[representative(count, l) | centre]);
+            if absent.is_empty() {
+                write!(f, "{}", count)?;
+            } else if present.len() <= absent.len() {
+                if !present.is_empty() {
+                    write!(f, "{}{}", count, present)?;
+                }
+            } else {
+                write!(f, "{}-{}", count, absent)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        "B3/S23".parse().unwrap()
+    }
+}
+
+/// Accepts `B3/S23` (in either order, any case) and the older `23/3` (S/B)
+/// notation. Counts can be followed by Hensel letters, optionally negated
+/// with `-`, e.g. `B2-a/S12` or `B2ce3/S23-k`. Trailing `V` or `H` selects
+/// the von Neumann or hexagonal neighbourhood, e.g. `B2/S34H`.
+impl FromStr for Rule {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (s, neighbourhood) = if let Some(s) = s.strip_suffix(['V', 'v']) {
+            (s, Neighbourhood::VonNeumann)
+        } else if let Some(s) = s.strip_suffix(['H', 'h']) {
+            (s, Neighbourhood::Hexagonal)
+        } else {
+            (s, Neighbourhood::Moore)
+        };
+        let mut rule = Rule {
+            neighbourhood,
+            table: [false; 512],
+        };
+        let parts: Vec<&str> = s.split('/').collect();
+        if parts.len() != 2 {
+            return Err("Error: Expected rule in B/S or S/B notation.");
+        }
+        let prefixed = |p: &str| p.starts_with(|c: char| c.is_ascii_alphabetic());
+        if prefixed(parts[0]) || prefixed(parts[1]) {
+            for part in parts {
+                let mut chars = part.chars();
+                match chars.next() {
+                    Some('B' | 'b') => rule.parse_transitions(chars.as_str(), 0)?,
+                    Some('S' | 's') => rule.parse_transitions(chars.as_str(), CENTRE)?,
+                    _ => return Err("Error: Unknown rule part, expected B or S."),
+                }
+            }
+        } else {
+            rule.parse_transitions(parts[0], CENTRE)?;
+            rule.parse_transitions(parts[1], 0)?;
+        }
+        Ok(rule)
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "B")?;
+        self.fmt_transitions(f, 0)?;
+        write!(f, "/S")?;
+        self.fmt_transitions(f, CENTRE)?;
+        write!(f, "{}", self.neighbourhood.suffix())
+    }
+}
+
+impl fmt::Debug for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Rule({})", self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn neighbourhood(cells: &[(i64, i64)]) -> usize {
+        cells.iter().map(|&(dx, dy)| bit(dx, dy)).sum()
+    }
+
+    #[test]
+    fn life() {
+        let rule = Rule::default();
+        for n in 0..512 {
+            let count = (n & NEIGHBOURS).count_ones();
+            let expected = count == 3 || (count == 2 && n & CENTRE != 0);
+            assert_eq!(rule.alive(n), expected);
+        }
+        assert!(rule.is_life());
+        assert_eq!(rule.to_string(), "B3/S23");
+        assert_eq!("23/3".parse::<Rule>().unwrap(), rule);
+        assert_eq!("s23/b3".parse::<Rule>().unwrap(), rule);
+        // all letters of a count are the same as the totalistic count
+        assert_eq!("B3cekainyqjr/S2cekain3".parse::<Rule>().unwrap(), rule);
+    }
+
+    #[test]
+    fn letters_partition_counts() {
+        for (count, letters) in LETTERS.iter().enumerate().take(8).skip(1) {
+            let mut seen = [false; 512];
+            for l in letters.chars() {
+                for n in symmetries(representative(count, l)) {
+                    assert_eq!((n & NEIGHBOURS).count_ones() as usize, count);
+                    assert_eq!(letter(n), Some(l));
+                    seen[n] = true;
+                }
+            }
+            let total = (0..512usize)
+                .filter(|n| n & !NEIGHBOURS == 0 && n.count_ones() as usize == count)
+                .count();
+            assert_eq!(seen.iter().filter(|&&s| s).count(), total);
+        }
+    }
+
+    #[test]
+    fn canonical_transitions() {
+        // 1c corner, 1e edge
+        assert_eq!(letter(neighbourhood(&[(-1, -1)])), Some('c'));
+        assert_eq!(letter(neighbourhood(&[(0, 1)])), Some('e'));
+        // 2c, 2e, 2k, 2a, 2i, 2n
+        assert_eq!(letter(neighbourhood(&[(-1, -1), (1, -1)])), Some('c'));
+        assert_eq!(letter(neighbourhood(&[(0, -1), (1, 0)])), Some('e'));
+        assert_eq!(letter(neighbourhood(&[(-1, -1), (1, 0)])), Some('k'));
+        assert_eq!(letter(neighbourhood(&[(-1, -1), (0, -1)])), Some('a'));
+        assert_eq!(letter(neighbourhood(&[(0, -1), (0, 1)])), Some('i'));
+        assert_eq!(letter(neighbourhood(&[(-1, -1), (1, 1)])), Some('n'));
+        // 3i is a line along a side, 3y the Y shape
+        assert_eq!(letter(neighbourhood(&[(-1, 1), (0, 1), (1, 1)])), Some('i'));
+        assert_eq!(
+            letter(neighbourhood(&[(-1, -1), (1, -1), (0, 1)])),
+            Some('y')
+        );
+        // 4t, 4z
+        let t = neighbourhood(&[(-1, -1), (0, -1), (1, -1), (0, 1)]);
+        assert_eq!(letter(t), Some('t'));
+        let z = neighbourhood(&[(-1, -1), (0, -1), (0, 1), (1, 1)]);
+        assert_eq!(letter(z), Some('z'));
+        // 5x is the complement of 3x
+        assert_eq!(
+            letter(NEIGHBOURS ^ neighbourhood(&[(-1, 1), (0, 1), (1, 1)])),
+            Some('i')
+        );
+        assert_eq!(letter(0), None);
+        assert_eq!(letter(NEIGHBOURS), None);
+    }
+
+    #[test]
+    fn hensel_rule() {
+        let rule: Rule = "B2-a/S12".parse().unwrap();
+        assert!(!rule.is_life());
+        // 2a doesn't give birth, other 2 neighbour configurations do
+        assert!(!rule.alive(neighbourhood(&[(-1, -1), (0, -1)])));
+        assert!(!rule.alive(neighbourhood(&[(1, 0), (1, 1)])));
+        assert!(rule.alive(neighbourhood(&[(-1, -1), (1, -1)])));
+        assert!(rule.alive(neighbourhood(&[(0, -1), (0, 1)])));
+        assert!(!rule.alive(neighbourhood(&[(0, -1), (0, 1), (1, 1)])));
+        // survival is totalistic
+        assert!(rule.alive(CENTRE | neighbourhood(&[(1, 1)])));
+        assert!(rule.alive(CENTRE | neighbourhood(&[(-1, -1), (0, -1)])));
+        assert!(!rule.alive(CENTRE));
+        assert_eq!(rule.to_string(), "B2-a/S12");
+
+        // tlife
+        let rule: Rule = "B3/S2-i34q".parse().unwrap();
+        assert!(!rule.alive(CENTRE | neighbourhood(&[(0, -1), (0, 1)])));
+        assert!(rule.alive(CENTRE | neighbourhood(&[(0, -1), (1, 0)])));
+        assert_eq!(rule.to_string(), "B3/S2-i34q");
+    }
+
+    #[test]
+    fn canonical_form() {
+        let rule: Rule = "B2ae3aijr/S1c".parse().unwrap();
+        assert_eq!(rule.to_string(), "B2ea3aijr/S1c");
+        assert_eq!(rule.to_string().parse::<Rule>().unwrap(), rule);
+        // more than half the letters are written negated
+        let rule: Rule = "B2cekai/S".parse().unwrap();
+        assert_eq!(rule.to_string(), "B2-n/S");
+        assert_eq!("B0/S8".parse::<Rule>().unwrap().to_string(), "B0/S8");
+    }
+
+    #[test]
+    fn invalid() {
+        assert!("B2x/S23".parse::<Rule>().is_err());
+        assert!("B1k/S23".parse::<Rule>().is_err());
+        assert!("B9/S23".parse::<Rule>().is_err());
+        assert!("B2-/S23".parse::<Rule>().is_err());
+        assert!("B3/S23/C3".parse::<Rule>().is_err());
+        assert!("X3/S23".parse::<Rule>().is_err());
+        assert!("".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn isotropic() {
+        let rule: Rule = "B2-a3j4wz/S1e2k5".parse().unwrap();
+        for n in 0..512 {
+            let centre = n & CENTRE;
+            for s in symmetries(n & NEIGHBOURS) {
+                assert_eq!(rule.alive(n), rule.alive(s | centre));
+            }
+        }
+    }
+
+    #[test]
+    fn von_neumann() {
+        let rule: Rule = "B1/S1V".parse().unwrap();
+        assert_eq!(rule.neighbourhood(), Neighbourhood::VonNeumann);
+        assert_eq!(rule.to_string(), "B1/S1V");
+        // diagonal cells are ignored
+        assert!(rule.alive(bit(0, -1)));
+        assert!(rule.alive(bit(0, -1) | bit(1, 1) | bit(-1, 1)));
+        assert!(!rule.alive(bit(1, 1)));
+        assert!(!rule.alive(bit(0, -1) | bit(0, 1)));
+        assert!(rule.alive(CENTRE | bit(-1, 0) | bit(-1, -1)));
+        assert_ne!(rule, "B1/S1".parse::<Rule>().unwrap());
+
+        assert!("B5/S1V".parse::<Rule>().is_err());
+        assert!("B2a/S1V".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn hexagonal() {
+        let rule: Rule = "B2/S34H".parse().unwrap();
+        assert_eq!(rule.neighbourhood(), Neighbourhood::Hexagonal);
+        assert_eq!(rule.to_string(), "B2/S34H");
+        assert_eq!("34/2h".parse::<Rule>().unwrap(), rule);
+        // north east and south west corners are ignored
+        assert!(rule.alive(bit(-1, -1) | bit(1, 1)));
+        assert!(!rule.alive(bit(1, -1) | bit(-1, 1)));
+        assert!(rule.alive(bit(1, -1) | bit(-1, 1) | bit(0, 1) | bit(1, 0)));
+        assert!(!rule.alive(bit(-1, -1) | bit(1, 1) | bit(0, 1)));
+        assert!("B7/S34H".parse::<Rule>().is_err());
+        assert_eq!(Neighbourhood::Hexagonal.mask().count_ones(), 6);
+    }
+
+    #[test]
+    fn shear_rows() {
+        assert_eq!(shear("X..\n.X.\n..X\n"), "  X . .\n . X .\n. . X\n");
+        assert_eq!(shear("X"), "X\n");
+    }
+}
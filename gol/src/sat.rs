@@ -0,0 +1,582 @@
+//! A small CDCL SAT solver with DIMACS input and output, enough for the
+//! predecessor search without any dependencies.
+//!
+//! Literals are DIMACS style in the formula, variable `v` (from 1) is `v`
+//! and its negation `-v`. The solver uses two watched literals, first UIP
+//! clause learning, VSIDS style variable activities in a heap with phase saving and
+//! Luby restarts. Learnt clauses are kept, the formulas are small.
+
+use std::fmt::Write;
+
+/// A formula in conjunctive normal form.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Cnf {
+    pub vars: usize,
+    pub clauses: Vec<Vec<i32>>,
+}
+
+impl Cnf {
+    pub fn new(vars: usize) -> Self {
+        Cnf {
+            vars,
+            clauses: Vec::new(),
+        }
+    }
+
+    /// Adds a new variable and returns it.
+    pub fn var(&mut self) -> i32 {
+        self.vars += 1;
+        self.vars as i32
+    }
+
+    pub fn add(&mut self, clause: Vec<i32>) {
+        debug_assert!(clause
+            .iter()
+            .all(|&l| l != 0 && l.unsigned_abs() as usize <= self.vars));
+        self.clauses.push(clause);
+    }
+
+    pub fn to_dimacs(&self) -> String {
+        let mut ret = format!("p cnf {} {}\n", self.vars, self.clauses.len());
+        for clause in &self.clauses {
+            for lit in clause {
+                write!(ret, "{} ", lit).unwrap();
+            }
+            ret.push_str("0\n");
+        }
+        ret
+    }
+
+    pub fn from_dimacs(s: &str) -> Result<Self, &'static str> {
+        let mut ret = None;
+        let mut clause = Vec::new();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('c') || line.starts_with('%') {
+                continue;
+            }
+            if let Some(header) = line.strip_prefix("p cnf") {
+                let vars = header.split_whitespace().next();
+                let vars = vars
+                    .and_then(|v| v.parse().ok())
+                    .ok_or("Error: Invalid DIMACS header.")?;
+                ret = Some(Cnf::new(vars));
+                continue;
+            }
+            let cnf = ret
+                .as_mut()
+                .ok_or("Error: DIMACS clause before the header.")?;
+            for lit in line.split_whitespace() {
+                let lit: i32 = lit.parse().map_err(|_| "Error: Invalid DIMACS literal.")?;
+                if lit == 0 {
+                    cnf.clauses.push(std::mem::take(&mut clause));
+                } else if lit.unsigned_abs() as usize > cnf.vars {
+                    return Err("Error: DIMACS literal beyond the variables.");
+                } else {
+                    clause.push(lit);
+                }
+            }
+        }
+        let mut ret = ret.ok_or("Error: Missing DIMACS header.")?;
+        if !clause.is_empty() {
+            ret.clauses.push(clause);
+        }
+        Ok(ret)
+    }
+}
+
+/// Reads the output of a solver in the format of the SAT competitions, an
+/// `s SATISFIABLE` or `s UNSATISFIABLE` line and the model on `v` lines.
+/// Variables missing from the model are false.
+pub fn parse_model(s: &str, vars: usize) -> Result<Option<Vec<bool>>, &'static str> {
+    let mut status = None;
+    let mut model = vec![false; vars];
+    for line in s.lines().map(str::trim) {
+        if let Some(s) = line.strip_prefix("s ") {
+            status = Some(s.trim() == "SATISFIABLE");
+        } else if let Some(values) = line.strip_prefix("v ") {
+            for lit in values.split_whitespace() {
+                let lit: i32 = lit
+                    .parse()
+                    .map_err(|_| "Error: Invalid literal in the model.")?;
+                if lit.unsigned_abs() as usize > vars {
+                    return Err("Error: Model literal beyond the variables.");
+                }
+                if lit > 0 {
+                    model[lit as usize - 1] = true;
+                }
+            }
+        }
+    }
+    match status {
+        Some(true) => Ok(Some(model)),
+        Some(false) => Ok(None),
+        None => Err("Error: Missing solution line in the model."),
+    }
+}
+
+/// Solver literal, `2 * var + negated` with variables from 0.
+type Lit = usize;
+
+fn lit(dimacs: i32) -> Lit {
+    (dimacs.unsigned_abs() as usize - 1) * 2 + (dimacs < 0) as usize
+}
+
+/// Values of the literals and variables.
+const UNDEF: i8 = 0;
+const TRUE: i8 = 1;
+const FALSE: i8 = -1;
+
+struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    /// Clauses watching each literal, visited when it becomes false.
+    watches: Vec<Vec<usize>>,
+    /// Value of each variable.
+    values: Vec<i8>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    /// Start of each decision level in the trail.
+    trail_lim: Vec<usize>,
+    /// Next trail entry to propagate.
+    head: usize,
+    activity: Vec<f64>,
+    bump: f64,
+    /// Binary max heap of variables by activity, with the position of each
+    /// variable in it, holding at least the unassigned ones.
+    heap: Vec<usize>,
+    positions: Vec<Option<usize>>,
+    /// Last value of each variable, reused when deciding.
+    phases: Vec<bool>,
+    seen: Vec<bool>,
+}
+
+impl Solver {
+    fn new(vars: usize) -> Self {
+        Solver {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); vars * 2],
+            values: vec![UNDEF; vars],
+            levels: vec![0; vars],
+            reasons: vec![None; vars],
+            trail: Vec::new(),
+            trail_lim: Vec::new(),
+            head: 0,
+            activity: vec![0.0; vars],
+            bump: 1.0,
+            heap: (0..vars).collect(),
+            positions: (0..vars).map(Some).collect(),
+            phases: vec![false; vars],
+            seen: vec![false; vars],
+        }
+    }
+
+    fn value(&self, lit: Lit) -> i8 {
+        let value = self.values[lit / 2];
+        if lit & 1 == 1 {
+            -value
+        } else {
+            value
+        }
+    }
+
+    fn level(&self) -> usize {
+        self.trail_lim.len()
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit / 2;
+        self.values[var] = if lit & 1 == 1 { FALSE } else { TRUE };
+        self.levels[var] = self.level();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Adds a clause of two or more literals, the first two are watched.
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let i = self.clauses.len();
+        self.watches[clause[0]].push(i);
+        self.watches[clause[1]].push(i);
+        self.clauses.push(clause);
+        i
+    }
+
+    /// Unit propagation, returns the conflicting clause if there is one.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.head < self.trail.len() {
+            let falsified = self.trail[self.head] ^ 1;
+            self.head += 1;
+            let mut watches = std::mem::take(&mut self.watches[falsified]);
+            let mut i = 0;
+            while i < watches.len() {
+                let c = watches[i];
+                let clause = &mut self.clauses[c];
+                if clause[0] == falsified {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                if self.value(first) == TRUE {
+                    i += 1;
+                    continue;
+                }
+                // look for another literal to watch
+                let clause = &self.clauses[c];
+                if let Some(k) = (2..clause.len()).find(|&k| self.value(clause[k]) != FALSE) {
+                    let clause = &mut self.clauses[c];
+                    clause.swap(1, k);
+                    self.watches[clause[1]].push(c);
+                    watches.swap_remove(i);
+                    continue;
+                }
+                if self.value(first) == FALSE {
+                    self.watches[falsified] = watches;
+                    return Some(c);
+                }
+                self.assign(first, Some(c));
+                i += 1;
+            }
+            self.watches[falsified] = watches;
+        }
+        None
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.bump;
+        if self.activity[var] > 1e100 {
+            self.activity.iter_mut().for_each(|a| *a *= 1e-100);
+            self.bump *= 1e-100;
+        }
+        if let Some(i) = self.positions[var] {
+            self.sift_up(i);
+        }
+    }
+
+    fn heap_swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.positions[self.heap[i]] = Some(i);
+        self.positions[self.heap[j]] = Some(j);
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.activity[self.heap[parent]] >= self.activity[self.heap[i]] {
+                break;
+            }
+            self.heap_swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let mut largest = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < self.heap.len()
+                    && self.activity[self.heap[child]] > self.activity[self.heap[largest]]
+                {
+                    largest = child;
+                }
+            }
+            if largest == i {
+                break;
+            }
+            self.heap_swap(i, largest);
+            i = largest;
+        }
+    }
+
+    fn heap_insert(&mut self, var: usize) {
+        if self.positions[var].is_none() {
+            self.heap.push(var);
+            self.positions[var] = Some(self.heap.len() - 1);
+            self.sift_up(self.heap.len() - 1);
+        }
+    }
+
+    fn heap_pop(&mut self) -> Option<usize> {
+        let last = self.heap.len().checked_sub(1)?;
+        self.heap_swap(0, last);
+        let var = self.heap.pop().unwrap();
+        self.positions[var] = None;
+        self.sift_down(0);
+        Some(var)
+    }
+
+    /// First UIP learning, returns the learnt clause with the asserting
+    /// literal first and the level to go back to.
+    fn analyze(&mut self, mut conflict: usize) -> (Vec<Lit>, usize) {
+        let mut learnt = vec![0];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut asserting = None;
+        loop {
+            // the first literal of a reason is the one it implied
+            let skip = asserting.is_some() as usize;
+            for k in skip..self.clauses[conflict].len() {
+                let lit = self.clauses[conflict][k];
+                let var = lit / 2;
+                if self.seen[var] || self.levels[var] == 0 {
+                    continue;
+                }
+                self.seen[var] = true;
+                self.bump(var);
+                if self.levels[var] == self.level() {
+                    pending += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+            // the next literal of the current level on the trail
+            let lit = loop {
+                index -= 1;
+                if self.seen[self.trail[index] / 2] {
+                    break self.trail[index];
+                }
+            };
+            self.seen[lit / 2] = false;
+            pending -= 1;
+            if pending == 0 {
+                asserting = Some(lit);
+                break;
+            }
+            asserting = Some(lit);
+            conflict = self.reasons[lit / 2].unwrap();
+        }
+        learnt[0] = asserting.unwrap() ^ 1;
+        // leave out the literals implied by the others alone
+        let all = learnt.clone();
+        learnt.retain(|&lit| match self.reasons[lit / 2] {
+            Some(reason) if lit != all[0] => !self.clauses[reason][1..]
+                .iter()
+                .all(|&l| self.seen[l / 2] || self.levels[l / 2] == 0),
+            _ => true,
+        });
+        for &lit in &all[1..] {
+            self.seen[lit / 2] = false;
+        }
+        // the second watch is the literal of the highest remaining level
+        let mut level = 0;
+        if learnt.len() > 1 {
+            let max = (1..learnt.len())
+                .max_by_key(|&i| self.levels[learnt[i] / 2])
+                .unwrap();
+            learnt.swap(1, max);
+            level = self.levels[learnt[1] / 2];
+        }
+        self.bump *= 1.0 / 0.95;
+        (learnt, level)
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        if self.level() <= level {
+            return;
+        }
+        let start = self.trail_lim[level];
+        for i in start..self.trail.len() {
+            let lit = self.trail[i];
+            let var = lit / 2;
+            self.phases[var] = lit & 1 == 0;
+            self.values[var] = UNDEF;
+            self.reasons[var] = None;
+            self.heap_insert(var);
+        }
+        self.trail.truncate(start);
+        self.trail_lim.truncate(level);
+        self.head = start;
+    }
+
+    /// The unassigned variable with the highest activity.
+    fn decide(&mut self) -> Option<usize> {
+        while let Some(var) = self.heap_pop() {
+            if self.values[var] == UNDEF {
+                return Some(var);
+            }
+        }
+        None
+    }
+}
+
+/// The `i`-th element (from 1) of the Luby sequence 1 1 2 1 1 2 4 ...
+fn luby(mut i: u64) -> u64 {
+    loop {
+        let k = 64 - i.leading_zeros() as u64;
+        if i == (1 << k) - 1 {
+            return 1 << (k - 1);
+        }
+        i -= (1 << (k - 1)) - 1;
+    }
+}
+
+/// A satisfying assignment of the variables (index `v - 1`), `None` if the
+/// formula is unsatisfiable.
+pub fn solve(cnf: &Cnf) -> Option<Vec<bool>> {
+    let mut solver = Solver::new(cnf.vars);
+    let mut units = Vec::new();
+    for clause in &cnf.clauses {
+        let mut clause: Vec<Lit> = clause.iter().map(|&l| lit(l)).collect();
+        clause.sort_unstable();
+        clause.dedup();
+        if clause.windows(2).any(|w| w[0] ^ 1 == w[1]) {
+            continue; // always true
+        }
+        match clause.len() {
+            0 => return None,
+            1 => units.push(clause[0]),
+            _ => {
+                solver.attach(clause);
+            }
+        }
+    }
+    for unit in units {
+        match solver.value(unit) {
+            FALSE => return None,
+            UNDEF => solver.assign(unit, None),
+            _ => {}
+        }
+    }
+
+    let mut restarts = 1;
+    let mut conflicts = 0;
+    loop {
+        if let Some(conflict) = solver.propagate() {
+            if solver.level() == 0 {
+                return None;
+            }
+            conflicts += 1;
+            let (learnt, level) = solver.analyze(conflict);
+            solver.backtrack(level);
+            if learnt.len() == 1 {
+                solver.assign(learnt[0], None);
+            } else {
+                let asserting = learnt[0];
+                let c = solver.attach(learnt);
+                solver.assign(asserting, Some(c));
+            }
+            continue;
+        }
+        if conflicts >= 100 * luby(restarts) {
+            conflicts = 0;
+            restarts += 1;
+            solver.backtrack(0);
+            continue;
+        }
+        let Some(var) = solver.decide() else {
+            return Some(solver.values.iter().map(|&v| v == TRUE).collect());
+        };
+        solver.trail_lim.push(solver.trail.len());
+        let lit = var * 2 + !solver.phases[var] as usize;
+        solver.assign(lit, None);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::random::Random;
+
+    fn satisfies(cnf: &Cnf, model: &[bool]) -> bool {
+        cnf.clauses.iter().all(|clause| {
+            clause
+                .iter()
+                .any(|&l| model[l.unsigned_abs() as usize - 1] == (l > 0))
+        })
+    }
+
+    /// `pigeons` pigeons in `holes` holes, each in its own hole.
+    fn pigeonhole(pigeons: usize, holes: usize) -> Cnf {
+        let mut cnf = Cnf::new(pigeons * holes);
+        let var = |p: usize, h: usize| (p * holes + h + 1) as i32;
+        for p in 0..pigeons {
+            cnf.add((0..holes).map(|h| var(p, h)).collect());
+        }
+        for h in 0..holes {
+            for p in 0..pigeons {
+                for q in p + 1..pigeons {
+                    cnf.add(vec![-var(p, h), -var(q, h)]);
+                }
+            }
+        }
+        cnf
+    }
+
+    #[test]
+    fn small() {
+        assert_eq!(luby(1), 1);
+        assert_eq!((1..=7).map(luby).collect::<Vec<_>>(), [1, 1, 2, 1, 1, 2, 4]);
+        let mut cnf = Cnf::new(2);
+        cnf.add(vec![1, 2]);
+        cnf.add(vec![-1]);
+        assert_eq!(solve(&cnf), Some(vec![false, true]));
+        cnf.add(vec![-2, 1]);
+        assert_eq!(solve(&cnf), None);
+        assert_eq!(solve(&Cnf::new(3)).map(|m| m.len()), Some(3));
+        let mut empty = Cnf::new(1);
+        empty.add(vec![]);
+        assert_eq!(solve(&empty), None);
+    }
+
+    #[test]
+    fn pigeons() {
+        assert_eq!(solve(&pigeonhole(6, 5)), None);
+        let cnf = pigeonhole(5, 5);
+        assert!(satisfies(&cnf, &solve(&cnf).unwrap()));
+    }
+
+    #[test]
+    fn random_3sat() {
+        // around the threshold of 4.26 clauses per variable both answers
+        // are common, unsatisfiable ones are checked by brute force
+        let mut random = Random::new(11);
+        let (mut sat, mut unsat) = (0, 0);
+        for _ in 0..40 {
+            let vars = 12;
+            let mut cnf = Cnf::new(vars);
+            for _ in 0..51 {
+                let clause = (0..3)
+                    .map(|_| {
+                        let v = random.below(vars as u64) as i32 + 1;
+                        if random.chance(0.5) {
+                            v
+                        } else {
+                            -v
+                        }
+                    })
+                    .collect();
+                cnf.add(clause);
+            }
+            match solve(&cnf) {
+                Some(model) => {
+                    assert!(satisfies(&cnf, &model));
+                    sat += 1;
+                }
+                None => {
+                    let brute = (0..1u32 << vars).any(|bits| {
+                        let model: Vec<bool> = (0..vars).map(|v| bits >> v & 1 == 1).collect();
+                        satisfies(&cnf, &model)
+                    });
+                    assert!(!brute);
+                    unsat += 1;
+                }
+            }
+        }
+        assert!(sat > 0 && unsat > 0, "{} {}", sat, unsat);
+    }
+
+    #[test]
+    fn dimacs() {
+        let cnf = pigeonhole(3, 2);
+        let text = cnf.to_dimacs();
+        assert!(text.starts_with("p cnf 6 9\n1 2 0\n"));
+        assert_eq!(Cnf::from_dimacs(&text), Ok(cnf));
+        let cnf = Cnf::from_dimacs("c comment\np cnf 3 2\n1 -3\n0 2 3 0\n").unwrap();
+        assert_eq!(cnf.clauses, [vec![1, -3], vec![2, 3]]);
+        assert!(Cnf::from_dimacs("1 2 0\n").is_err());
+        assert!(Cnf::from_dimacs("p cnf 2 1\n1 3 0\n").is_err());
+
+        let output = "c solved\ns SATISFIABLE\nv -1 2\nv -3 0\n";
+        assert_eq!(parse_model(output, 3), Ok(Some(vec![false, true, false])));
+        assert_eq!(parse_model("s UNSATISFIABLE\n", 3), Ok(None));
+        assert!(parse_model("v 1 0\n", 3).is_err());
+        assert!(parse_model("s SATISFIABLE\nv 4 0\n", 3).is_err());
+    }
+}
@@ -0,0 +1,238 @@
+//! Searching for oscillators and spaceships in the way of lifesrc: every
+//! generation of a period in a box is a set of unknown cells, connected by
+//! the rule, and the last one leads back to the first shifted by the
+//! displacement. The backtracking is left to the SAT solver, which also
+//! learns why a partial pattern can't work.
+
+use crate::pattern::Pattern;
+use crate::predecessor::{Cell, Encoder};
+use crate::rule::Rule;
+use crate::sat::{self, Cnf};
+
+/// The objects to look for, the generations of one period all fit in the box.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Search {
+    pub width: usize,
+    pub height: usize,
+    pub period: usize,
+    /// Where the object is after a period, 0, 0 for oscillators and still
+    /// lifes.
+    pub dx: i64,
+    pub dy: i64,
+}
+
+impl Search {
+    pub fn new(width: usize, height: usize, period: usize) -> Self {
+        Search {
+            width,
+            height,
+            period,
+            dx: 0,
+            dy: 0,
+        }
+    }
+
+    /// The cell of the generation, dead outside the box.
+    fn cell(&self, generation: usize, x: i64, y: i64) -> Cell {
+        let (w, h) = (self.width as i64, self.height as i64);
+        if x < 0 || y < 0 || x >= w || y >= h {
+            return Cell::Dead;
+        }
+        Cell::Var((generation as i64 * w * h + y * w + x + 1) as i32)
+    }
+
+    /// The cell of the generation, where the one after the last is the
+    /// first shifted.
+    fn wrapped(&self, generation: usize, x: i64, y: i64) -> Cell {
+        if generation == self.period {
+            self.cell(0, x - self.dx, y - self.dy)
+        } else {
+            self.cell(generation, x, y)
+        }
+    }
+
+    /// The formula of the search. The first generation is variables 1 to
+    /// `width * height` in row major order, the others follow.
+    pub fn encode(&self, rule: &Rule) -> Cnf {
+        let encoder = Encoder::new(rule);
+        let (w, h) = (self.width as i64, self.height as i64);
+        let mut cnf = Cnf::new(self.period * self.width * self.height);
+        let lit = |cell: Cell| match cell {
+            Cell::Var(var) => Some(var),
+            _ => None,
+        };
+
+        for t in 0..self.period {
+            // the box and the ring around it, which has to stay dead
+            for y in -1..=h {
+                for x in -1..=w {
+                    let mut neighbourhood = [Cell::Dead; 9];
+                    for (bit, cell) in neighbourhood.iter_mut().enumerate() {
+                        let (nx, ny) = (x + bit as i64 % 3 - 1, y + bit as i64 / 3 - 1);
+                        *cell = self.cell(t, nx, ny);
+                    }
+                    let next = self.wrapped(t + 1, x, y);
+                    encoder.transition(&mut cnf, &neighbourhood, next);
+                }
+            }
+        }
+        // cells shifted out of the box can't be alive
+        for y in 0..h {
+            for x in 0..w {
+                let (sx, sy) = (x + self.dx, y + self.dy);
+                if sx < 0 || sy < 0 || sx >= w || sy >= h {
+                    cnf.add(vec![-lit(self.cell(0, x, y)).unwrap()]);
+                }
+            }
+        }
+
+        // not empty, and touching the top and left side in some generation
+        // so the same object isn't found at every position
+        let cells = |f: &dyn Fn(i64, i64) -> bool| {
+            let mut ret = Vec::new();
+            for t in 0..self.period {
+                for y in 0..h {
+                    for x in (0..w).filter(|&x| f(x, y)) {
+                        ret.extend(lit(self.cell(t, x, y)));
+                    }
+                }
+            }
+            ret
+        };
+        cnf.add(cells(&|_, _| true));
+        cnf.add(cells(&|_, y| y == 0));
+        cnf.add(cells(&|x, _| x == 0));
+
+        // not repeating after a part of the period, as far as the
+        // displacement can be split up
+        for d in (1..self.period).filter(|d| self.period.is_multiple_of(*d)) {
+            let p = self.period as i64;
+            let (sx, sy) = (self.dx * d as i64, self.dy * d as i64);
+            if sx % p != 0 || sy % p != 0 {
+                continue;
+            }
+            let (sx, sy) = (sx / p, sy / p);
+            let mut differs = Vec::new();
+            for y in 0..h {
+                for x in 0..w {
+                    let a = lit(self.cell(d, x, y)).unwrap();
+                    match lit(self.cell(0, x - sx, y - sy)) {
+                        Some(b) => {
+                            let e = cnf.var();
+                            cnf.add(vec![-e, a, b]);
+                            cnf.add(vec![-e, -a, -b]);
+                            differs.push(e);
+                        }
+                        None => differs.push(a),
+                    }
+                }
+            }
+            cnf.add(differs);
+        }
+        cnf
+    }
+
+    /// The first generation in a model of the formula.
+    fn decode(&self, model: &[bool]) -> Pattern {
+        let mut ret = Pattern::new(self.width, self.height);
+        for (cell, &alive) in ret.cells.iter_mut().zip(model) {
+            *cell = alive as u8;
+        }
+        ret
+    }
+
+    /// The objects found, in their first generation. Each one is ruled out
+    /// before looking for the next, the other phases and orientations of
+    /// an object can still come up.
+    pub fn solutions(&self, rule: &Rule) -> impl Iterator<Item = Pattern> + '_ {
+        let mut cnf = self.encode(rule);
+        let cells = self.width * self.height;
+        std::iter::from_fn(move || {
+            let model = sat::solve(&cnf)?;
+            let blocking = (1..=cells as i32)
+                .map(|v| if model[v as usize - 1] { -v } else { v })
+                .collect();
+            cnf.add(blocking);
+            Some(self.decode(&model))
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The pattern stepped in a larger board, which is the object without
+    /// the edges.
+    fn step(pattern: &Pattern, rule: &Rule) -> Pattern {
+        let mut ret = Pattern::new(pattern.width, pattern.height);
+        let get = |x: i64, y: i64| {
+            x >= 0
+                && y >= 0
+                && (x as usize) < pattern.width
+                && (y as usize) < pattern.height
+                && pattern.get(x as usize, y as usize) != 0
+        };
+        for y in 0..pattern.height as i64 {
+            for x in 0..pattern.width as i64 {
+                let index = (0..9)
+                    .filter(|b| get(x + b % 3 - 1, y + b / 3 - 1))
+                    .fold(0, |i, b| i | 1 << b);
+                ret.set(x as usize, y as usize, rule.alive(index) as u8);
+            }
+        }
+        ret
+    }
+
+    /// Checks the period and displacement of the object.
+    fn check(search: &Search, found: &Pattern) {
+        let rule = Rule::default();
+        let p = search.period as i64;
+        let pad = 2 + search.period;
+        let start = found.pad(pad, pad, pad, pad);
+        let mut pattern = start.clone();
+        for t in 1..=p {
+            pattern = step(&pattern, &rule);
+            let (dx, dy) = (search.dx * t, search.dy * t);
+            if dx % p == 0 && dy % p == 0 {
+                let back = pattern == start.translate(dx / p, dy / p);
+                assert_eq!(back, t == p, "{}", found.to_plaintext());
+            }
+        }
+    }
+
+    #[test]
+    fn still_lifes_and_oscillators() {
+        let rule = Rule::default();
+        let search = Search::new(2, 2, 1);
+        let found: Vec<_> = search.solutions(&rule).collect();
+        assert_eq!(found, [Pattern::from_plaintext("XX\nXX\n").unwrap()]);
+
+        let search = Search::new(3, 3, 2);
+        let found: Vec<_> = search.solutions(&rule).collect();
+        // the two phases of the blinker
+        assert_eq!(found.len(), 2);
+        for pattern in &found {
+            check(&search, pattern);
+        }
+        assert_eq!(Search::new(4, 4, 3).solutions(&rule).next(), None);
+    }
+
+    #[test]
+    fn spaceships() {
+        let rule = Rule::default();
+        let mut search = Search::new(4, 4, 4);
+        search.dx = 1;
+        search.dy = 1;
+        let glider = search.solutions(&rule).next().unwrap();
+        assert_eq!(glider.cells.iter().filter(|&&c| c != 0).count(), 5);
+        check(&search, &glider);
+        search.dy = -1;
+        check(&search, &search.solutions(&rule).next().unwrap());
+
+        let mut search = Search::new(7, 6, 4);
+        search.dx = -2;
+        let lwss = search.solutions(&rule).next().unwrap();
+        check(&search, &lwss);
+    }
+}
@@ -0,0 +1,537 @@
+//! Golly `.rule` files with a `@TABLE` section, compiled for lookup the same
+//! way Golly does it. Every transition is expanded for its bound variables
+//! and symmetries, then for each position of the neighbourhood and each state
+//! a bitset of the transitions accepting that state there is stored. The next
+//! state of a cell is the output of the first transition left after
+//! intersecting the bitsets of its neighbourhood, if there is none the cell
+//! keeps its state.
+//!
+//! ```text
+//! @RULE WireWorld
+//! @TABLE
+//! n_states:4
+//! neighborhood:Moore
+//! symmetries:permute
+//! var a={0,1,2,3}
+//! ...
+//! # C,N,NE,E,SE,S,SW,W,NW,C'
+//! 1,a,b,c,d,e,f,g,h,2
+//! ```
+
+use crate::rule::Neighbourhood;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+/// A transition with every position resolved to the set of states it accepts.
+type Transition = (Vec<Vec<u8>>, u8);
+
+#[derive(Debug, Clone)]
+pub struct Table {
+    name: String,
+    states: usize,
+    neighbourhood: Neighbourhood,
+    /// Number of `u64` words of each transition bitset.
+    words: usize,
+    /// Bitset of the transitions accepting `state` at `position` (0 is the
+    /// cell itself) at `(position * states + state) * words`.
+    lut: Vec<u64>,
+    outputs: Vec<u8>,
+}
+
+impl Table {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn states(&self) -> usize {
+        self.states
+    }
+
+    pub fn neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    /// Offsets of the neighbours in the order of the table's transitions,
+    /// clockwise from north.
+    pub fn offsets(&self) -> &'static [(i64, i64)] {
+        match self.neighbourhood {
+            Neighbourhood::Moore => &[
+                (0, -1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+                (0, 1),
+                (-1, 1),
+                (-1, 0),
+                (-1, -1),
+            ],
+            Neighbourhood::VonNeumann => &[(0, -1), (1, 0), (0, 1), (-1, 0)],
+            Neighbourhood::Hexagonal => &[(0, -1), (1, 0), (1, 1), (0, 1), (-1, 0), (-1, -1)],
+        }
+    }
+
+    /// Returns the next state of the cell with given `neighbours` states,
+    /// ordered as `offsets`.
+    pub fn next(&self, centre: u8, neighbours: &[u8]) -> u8 {
+        let entry =
+            |position: usize, state: u8| (position * self.states + state as usize) * self.words;
+        let centre_entry = entry(0, centre);
+        for w in 0..self.words {
+            let mut bits = self.lut[centre_entry + w];
+            for (p, &state) in neighbours.iter().enumerate() {
+                if bits == 0 {
+                    break;
+                }
+                bits &= self.lut[entry(p + 1, state) + w];
+            }
+            if bits != 0 {
+                return self.outputs[w * 64 + bits.trailing_zeros() as usize];
+            }
+        }
+        centre
+    }
+}
+
+/// Permutations of the neighbour positions for the symmetries, `None` for
+/// `permute` which is handled separately.
+fn symmetries(name: &str, neighbourhood: Neighbourhood) -> Result<Option<Vec<Vec<usize>>>, String> {
+    let n = match neighbourhood {
+        Neighbourhood::Moore => 8,
+        Neighbourhood::VonNeumann => 4,
+        Neighbourhood::Hexagonal => 6,
+    };
+    let rotate = |step: usize| (0..n).map(|i| (i + step) % n).collect::<Vec<usize>>();
+    let reflect = (0..n).map(|i| (n - i) % n).collect::<Vec<usize>>();
+    let generators = match (neighbourhood, name) {
+        (_, "permute") => return Ok(None),
+        (_, "none") => vec![],
+        (Neighbourhood::Moore, "rotate4") => vec![rotate(2)],
+        (Neighbourhood::Moore, "rotate8") => vec![rotate(1)],
+        (Neighbourhood::Moore, "rotate4reflect") => vec![rotate(2), reflect],
+        (Neighbourhood::Moore, "rotate8reflect") => vec![rotate(1), reflect],
+        (Neighbourhood::VonNeumann, "rotate4") => vec![rotate(1)],
+        (Neighbourhood::VonNeumann, "rotate4reflect") => vec![rotate(1), reflect],
+        (Neighbourhood::Moore | Neighbourhood::VonNeumann, "reflect") => vec![reflect],
+        (Neighbourhood::Hexagonal, "rotate2") => vec![rotate(3)],
+        (Neighbourhood::Hexagonal, "rotate3") => vec![rotate(2)],
+        (Neighbourhood::Hexagonal, "rotate6") => vec![rotate(1)],
+        (Neighbourhood::Hexagonal, "rotate6reflect") => vec![rotate(1), reflect],
+        _ => {
+            return Err(format!(
+                "Unsupported symmetries '{}' for the neighborhood",
+                name
+            ))
+        }
+    };
+
+    // close the generators under composition
+    let mut group = vec![(0..n).collect::<Vec<usize>>()];
+    let mut i = 0;
+    while i < group.len() {
+        for g in &generators {
+            let composed: Vec<usize> = group[i].iter().map(|&p| g[p]).collect();
+            if !group.contains(&composed) {
+                group.push(composed);
+            }
+        }
+        i += 1;
+    }
+    Ok(Some(group))
+}
+
+/// Rearranges `items` to the next lexicographic permutation, false when
+/// they were already the last one.
+fn next_permutation(items: &mut [usize]) -> bool {
+    let i = match (1..items.len()).rev().find(|&i| items[i - 1] < items[i]) {
+        Some(i) => i,
+        None => return false,
+    };
+    let j = (i..items.len())
+        .rev()
+        .find(|&j| items[j] > items[i - 1])
+        .unwrap();
+    items.swap(i - 1, j);
+    items[i..].reverse();
+    true
+}
+
+/// All the symmetric variants of the transition, the original first.
+fn expand_symmetries(transition: Transition, group: &Option<Vec<Vec<usize>>>) -> Vec<Transition> {
+    let (inputs, output) = transition;
+    let neighbours = &inputs[1..];
+    let mut ret: Vec<Transition> = Vec::new();
+    let mut push = |arranged: Vec<Vec<u8>>| {
+        let mut full = vec![inputs[0].clone()];
+        full.extend(arranged);
+        let variant = (full, output);
+        if !ret.contains(&variant) {
+            ret.push(variant);
+        }
+    };
+    match group {
+        Some(group) => {
+            for perm in group {
+                let mut arranged = vec![Vec::new(); neighbours.len()];
+                for (i, set) in neighbours.iter().enumerate() {
+                    arranged[perm[i]] = set.clone();
+                }
+                push(arranged);
+            }
+        }
+        None => {
+            // every distinct arrangement of the neighbour sets
+            let mut distinct: Vec<&Vec<u8>> = Vec::new();
+            let mut ids: Vec<usize> = neighbours
+                .iter()
+                .map(|set| match distinct.iter().position(|d| *d == set) {
+                    Some(id) => id,
+                    None => {
+                        distinct.push(set);
+                        distinct.len() - 1
+                    }
+                })
+                .collect();
+            ids.sort_unstable();
+            loop {
+                push(ids.iter().map(|&id| distinct[id].clone()).collect());
+                if !next_permutation(&mut ids) {
+                    break;
+                }
+            }
+        }
+    }
+    ret
+}
+
+impl FromStr for Table {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut name = String::new();
+        let mut states: Option<usize> = None;
+        let mut neighbourhood: Option<Neighbourhood> = None;
+        let mut group: Option<Option<Vec<Vec<usize>>>> = None;
+        let mut symmetries_name = String::from("none");
+        let mut vars: HashMap<String, Vec<u8>> = HashMap::new();
+        let mut transitions: Vec<Transition> = Vec::new();
+        let mut in_table = false;
+
+        for (n, line) in s.lines().enumerate() {
+            let err = |msg: &str| format!("Error: line {}: {}", n + 1, msg);
+            let line = line.split('#').next().unwrap().trim();
+            if let Some(section) = line.strip_prefix('@') {
+                in_table = section.starts_with("TABLE");
+                if let Some(rule) = section.strip_prefix("RULE") {
+                    name = rule.trim().to_string();
+                }
+                continue;
+            }
+            if !in_table || line.is_empty() {
+                continue;
+            }
+
+            if let Some((key, value)) = line.split_once(':') {
+                let value = value.trim();
+                match key.trim() {
+                    "n_states" => match value.parse::<usize>() {
+                        Ok(n) if (2..=256).contains(&n) => states = Some(n),
+                        _ => return Err(err("n_states has to be between 2 and 256")),
+                    },
+                    "neighborhood" => {
+                        neighbourhood = Some(match value {
+                            "Moore" => Neighbourhood::Moore,
+                            "vonNeumann" => Neighbourhood::VonNeumann,
+                            "hexagonal" => Neighbourhood::Hexagonal,
+                            _ => return Err(err("unsupported neighborhood")),
+                        })
+                    }
+                    "symmetries" => symmetries_name = value.to_string(),
+                    _ => return Err(err("unknown setting")),
+                }
+                continue;
+            }
+
+            let states = states.ok_or_else(|| err("n_states has to come first"))?;
+            let literal = |token: &str| -> Result<Option<u8>, String> {
+                match token.parse::<usize>() {
+                    Ok(state) if state < states => Ok(Some(state as u8)),
+                    Ok(_) => Err(err("state out of range")),
+                    Err(_) => Ok(None),
+                }
+            };
+
+            if let Some(var) = line.strip_prefix("var ") {
+                let (var_name, values) = var
+                    .split_once('=')
+                    .ok_or_else(|| err("expected var name={...}"))?;
+                let values = values
+                    .trim()
+                    .strip_prefix('{')
+                    .and_then(|v| v.strip_suffix('}'))
+                    .ok_or_else(|| err("expected var values in braces"))?;
+                let mut set: Vec<u8> = Vec::new();
+                for token in values.split(',').map(str::trim) {
+                    match literal(token)? {
+                        Some(state) => set.push(state),
+                        None => set.extend(
+                            vars.get(token)
+                                .ok_or_else(|| err(&format!("unknown variable '{}'", token)))?,
+                        ),
+                    }
+                }
+                set.sort_unstable();
+                set.dedup();
+                vars.insert(var_name.trim().to_string(), set);
+                continue;
+            }
+
+            // transition
+            let neighbourhood = neighbourhood.unwrap_or(Neighbourhood::Moore);
+            if group.is_none() {
+                group = Some(symmetries(&symmetries_name, neighbourhood).map_err(|e| err(&e))?);
+            }
+            let tokens: Vec<String> = if line.contains(',') {
+                line.split(',').map(|t| t.trim().to_string()).collect()
+            } else if states <= 10 {
+                line.chars()
+                    .filter(|c| !c.is_whitespace())
+                    .map(String::from)
+                    .collect()
+            } else {
+                return Err(err("transitions need commas with more than 10 states"));
+            };
+            let positions = neighbourhood.mask().count_ones() as usize + 1;
+            if tokens.len() != positions + 1 {
+                return Err(err(&format!("expected {} entries", positions + 1)));
+            }
+
+            // variables used more than once are bound to the same value
+            let mut bound: Vec<&str> = Vec::new();
+            for token in &tokens {
+                if vars.contains_key(token.as_str())
+                    && !bound.contains(&token.as_str())
+                    && tokens.iter().filter(|t| *t == token).count() > 1
+                {
+                    bound.push(token);
+                }
+            }
+            let output = &tokens[positions];
+            if literal(output)?.is_none() && !bound.contains(&output.as_str()) {
+                return Err(err("output has to be a state or a bound variable"));
+            }
+            let mut choice = vec![0; bound.len()];
+            loop {
+                let resolve = |token: &str| -> Result<Vec<u8>, String> {
+                    if let Some(i) = bound.iter().position(|b| *b == token) {
+                        return Ok(vec![vars[token][choice[i]]]);
+                    }
+                    match literal(token)? {
+                        Some(state) => Ok(vec![state]),
+                        None => vars
+                            .get(token)
+                            .cloned()
+                            .ok_or_else(|| err(&format!("unknown variable '{}'", token))),
+                    }
+                };
+                let inputs = tokens[..positions]
+                    .iter()
+                    .map(|t| resolve(t))
+                    .collect::<Result<Vec<Vec<u8>>, String>>()?;
+                let output = resolve(output)?[0];
+                transitions.extend(expand_symmetries((inputs, output), group.as_ref().unwrap()));
+
+                // next combination of the bound variables
+                let mut i = 0;
+                while i < bound.len() {
+                    choice[i] += 1;
+                    if choice[i] < vars[bound[i]].len() {
+                        break;
+                    }
+                    choice[i] = 0;
+                    i += 1;
+                }
+                if i == bound.len() {
+                    break;
+                }
+            }
+        }
+
+        let states = states.ok_or("Error: Missing @TABLE with n_states.")?;
+        let neighbourhood = neighbourhood.unwrap_or(Neighbourhood::Moore);
+        let positions = neighbourhood.mask().count_ones() as usize + 1;
+        let words = transitions.len().div_ceil(64).max(1);
+        let mut lut = vec![0; positions * states * words];
+        let mut seen = HashSet::new();
+        let mut outputs = Vec::new();
+        for (inputs, output) in transitions {
+            // a later copy can never be the first match
+            if !seen.insert((inputs.clone(), output)) {
+                continue;
+            }
+            let t = outputs.len();
+            for (position, set) in inputs.iter().enumerate() {
+                for &state in set {
+                    lut[(position * states + state as usize) * words + t / 64] |= 1 << (t % 64);
+                }
+            }
+            outputs.push(output);
+        }
+        Ok(Table {
+            name,
+            states,
+            neighbourhood,
+            words,
+            lut,
+            outputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rule::{self, Rule};
+
+    const LIFE: &str = "@RULE Life
+@TABLE
+n_states:2
+neighborhood:Moore
+symmetries:permute
+var a={0,1}
+var b={a}
+var c={0,1}
+var d={0,1}
+var e={0,1}
+var f={0,1}
+var g={0,1}
+var h={0,1}
+var i={0,1}
+0,1,1,1,0,0,0,0,0,1
+1,1,1,0,0,0,0,0,0,1
+1,1,1,1,0,0,0,0,0,1
+1,a,b,c,d,e,f,g,h,0 # everything else dies
+
+@COLORS
+1 255 255 255
+";
+
+    const WIREWORLD: &str = "@RULE WireWorld
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+var d={0,1,2,3}
+var e={0,1,2,3}
+var f={0,1,2,3}
+var g={0,1,2,3}
+var h={0,1,2,3}
+var i={0,2,3}
+var j={0,2,3}
+var k={0,2,3}
+var l={0,2,3}
+var m={0,2,3}
+var n={0,2,3}
+var o={0,2,3}
+# head -> tail -> conductor
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+# conductor -> head with one or two heads around
+3,1,i,j,k,l,m,n,o,1
+3,1,1,i,j,k,l,m,n,1
+";
+
+    #[test]
+    fn life_table() {
+        let table: Table = LIFE.parse().unwrap();
+        assert_eq!(table.name(), "Life");
+        assert_eq!(table.states(), 2);
+        let rule = Rule::default();
+        for n in 0..512 {
+            let neighbours: Vec<u8> = table
+                .offsets()
+                .iter()
+                .map(|&(dx, dy)| (n & rule::bit(dx, dy) != 0) as u8)
+                .collect();
+            let centre = (n & rule::CENTRE != 0) as u8;
+            assert_eq!(table.next(centre, &neighbours) == 1, rule.alive(n), "{}", n);
+        }
+    }
+
+    #[test]
+    fn wireworld() {
+        let table: Table = WIREWORLD.parse().unwrap();
+        assert_eq!(table.next(1, &[3, 3, 0, 0, 0, 0, 0, 0]), 2);
+        assert_eq!(table.next(2, &[1, 1, 1, 0, 0, 0, 0, 0]), 3);
+        assert_eq!(table.next(3, &[0, 0, 1, 0, 0, 0, 3, 3]), 1);
+        assert_eq!(table.next(3, &[2, 1, 3, 0, 0, 1, 0, 0]), 1);
+        assert_eq!(table.next(3, &[1, 1, 1, 0, 0, 0, 0, 0]), 3);
+        assert_eq!(table.next(3, &[0, 0, 0, 0, 0, 0, 0, 0]), 3);
+        // no transition, the state stays
+        assert_eq!(table.next(0, &[1, 1, 1, 0, 0, 0, 0, 0]), 0);
+    }
+
+    #[test]
+    fn symmetries_and_bound_variables() {
+        // a cell turns into its north neighbour, rotated to all four sides
+        let table: Table = "@TABLE
+n_states:3
+neighborhood:vonNeumann
+symmetries:rotate4
+var a={1,2}
+0,a,0,0,0,a
+"
+        .parse()
+        .unwrap();
+        assert_eq!(table.neighbourhood(), Neighbourhood::VonNeumann);
+        assert_eq!(table.next(0, &[2, 0, 0, 0]), 2);
+        assert_eq!(table.next(0, &[0, 0, 0, 1]), 1);
+        assert_eq!(table.next(0, &[0, 1, 1, 0]), 0);
+
+        // without symmetries and in the compact form
+        let table: Table = "@TABLE
+n_states:3
+neighborhood:vonNeumann
+symmetries:none
+var a={1,2}
+0a00a1
+"
+        .parse()
+        .unwrap();
+        assert_eq!(table.next(0, &[2, 0, 0, 2]), 1);
+        assert_eq!(table.next(0, &[1, 0, 0, 2]), 0);
+        assert_eq!(table.next(0, &[0, 2, 2, 0]), 0);
+    }
+
+    #[test]
+    fn first_match_wins() {
+        let table: Table = "@TABLE
+n_states:3
+neighborhood:hexagonal
+symmetries:rotate6
+0,1,0,0,0,0,0,2
+0,1,0,0,0,0,0,1
+"
+        .parse()
+        .unwrap();
+        assert_eq!(table.offsets().len(), 6);
+        assert_eq!(table.next(0, &[0, 0, 1, 0, 0, 0]), 2);
+    }
+
+    #[test]
+    fn invalid() {
+        let parse = |s: &str| s.parse::<Table>();
+        assert!(parse("@TABLE\nneighborhood:Moore\n0,0,0,0,0,0,0,0,0,1").is_err());
+        assert!(parse("@TABLE\nn_states:2\n0,0,0,0,0,0,0,0,0,2").is_err());
+        assert!(parse("@TABLE\nn_states:2\n0,0,0,0,0,0,0,0,1").is_err());
+        assert!(parse("@TABLE\nn_states:2\nvar a={0,1}\n0,0,0,0,0,0,0,0,0,a").is_err());
+        assert!(parse("@TABLE\nn_states:2\n0,0,0,0,0,0,0,0,x,1").is_err());
+        assert!(parse("@TABLE\nn_states:2\nsymmetries:rotate6\n0,0,0,0,0,0,0,0,0,1").is_err());
+        assert!(parse("@TABLE\nn_states:2\nneighborhood:oneDimensional\n").is_err());
+        assert!(parse("@RULE Empty\n").is_err());
+        let err = parse("@TABLE\nn_states:2\n\n0,0,0\n").unwrap_err();
+        assert!(err.contains("line 4"), "{}", err);
+    }
+}
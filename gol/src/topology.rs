@@ -0,0 +1,71 @@
+//! What is beyond the edges of the board.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Each cell outside the board is dead and remains dead.
+    #[default]
+    Bounded,
+    /// The board wraps around, the left edge is next to the right one and the
+    /// top edge to the bottom one.
+    Torus,
+}
+
+impl Topology {
+    /// Returns the coordinate `c + d` on an axis of `size` cells, `None` when
+    /// it falls off the bounded board.
+    pub fn offset(&self, c: usize, d: i64, size: usize) -> Option<usize> {
+        let n = c as i64 + d;
+        match self {
+            Topology::Bounded if n < 0 || n >= size as i64 => None,
+            Topology::Bounded => Some(n as usize),
+            Topology::Torus => Some(n.rem_euclid(size as i64) as usize),
+        }
+    }
+}
+
+impl FromStr for Topology {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "bounded" | "plane" => Ok(Topology::Bounded),
+            "torus" => Ok(Topology::Torus),
+            _ => Err("Error: Unknown topology, expected bounded or torus."),
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Topology::Bounded => write!(f, "bounded"),
+            Topology::Torus => write!(f, "torus"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn offset() {
+        assert_eq!(Topology::Bounded.offset(0, -1, 5), None);
+        assert_eq!(Topology::Bounded.offset(4, 1, 5), None);
+        assert_eq!(Topology::Bounded.offset(2, 1, 5), Some(3));
+        assert_eq!(Topology::Torus.offset(0, -1, 5), Some(4));
+        assert_eq!(Topology::Torus.offset(4, 1, 5), Some(0));
+        assert_eq!(Topology::Torus.offset(0, -1, 1), Some(0));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!("torus".parse(), Ok(Topology::Torus));
+        assert_eq!("Bounded".parse(), Ok(Topology::Bounded));
+        assert_eq!(Topology::Torus.to_string(), "torus");
+        assert!("sphere".parse::<Topology>().is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Geometric transforms and editing of patterns, so boards can be composed
+//! without editing text files. All operations return a new pattern except
+//! `paste`, cells keep their states and obstacles and the rule is kept.
+
+use crate::mask::Mask;
+use crate::pattern::Pattern;
+use std::fmt;
+use std::str::FromStr;
+
+/// The symmetries of a rectangle, rotations are clockwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Mirrored left to right.
+    FlipHorizontal,
+    /// Mirrored top to bottom.
+    FlipVertical,
+    /// Mirrored along the main diagonal.
+    Transpose,
+    /// Mirrored along the other diagonal.
+    AntiTranspose,
+}
+
+pub const ORIENTATIONS: [Orientation; 8] = [
+    Orientation::Identity,
+    Orientation::Rotate90,
+    Orientation::Rotate180,
+    Orientation::Rotate270,
+    Orientation::FlipHorizontal,
+    Orientation::FlipVertical,
+    Orientation::Transpose,
+    Orientation::AntiTranspose,
+];
+
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Orientation::Identity => "identity",
+            Orientation::Rotate90 => "rotate90",
+            Orientation::Rotate180 => "rotate180",
+            Orientation::Rotate270 => "rotate270",
+            Orientation::FlipHorizontal => "flip-horizontal",
+            Orientation::FlipVertical => "flip-vertical",
+            Orientation::Transpose => "transpose",
+            Orientation::AntiTranspose => "antitranspose",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Orientation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ORIENTATIONS
+            .into_iter()
+            .find(|o| o.to_string() == s)
+            .ok_or_else(|| format!("Error: Unknown orientation {}.", s))
+    }
+}
+
+/// How `paste` combines the cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Paste {
+    /// Live cells of the pasted pattern are set, dead ones change nothing.
+    #[default]
+    Or,
+    /// Live cells toggle, where both are alive the cell dies.
+    Xor,
+    /// The whole rectangle of the pasted pattern is copied, dead cells too.
+    Replace,
+}
+
+impl FromStr for Paste {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "or" => Ok(Paste::Or),
+            "xor" => Ok(Paste::Xor),
+            "replace" => Ok(Paste::Replace),
+            _ => Err(format!(
+                "Error: Unknown paste mode {}, expected or, xor or replace.",
+                s
+            )),
+        }
+    }
+}
+
+impl Pattern {
+    /// An empty pattern of the size with the same rule, and an empty mask
+    /// if this one has obstacles.
+    fn blank(&self, width: usize, height: usize) -> Pattern {
+        let mut ret = Pattern::new(width, height);
+        ret.rule = self.rule.clone();
+        ret.mask = self.mask.as_ref().map(|_| Mask::new(width, height));
+        ret
+    }
+
+    /// The state the cell is kept in by an obstacle, see `Mask::get`.
+    fn obstacle(&self, x: usize, y: usize) -> Option<u8> {
+        self.mask.as_ref().and_then(|m| m.get(x, y))
+    }
+
+    fn set_obstacle(&mut self, x: usize, y: usize, obstacle: Option<u8>) {
+        if obstacle.is_none() && self.mask.is_none() {
+            return;
+        }
+        let (width, height) = (self.width, self.height);
+        let mask = self.mask.get_or_insert_with(|| Mask::new(width, height));
+        mask.walls.set(x, y, obstacle == Some(0));
+        mask.fixed.set(x, y, obstacle == Some(1));
+    }
+
+    pub fn orient(&self, orientation: Orientation) -> Pattern {
+        let (w, h) = (self.width, self.height);
+        let swapped = matches!(
+            orientation,
+            Orientation::Rotate90
+                | Orientation::Rotate270
+                | Orientation::Transpose
+                | Orientation::AntiTranspose
+        );
+        let mut ret = if swapped {
+            self.blank(h, w)
+        } else {
+            self.blank(w, h)
+        };
+        for y in 0..h {
+            for x in 0..w {
+                let (nx, ny) = match orientation {
+                    Orientation::Identity => (x, y),
+                    Orientation::Rotate90 => (h - 1 - y, x),
+                    Orientation::Rotate180 => (w - 1 - x, h - 1 - y),
+                    Orientation::Rotate270 => (y, w - 1 - x),
+                    Orientation::FlipHorizontal => (w - 1 - x, y),
+                    Orientation::FlipVertical => (x, h - 1 - y),
+                    Orientation::Transpose => (y, x),
+                    Orientation::AntiTranspose => (h - 1 - y, w - 1 - x),
+                };
+                ret.set(nx, ny, self.get(x, y));
+                ret.set_obstacle(nx, ny, self.obstacle(x, y));
+            }
+        }
+        ret
+    }
+
+    /// Moves the cells by `dx`, `dy` within the same board, the ones moved
+    /// off it are lost.
+    pub fn translate(&self, dx: i64, dy: i64) -> Pattern {
+        let mut ret = self.blank(self.width, self.height);
+        ret.paste(self, dx, dy, Paste::Replace);
+        ret
+    }
+
+    /// Left, top, width and height of the smallest rectangle with all the
+    /// live cells and obstacles, `None` without any.
+    pub fn bounding_box(&self) -> Option<(usize, usize, usize, usize)> {
+        let used = |x: usize, y: usize| self.get(x, y) != 0 || self.obstacle(x, y).is_some();
+        let live_row = |y: usize| (0..self.width).any(|x| used(x, y));
+        let live_column = |x: usize| (0..self.height).any(|y| used(x, y));
+        let top = (0..self.height).find(|&y| live_row(y))?;
+        let bottom = (0..self.height).rfind(|&y| live_row(y))?;
+        let left = (0..self.width).find(|&x| live_column(x))?;
+        let right = (0..self.width).rfind(|&x| live_column(x))?;
+        Some((left, top, right - left + 1, bottom - top + 1))
+    }
+
+    /// The pattern cut down to its bounding box, empty without live cells
+    /// or obstacles.
+    pub fn crop(&self) -> Pattern {
+        match self.bounding_box() {
+            Some((x, y, width, height)) => {
+                let mut ret = self.blank(width, height);
+                ret.paste(self, -(x as i64), -(y as i64), Paste::Replace);
+                ret
+            }
+            None => self.blank(0, 0),
+        }
+    }
+
+    /// Adds dead cells around the pattern.
+    pub fn pad(&self, left: usize, top: usize, right: usize, bottom: usize) -> Pattern {
+        let mut ret = self.blank(self.width + left + right, self.height + top + bottom);
+        ret.paste(self, left as i64, top as i64, Paste::Replace);
+        ret
+    }
+
+    /// Changes the size of the board, keeping the top left corner.
+    pub fn resize(&self, width: usize, height: usize) -> Pattern {
+        let mut ret = self.blank(width, height);
+        ret.paste(self, 0, 0, Paste::Replace);
+        ret
+    }
+
+    /// `columns` x `rows` copies of the pattern next to each other.
+    pub fn tile(&self, columns: usize, rows: usize) -> Pattern {
+        let mut ret = self.blank(self.width * columns, self.height * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x, y) = (column * self.width, row * self.height);
+                ret.paste(self, x as i64, y as i64, Paste::Replace);
+            }
+        }
+        ret
+    }
+
+    /// Puts `other` with its top left corner at `x`, `y`, the parts outside
+    /// the board are left out. The obstacles of `other` are pasted over the
+    /// cells, in `Replace` mode its rectangle loses the obstacles it had.
+    pub fn paste(&mut self, other: &Pattern, x: i64, y: i64, mode: Paste) {
+        for oy in 0..other.height {
+            let ty = y + oy as i64;
+            if ty < 0 || ty >= self.height as i64 {
+                continue;
+            }
+            for ox in 0..other.width {
+                let tx = x + ox as i64;
+                if tx < 0 || tx >= self.width as i64 {
+                    continue;
+                }
+                let (tx, ty) = (tx as usize, ty as usize);
+                let (old, new) = (self.get(tx, ty), other.get(ox, oy));
+                let obstacle = other.obstacle(ox, oy);
+                let state = match (obstacle, mode) {
+                    (Some(state), _) => state,
+                    (None, Paste::Or) if new == 0 => old,
+                    (None, Paste::Xor) if old != 0 && new != 0 => 0,
+                    (None, Paste::Xor) => old.max(new),
+                    _ => new,
+                };
+                if obstacle.is_some() || mode == Paste::Replace {
+                    self.set_obstacle(tx, ty, obstacle);
+                }
+                self.set(tx, ty, state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn p(s: &str) -> Pattern {
+        Pattern::from_plaintext(s).unwrap()
+    }
+
+    #[test]
+    fn orientations() {
+        let pattern = p("XX.\n..X\n");
+        let plaintext = |o: Orientation| pattern.orient(o).to_plaintext();
+        assert_eq!(plaintext(Orientation::Identity), "XX.\n..X\n");
+        assert_eq!(plaintext(Orientation::Rotate90), ".X\n.X\nX.\n");
+        assert_eq!(plaintext(Orientation::Rotate180), "X..\n.XX\n");
+        assert_eq!(plaintext(Orientation::Rotate270), ".X\nX.\nX.\n");
+        assert_eq!(plaintext(Orientation::FlipHorizontal), ".XX\nX..\n");
+        assert_eq!(plaintext(Orientation::FlipVertical), "..X\nXX.\n");
+        assert_eq!(plaintext(Orientation::Transpose), "X.\nX.\n.X\n");
+        assert_eq!(plaintext(Orientation::AntiTranspose), "X.\n.X\n.X\n");
+        for o in ORIENTATIONS {
+            assert_eq!(o.to_string().parse(), Ok(o));
+        }
+        let twice = pattern
+            .orient(Orientation::Rotate90)
+            .orient(Orientation::Rotate90);
+        assert_eq!(twice, pattern.orient(Orientation::Rotate180));
+    }
+
+    #[test]
+    fn resizing() {
+        let pattern = p("....\n.X2.\n....\n");
+        assert_eq!(pattern.bounding_box(), Some((1, 1, 2, 1)));
+        assert_eq!(pattern.crop().to_plaintext(), "X2\n");
+        assert_eq!(Pattern::new(3, 2).crop(), Pattern::new(0, 0));
+        assert_eq!(pattern.crop().pad(1, 0, 0, 1).to_plaintext(), ".X2\n...\n");
+        assert_eq!(pattern.resize(3, 2).to_plaintext(), "...\n.X2\n");
+        assert_eq!(
+            pattern.translate(-1, 1).to_plaintext(),
+            "....\n....\nX2..\n"
+        );
+        assert_eq!(pattern.translate(5, 0), Pattern::new(4, 3));
+        assert_eq!(p("X.\n").tile(3, 2).to_plaintext(), "X.X.X.\nX.X.X.\n");
+    }
+
+    #[test]
+    fn pasting() {
+        let base = p("XX.\nX..\n");
+        let other = p("X.\n.X\n");
+        let pasted = |mode| {
+            let mut ret = base.clone();
+            ret.paste(&other, 1, 0, mode);
+            ret.to_plaintext()
+        };
+        assert_eq!(pasted(Paste::Or), "XX.\nX.X\n");
+        assert_eq!(pasted(Paste::Xor), "X..\nX.X\n");
+        assert_eq!(pasted(Paste::Replace), "XX.\nX.X\n");
+        let mut replaced = base.clone();
+        replaced.paste(&other, -1, 0, Paste::Replace);
+        assert_eq!(replaced.to_plaintext(), ".X.\nX..\n");
+        assert_eq!("xor".parse(), Ok(Paste::Xor));
+        assert!("and".parse::<Paste>().is_err());
+    }
+
+    #[test]
+    fn obstacles() {
+        let pattern = p("#..X\n.X.@\n");
+        let rotated = pattern.orient(Orientation::Rotate180);
+        assert_eq!(rotated.to_plaintext(), "@.X.\nX..#\n");
+        assert_eq!(rotated.get(0, 0), 1);
+        assert_eq!(
+            pattern.orient(Orientation::Transpose).to_plaintext(),
+            "#.\n.X\n..\nX@\n"
+        );
+        assert_eq!(pattern.translate(1, 0).to_plaintext(), ".#..\n..X.\n");
+        assert_eq!(p("....\n.#..\n").crop().to_plaintext(), "#\n");
+        assert_eq!(
+            pattern.pad(1, 0, 0, 1).to_plaintext(),
+            ".#..X\n..X.@\n.....\n"
+        );
+        assert_eq!(pattern.tile(2, 1).to_plaintext(), "#..X#..X\n.X.@.X.@\n");
+
+        let mut pasted = p("XXXX\n....\n");
+        pasted.paste(&pattern, 0, 0, Paste::Or);
+        assert_eq!(pasted.to_plaintext(), "#XXX\n.X.@\n");
+        assert_eq!(pasted.get(0, 0), 0);
+        let mut replaced = pattern.clone();
+        replaced.paste(&p("..\n..\n"), 2, 0, Paste::Replace);
+        assert_eq!(replaced.to_plaintext(), "#...\n.X..\n");
+    }
+}
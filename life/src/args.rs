@@ -0,0 +1,186 @@
+//! A small command line parser, the project doesn't use any dependencies.
+//!
+//! Options are `--name value`, `--name=value` or `-n value`, flags have no
+//! value. `--` ends the options and a lone `-` is a positional argument, the
+//! usual name of stdin, so are negative numbers like `-2`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+#[derive(Clone, Copy)]
+pub struct Opt {
+    pub long: &'static str,
+    pub short: Option<char>,
+    /// Name of the value shown in the help, `None` for flags.
+    pub value: Option<&'static str>,
+    pub help: &'static str,
+}
+
+#[derive(Debug, Default)]
+pub struct Matches {
+    /// Values of the given options by their long name, empty for flags.
+    values: HashMap<&'static str, String>,
+    pub positional: Vec<String>,
+}
+
+impl Matches {
+    pub fn flag(&self, long: &str) -> bool {
+        self.values.contains_key(long)
+    }
+
+    pub fn value(&self, long: &str) -> Option<&str> {
+        self.values.get(long).map(String::as_str)
+    }
+
+    /// Parses the value of the option, `None` when it wasn't given.
+    pub fn parse<T: FromStr>(&self, long: &str) -> Result<Option<T>, String> {
+        match self.value(long) {
+            None => Ok(None),
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("Error: Invalid value {} of --{}.", value, long)),
+        }
+    }
+}
+
+pub fn parse(opts: &[Opt], args: impl IntoIterator<Item = String>) -> Result<Matches, String> {
+    let mut ret = Matches::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            ret.positional.extend(args);
+            break;
+        }
+        let negative = arg
+            .strip_prefix('-')
+            .is_some_and(|n| n.starts_with(|c: char| c.is_ascii_digit()));
+        let (opt, inline) = if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            (opts.iter().find(|o| o.long == name), inline)
+        } else if arg.len() == 2 && arg.starts_with('-') && !negative {
+            let short = arg.chars().nth(1);
+            (opts.iter().find(|o| o.short == short), None)
+        } else {
+            ret.positional.push(arg);
+            continue;
+        };
+
+        let opt = opt.ok_or_else(|| format!("Error: Unknown option {}, see --help.", arg))?;
+        let value = match (opt.value, inline) {
+            (Some(_), Some(value)) => value,
+            (Some(_), None) => args
+                .next()
+                .ok_or_else(|| format!("Error: Missing value of --{}.", opt.long))?,
+            (None, Some(_)) => return Err(format!("Error: --{} takes no value.", opt.long)),
+            (None, None) => String::new(),
+        };
+        ret.values.insert(opt.long, value);
+    }
+    Ok(ret)
+}
+
+/// The help text with the options aligned under `usage`.
+pub fn help(usage: &str, opts: &[Opt]) -> String {
+    let names: Vec<String> = opts
+        .iter()
+        .map(|o| {
+            let short = o.short.map_or("    ".to_string(), |c| format!("-{}, ", c));
+            let value = o.value.map_or(String::new(), |v| format!(" {}", v));
+            format!("{}--{}{}", short, o.long, value)
+        })
+        .collect();
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+    let mut ret = format!("{}\n\nOptions:\n", usage.trim_end());
+    for (name, opt) in names.iter().zip(opts) {
+        let mut lines = opt.help.lines();
+        ret.push_str(&format!(
+            "  {:width$}  {}\n",
+            name,
+            lines.next().unwrap_or(""),
+            width = width
+        ));
+        for line in lines {
+            ret.push_str(&format!("  {:width$}  {}\n", "", line, width = width));
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const OPTS: &[Opt] = &[
+        Opt {
+            long: "engine",
+            short: Some('e'),
+            value: Some("NAME"),
+            help: "engine to use",
+        },
+        Opt {
+            long: "quiet",
+            short: Some('q'),
+            value: None,
+            help: "no output\nat all",
+        },
+        Opt {
+            long: "every",
+            short: None,
+            value: Some("N"),
+            help: "every N",
+        },
+    ];
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn options() {
+        let m = parse(OPTS, args("-e simple board.txt --every=5 -q 10")).unwrap();
+        assert_eq!(m.value("engine"), Some("simple"));
+        assert_eq!(m.parse::<usize>("every"), Ok(Some(5)));
+        assert!(m.flag("quiet"));
+        assert_eq!(m.positional, args("board.txt 10"));
+
+        let m = parse(OPTS, args("- --engine bitboards -- --quiet")).unwrap();
+        assert_eq!(m.value("engine"), Some("bitboards"));
+        assert!(!m.flag("quiet"));
+        assert_eq!(m.parse::<usize>("every"), Ok(None));
+        assert_eq!(m.positional, args("- --quiet"));
+
+        let m = parse(OPTS, args("7 -2 -q -10 -0.5 --every -3")).unwrap();
+        assert_eq!(m.positional, args("7 -2 -10 -0.5"));
+        assert_eq!(m.value("every"), Some("-3"));
+        assert!(m.flag("quiet"));
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(OPTS, args("--unknown")).is_err());
+        assert!(parse(OPTS, args("-x")).is_err());
+        assert!(parse(OPTS, args("--engine")).is_err());
+        assert!(parse(OPTS, args("--quiet=yes")).is_err());
+        let m = parse(OPTS, args("--every five")).unwrap();
+        assert!(m.parse::<usize>("every").is_err());
+    }
+
+    #[test]
+    fn help_text() {
+        assert_eq!(
+            help("Usage: life", OPTS),
+            "Usage: life
+
+Options:
+  -e, --engine NAME  engine to use
+  -q, --quiet        no output
+                     at all
+      --every N      every N
+"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+//! `life batch`, many small boards stepped 64 at a time.
+
+use crate::args::{self, Opt};
+use crate::run;
+use bitboards::batch::{Batch, BOARDS};
+use gol::pattern::split_boards;
+use gol::{Pattern, Rule, Topology};
+use life::format::Format;
+use std::io::Write;
+
+const USAGE: &str = "Usage: life batch [options] boards iterations
+
+Steps every board of the file, separated by empty lines and all of the same
+size, with B3/S23 and prints them in the same order, also separated by empty
+lines. The boards are run 64 at a time by the bit sliced batch engine. A path
+of - reads stdin.";
+
+const OPTIONS: &[Opt] = &[Opt {
+    long: "populations",
+    short: Some('p'),
+    value: None,
+    help: "print the population of each board instead",
+}];
+
+fn options() -> Vec<Opt> {
+    let run = ["topology", "format", "output", "quiet", "help"];
+    let run = run::OPTIONS.iter().filter(|o| run.contains(&o.long));
+    OPTIONS.iter().chain(run).copied().collect()
+}
+
+/// Splits the file at its empty lines and reads each board.
+fn read_boards(input: &str, format: Format) -> Result<Vec<Pattern>, String> {
+    let mut ret = Vec::new();
+    for board in split_boards(input) {
+        let pattern = format.read(&board)?;
+        if pattern
+            .rule
+            .as_deref()
+            .is_some_and(|r| r.parse() != Ok(Rule::default()))
+        {
+            return Err("Error: The batch engine only runs B3/S23.".to_string());
+        }
+        ret.push(pattern);
+    }
+    Ok(ret)
+}
+
+/// Steps the boards in batches of 64.
+fn step(boards: &[Pattern], topology: Topology, iterations: u64) -> Result<Vec<Pattern>, String> {
+    let mut ret = Vec::with_capacity(boards.len());
+    for chunk in boards.chunks(BOARDS) {
+        let mut batch = Batch::new(chunk[0].width, chunk[0].height);
+        batch.topology = topology;
+        for (board, pattern) in chunk.iter().enumerate() {
+            batch.load(board, pattern)?;
+        }
+        for _ in 0..iterations {
+            batch.step();
+        }
+        ret.extend((0..chunk.len()).map(|board| batch.extract(board)));
+    }
+    Ok(ret)
+}
+
+pub fn batch(args: Vec<String>) -> Result<(), String> {
+    let opts = options();
+    let m = args::parse(&opts, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, &opts));
+        return Ok(());
+    }
+    let (path, iterations) = match &m.positional[..] {
+        [path, iterations] => (path, run::parse_iterations(iterations)?),
+        _ => return Err("Error: Expected boards and iterations, see --help.".to_string()),
+    };
+    let input = run::read_input(path)?;
+    let (input_format, format) = run::formats(m.value("format"), &input)?;
+    let topology = m.parse::<Topology>("topology")?.unwrap_or_default();
+    let boards = step(&read_boards(&input, input_format)?, topology, iterations)?;
+    if m.flag("quiet") {
+        return Ok(());
+    }
+
+    let mut out = run::open_output(m.value("output"))?;
+    let written = boards.iter().enumerate().try_for_each(|(i, board)| {
+        if m.flag("populations") {
+            writeln!(out, "{}", board.cells.iter().filter(|&&c| c == 1).count())
+        } else {
+            let separator = if i == 0 { "" } else { "\n" };
+            write!(out, "{}{}", separator, format.write_pattern(board))
+        }
+    });
+    written
+        .and_then(|_| out.flush())
+        .map_err(|_| "Error: Couldn't write the output.".to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn boards() {
+        let input = "...\nXXX\n...\n\n\nX..\n.X.\n...\n";
+        let boards = read_boards(input, Format::Plaintext).unwrap();
+        assert_eq!(boards.len(), 2);
+        let stepped = step(&boards, Topology::Bounded, 1).unwrap();
+        assert_eq!(stepped[0].to_plaintext(), ".X.\n.X.\n.X.\n");
+        assert_eq!(stepped[1].to_plaintext(), "...\n...\n...\n");
+
+        // more than one batch
+        let many: Vec<Pattern> = (0..130).map(|i| boards[i % 2].clone()).collect();
+        let stepped = step(&many, Topology::Bounded, 2).unwrap();
+        assert_eq!(stepped.len(), 130);
+        assert_eq!(stepped[128], boards[0]);
+
+        let sizes = "XX\n\nX\n";
+        let boards = read_boards(sizes, Format::Plaintext).unwrap();
+        assert!(step(&boards, Topology::Torus, 1).is_err());
+        let states = read_boards("X2\n..\n", Format::Plaintext).unwrap();
+        assert!(step(&states, Topology::Bounded, 1).is_err());
+        let rle = "x = 1, y = 1, rule = B36/S23\no!\n";
+        assert!(read_boards(rle, Format::Rle).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+//! `life census`, what settled boards and soups leave behind.
+
+use crate::args::{self, Opt};
+use crate::generate;
+use crate::run::{self, Run};
+use gol::{Pattern, Topology};
+use life::engines;
+use life::format::Format;
+use life::objects::{self, Census};
+
+const USAGE: &str = "Usage: life census [options] initial_state
+       life census [options] --soups N width height
+
+Steps the board until it settles, splits it into its objects and prints how
+many of each there are by apgcode. With --soups it does the same for N random
+soups of width x height with the seeds from --seed on.";
+
+const OPTIONS: &[Opt] = &[
+    Opt {
+        long: "soups",
+        short: Some('n'),
+        value: Some("N"),
+        help: "census of N soups instead of a board",
+    },
+    Opt {
+        long: "max-generations",
+        short: None,
+        value: Some("N"),
+        help: "give up on boards which don't settle within N\n\
+               generations, 100000 by default",
+    },
+];
+
+fn options() -> Vec<Opt> {
+    let generate = generate::OPTIONS.iter().filter(|o| o.long != "format");
+    let run = ["engine", "rule", "topology", "output", "help"];
+    let run = run::OPTIONS.iter().filter(|o| run.contains(&o.long));
+    OPTIONS.iter().chain(generate).chain(run).copied().collect()
+}
+
+pub fn census(args: Vec<String>) -> Result<(), String> {
+    let opts = options();
+    let m = args::parse(&opts, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, &opts));
+        return Ok(());
+    }
+    let max_generations = m.parse::<u64>("max-generations")?.unwrap_or(100_000);
+    // the soups are generated one at a time
+    let boards: Box<dyn Iterator<Item = Result<Pattern, String>>> =
+        match (&m.positional[..], m.parse::<u64>("soups")?) {
+            ([path], None) => {
+                let input = run::read_input(path)?;
+                Box::new(std::iter::once(Format::detect(&input).read(&input)))
+            }
+            ([width, height], Some(soups)) => {
+                let soup = generate::soup(&m, width, height)?;
+                Box::new((0..soups).map(move |i| {
+                    let mut soup = soup.clone();
+                    soup.seed = soup.seed.wrapping_add(i);
+                    soup.generate()
+                }))
+            }
+            _ => return Err("Error: Expected initial_state, see --help.".to_string()),
+        };
+
+    let mut census = Census::default();
+    for pattern in boards {
+        let mut run = Run::new(&m, &pattern?)?;
+        let (engine, rule, table) = (&run.engine, &run.rule, run.table.as_deref());
+        let build = |p: &Pattern| {
+            engines::build_with_table(Some(engine), p, Some(rule), table, Topology::Bounded)
+        };
+        let Some((_, period)) = objects::settle(&mut *run.life, max_generations) else {
+            census.unsettled += 1;
+            continue;
+        };
+        for object in objects::objects(&mut *run.life, period, &build)? {
+            census.add(&object);
+        }
+    }
+
+    run::write_output(m.value("output"), &census.to_string())
+}
@@ -0,0 +1,276 @@
+//! An engine stepping on a worker thread, controlled over channels, so an
+//! interactive tool stays responsive while large boards are stepped.
+//!
+//! Commands go to the worker in order. Snapshots of the board come back
+//! after `Step` and `Pause`, when asked for and, while running, at most once
+//! per frame interval, so a viewer can render the newest one it received.
+
+use crate::engines;
+use gol::{BitGrid, Engine, Pattern, Topology};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Steps the number of generations, then sends a snapshot.
+    Step(u64),
+    /// Steps until paused.
+    Run,
+    /// Stops running and sends a snapshot.
+    Pause,
+    /// Sets the state of the cell at `x`, `y`.
+    Set(usize, usize, u8),
+    /// Rebuilds the engine with the rulestring, keeping the board.
+    Rule(String),
+    Snapshot,
+}
+
+/// The board at some generation, every state other than dead is alive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub generation: u64,
+    pub population: usize,
+    pub running: bool,
+    pub board: BitGrid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    Snapshot(Snapshot),
+    /// A command which couldn't be done, the worker goes on.
+    Error(String),
+}
+
+struct Worker {
+    life: Box<dyn Engine + Send>,
+    /// Engine name for rebuilding it with another rule, the default one for
+    /// each rule without it.
+    engine: Option<String>,
+    generation: u64,
+    running: bool,
+    events: Sender<Event>,
+}
+
+impl Worker {
+    fn snapshot(&self) {
+        // nobody listening isn't an error, the commands still count
+        let _ = self.events.send(Event::Snapshot(Snapshot {
+            generation: self.generation,
+            population: self.life.population(),
+            running: self.running,
+            board: self.life.to_bitgrid(),
+        }));
+    }
+
+    fn execute(&mut self, command: Command) -> Result<(), String> {
+        match command {
+            Command::Step(n) => {
+                for _ in 0..n {
+                    self.life.step();
+                }
+                self.generation += n;
+                self.snapshot();
+            }
+            Command::Run => self.running = true,
+            Command::Pause => {
+                self.running = false;
+                self.snapshot();
+            }
+            Command::Set(x, y, state) => {
+                if x >= self.life.width() || y >= self.life.height() {
+                    return Err(format!("Error: Cell {} {} is outside the board.", x, y));
+                }
+                if state as usize >= self.life.states() {
+                    return Err("Error: Cell state is not valid for the rule.".to_string());
+                }
+                self.life.set(x, y, state);
+            }
+            Command::Rule(rule) => {
+                let pattern = self.life.to_pattern();
+                let topology = self.life.topology();
+                self.life =
+                    engines::build(self.engine.as_deref(), &pattern, Some(&rule), topology)?;
+            }
+            Command::Snapshot => self.snapshot(),
+        }
+        Ok(())
+    }
+
+    /// Runs the commands until the controller is dropped.
+    fn work(mut self, commands: Receiver<Command>, frame: Duration) -> Box<dyn Engine + Send> {
+        let mut last_frame = Instant::now();
+        loop {
+            let command = if self.running {
+                match commands.try_recv() {
+                    Ok(command) => Some(command),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => break,
+                }
+            } else {
+                match commands.recv() {
+                    Ok(command) => Some(command),
+                    Err(_) => break,
+                }
+            };
+            match command {
+                Some(command) => {
+                    if let Err(e) = self.execute(command) {
+                        let _ = self.events.send(Event::Error(e));
+                    }
+                }
+                None => {
+                    self.life.step();
+                    self.generation += 1;
+                    if last_frame.elapsed() >= frame {
+                        self.snapshot();
+                        last_frame = Instant::now();
+                    }
+                }
+            }
+        }
+        self.life
+    }
+}
+
+pub struct Controller {
+    commands: Option<Sender<Command>>,
+    events: Receiver<Event>,
+    worker: Option<JoinHandle<Box<dyn Engine + Send>>>,
+}
+
+impl Controller {
+    /// Builds the engine as `engines::build` does and starts its worker,
+    /// which sends at most one snapshot per `frame` while running.
+    pub fn new(
+        engine: Option<&str>,
+        pattern: &Pattern,
+        rule: Option<&str>,
+        topology: Topology,
+        frame: Duration,
+    ) -> Result<Self, String> {
+        let life = engines::build(engine, pattern, rule, topology)?;
+        let (commands, receiver) = mpsc::channel();
+        let (sender, events) = mpsc::channel();
+        let worker = Worker {
+            life,
+            engine: engine.map(str::to_string),
+            generation: 0,
+            running: false,
+            events: sender,
+        };
+        let worker = thread::spawn(move || worker.work(receiver, frame));
+        Ok(Controller {
+            commands: Some(commands),
+            events,
+            worker: Some(worker),
+        })
+    }
+
+    /// Queues the command, which fails only if the worker is gone.
+    pub fn send(&self, command: Command) -> Result<(), String> {
+        self.commands
+            .as_ref()
+            .and_then(|c| c.send(command).ok())
+            .ok_or_else(|| "Error: The simulation thread has stopped.".to_string())
+    }
+
+    /// The snapshots and errors from the worker.
+    pub fn events(&self) -> &Receiver<Event> {
+        &self.events
+    }
+
+    /// The next snapshot, errors sent before it are returned instead.
+    pub fn next_snapshot(&self) -> Result<Snapshot, String> {
+        match self.events.recv() {
+            Ok(Event::Snapshot(snapshot)) => Ok(snapshot),
+            Ok(Event::Error(e)) => Err(e),
+            Err(_) => Err("Error: The simulation thread has stopped.".to_string()),
+        }
+    }
+
+    /// Stops the worker and returns the engine.
+    pub fn stop(mut self) -> Box<dyn Engine + Send> {
+        self.commands = None;
+        let worker = self.worker.take().unwrap();
+        worker.join().expect("simulation thread panicked")
+    }
+}
+
+impl Drop for Controller {
+    fn drop(&mut self) {
+        self.commands = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn controller(frame: Duration) -> Controller {
+        let glider = Pattern::from_plaintext(".X.\n..X\nXXX\n").unwrap();
+        let board = glider.pad(0, 0, 9, 9);
+        Controller::new(None, &board, None, Topology::Torus, frame).unwrap()
+    }
+
+    #[test]
+    fn commands() {
+        let controller = controller(Duration::from_secs(60));
+        controller.send(Command::Step(4)).unwrap();
+        let snapshot = controller.next_snapshot().unwrap();
+        assert_eq!((snapshot.generation, snapshot.population), (4, 5));
+        // moved by one cell down and to the right
+        assert!(snapshot.board.get(2, 1) && snapshot.board.get(3, 2));
+
+        controller.send(Command::Set(10, 10, 1)).unwrap();
+        controller.send(Command::Set(20, 0, 1)).unwrap();
+        controller.send(Command::Snapshot).unwrap();
+        assert!(controller.next_snapshot().is_err());
+        let snapshot = controller.next_snapshot().unwrap();
+        assert_eq!(snapshot.population, 6);
+
+        // a lone cell survives with S0
+        controller
+            .send(Command::Rule("B3/S023".to_string()))
+            .unwrap();
+        controller.send(Command::Step(1)).unwrap();
+        let snapshot = controller.next_snapshot().unwrap();
+        assert!(snapshot.board.get(10, 10));
+
+        // a Generations rule moves the board to its engine, where the lone
+        // cell starts dying
+        controller
+            .send(Command::Rule("B3/S23/C4".to_string()))
+            .unwrap();
+        controller.send(Command::Step(1)).unwrap();
+        let snapshot = controller.next_snapshot().unwrap();
+        assert!(snapshot.board.get(10, 10));
+
+        let life = controller.stop();
+        assert_eq!(life.rule(), "B3/S23/C4");
+        assert_eq!(life.get(10, 10), 2);
+        assert_eq!(life.topology(), Topology::Torus);
+    }
+
+    #[test]
+    fn running() {
+        let controller = controller(Duration::ZERO);
+        controller.send(Command::Run).unwrap();
+        let running = controller.next_snapshot().unwrap();
+        assert!(running.running);
+        controller.send(Command::Pause).unwrap();
+        // the snapshot of the pause is the last one
+        let paused = controller.events().iter().find_map(|event| match event {
+            Event::Snapshot(s) if !s.running => Some(s),
+            _ => None,
+        });
+        let paused = paused.unwrap();
+        assert!(paused.generation >= running.generation);
+        assert_eq!(paused.population, 5);
+        controller.send(Command::Snapshot).unwrap();
+        assert_eq!(controller.next_snapshot(), Ok(paused));
+    }
+}
@@ -0,0 +1,59 @@
+//! `life diff`, the births and deaths between two boards.
+
+use crate::args::{self, Opt};
+use crate::run::{self, DiffMode};
+use gol::diff::Diff;
+use gol::BitGrid;
+use life::format::Format;
+
+const USAGE: &str = "Usage: life diff [options] before after
+
+Prints the cells which are alive in after but not in before as + x y lines
+and the ones which died as - x y lines, or with --grid the board after with
+the births as + and the deaths as -. Both boards have the same size, every
+state other than dead counts as alive.";
+
+const OPTIONS: &[Opt] = &[
+    Opt {
+        long: "grid",
+        short: Some('g'),
+        value: None,
+        help: "print the changes as a grid",
+    },
+    Opt {
+        long: "output",
+        short: Some('o'),
+        value: Some("PATH"),
+        help: "write to the file instead of stdout",
+    },
+    Opt {
+        long: "help",
+        short: Some('h'),
+        value: None,
+        help: "print this help",
+    },
+];
+
+fn read_grid(path: &str) -> Result<BitGrid, String> {
+    let input = run::read_input(path)?;
+    Ok(BitGrid::from_pattern(&Format::detect(&input).read(&input)?))
+}
+
+pub fn diff(args: Vec<String>) -> Result<(), String> {
+    let m = args::parse(OPTIONS, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, OPTIONS));
+        return Ok(());
+    }
+    let [before, after] = &m.positional[..] else {
+        return Err("Error: Expected before and after, see --help.".to_string());
+    };
+    let diff = Diff::new(&read_grid(before)?, &read_grid(after)?)?;
+    let mode = if m.flag("grid") {
+        DiffMode::Grid
+    } else {
+        DiffMode::List
+    };
+    let output = mode.write(&diff);
+    run::write_output(m.value("output"), &output)
+}
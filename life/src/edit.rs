@@ -0,0 +1,206 @@
+//! `life edit`, transforming and composing boards.
+
+use crate::args::{self, Opt};
+use crate::run;
+use gol::transform::{Orientation, Paste};
+use gol::Pattern;
+use life::format::Format;
+
+const USAGE: &str = "Usage: life edit [options] input [operation...]
+
+Applies the operations to the board in input from left to right and prints
+the result, in the same format unless told otherwise. The operations are:
+
+  rotate90, rotate180, rotate270  rotate clockwise
+  flip-horizontal, flip-vertical  mirror left to right or top to bottom
+  transpose, antitranspose        mirror along a diagonal
+  translate=DX,DY                 move the cells within the board
+  crop                            cut down to the bounding box
+  pad=N or pad=LEFT,TOP,RIGHT,BOTTOM
+                                  add dead cells around the board
+  resize=WxH                      change the size, keeping the top left corner
+  tile=NxM                        N columns and M rows of copies
+  paste=PATH,X,Y[,MODE]           put the board in PATH at X,Y, the mode is
+                                  or (the default), xor or replace";
+
+const OPTIONS: &[Opt] = &[
+    Opt {
+        long: "format",
+        short: Some('f'),
+        value: Some("IN[/OUT]"),
+        help: "plaintext, rle, mc, hex or pbm (both output only),\n\
+               the input format is detected when empty and the\n\
+               output one defaults to it",
+    },
+    Opt {
+        long: "output",
+        short: Some('o'),
+        value: Some("PATH"),
+        help: "write to the file instead of stdout",
+    },
+    Opt {
+        long: "help",
+        short: Some('h'),
+        value: None,
+        help: "print this help",
+    },
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Operation {
+    Orient(Orientation),
+    Translate(i64, i64),
+    Crop,
+    Pad(usize, usize, usize, usize),
+    Resize(usize, usize),
+    Tile(usize, usize),
+    Paste(Pattern, i64, i64, Paste),
+}
+
+/// Parses the comma separated numbers of an operation.
+fn numbers<T: std::str::FromStr>(s: &str, separator: char) -> Result<Vec<T>, String> {
+    s.split(separator)
+        .map(|n| n.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Error: Invalid numbers {}.", s))
+}
+
+fn read_pattern(path: &str) -> Result<Pattern, String> {
+    let input = run::read_input(path)?;
+    Format::detect(&input).read(&input)
+}
+
+impl Operation {
+    /// Parses one operation, `read` loads the pattern of a paste.
+    fn parse(s: &str, read: impl Fn(&str) -> Result<Pattern, String>) -> Result<Self, String> {
+        let (name, value) = s.split_once('=').unwrap_or((s, ""));
+        let invalid = || format!("Error: Invalid operation {}, see --help.", s);
+        Ok(match (name, value) {
+            ("crop", "") => Operation::Crop,
+            ("translate", value) => match numbers(value, ',')?[..] {
+                [dx, dy] => Operation::Translate(dx, dy),
+                _ => return Err(invalid()),
+            },
+            ("pad", value) => match numbers(value, ',')?[..] {
+                [n] => Operation::Pad(n, n, n, n),
+                [left, top, right, bottom] => Operation::Pad(left, top, right, bottom),
+                _ => return Err(invalid()),
+            },
+            ("resize", value) => match numbers(value, 'x')?[..] {
+                [width, height] => Operation::Resize(width, height),
+                _ => return Err(invalid()),
+            },
+            ("tile", value) => match numbers(value, 'x')?[..] {
+                [columns, rows] => Operation::Tile(columns, rows),
+                _ => return Err(invalid()),
+            },
+            ("paste", value) => {
+                // from the right, so the path may contain commas
+                let mut parts: Vec<&str> = value.rsplitn(4, ',').collect();
+                let mode = match parts[0].parse::<Paste>() {
+                    Ok(mode) => {
+                        parts.remove(0);
+                        mode
+                    }
+                    Err(_) => {
+                        parts = value.rsplitn(3, ',').collect();
+                        Paste::Or
+                    }
+                };
+                match parts[..] {
+                    [y, x, path] => {
+                        let (x, y) = (numbers(x, ',')?[0], numbers(y, ',')?[0]);
+                        Operation::Paste(read(path)?, x, y, mode)
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            (name, "") => Operation::Orient(name.parse().map_err(|_| invalid())?),
+            _ => return Err(invalid()),
+        })
+    }
+
+    fn apply(&self, pattern: &Pattern) -> Pattern {
+        match self {
+            Operation::Orient(orientation) => pattern.orient(*orientation),
+            Operation::Translate(dx, dy) => pattern.translate(*dx, *dy),
+            Operation::Crop => pattern.crop(),
+            Operation::Pad(left, top, right, bottom) => pattern.pad(*left, *top, *right, *bottom),
+            Operation::Resize(width, height) => pattern.resize(*width, *height),
+            Operation::Tile(columns, rows) => pattern.tile(*columns, *rows),
+            Operation::Paste(other, x, y, mode) => {
+                let mut ret = pattern.clone();
+                ret.paste(other, *x, *y, *mode);
+                ret
+            }
+        }
+    }
+}
+
+pub fn edit(args: Vec<String>) -> Result<(), String> {
+    let m = args::parse(OPTIONS, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, OPTIONS));
+        return Ok(());
+    }
+    let Some((path, operations)) = m.positional.split_first() else {
+        return Err("Error: Expected input, see --help.".to_string());
+    };
+    let operations = operations
+        .iter()
+        .map(|o| Operation::parse(o, read_pattern))
+        .collect::<Result<Vec<_>, _>>()?;
+    let input = run::read_input(path)?;
+    let (in_format, out_format) = run::formats(m.value("format"), &input)?;
+    let mut pattern = in_format.read(&input)?;
+    for operation in &operations {
+        pattern = operation.apply(&pattern);
+    }
+
+    let output = out_format.write_pattern(&pattern);
+    run::write_output(m.value("output"), &output)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(s: &str) -> Result<Operation, String> {
+        Operation::parse(s, |path| {
+            assert_eq!(path, "dir,1/glider.rle");
+            Ok(Pattern::new(1, 1))
+        })
+    }
+
+    #[test]
+    fn operations() {
+        assert_eq!(
+            parse("rotate90"),
+            Ok(Operation::Orient(Orientation::Rotate90))
+        );
+        assert_eq!(parse("translate=-2,3"), Ok(Operation::Translate(-2, 3)));
+        assert_eq!(parse("pad=1"), Ok(Operation::Pad(1, 1, 1, 1)));
+        assert_eq!(parse("pad=1,2,3,4"), Ok(Operation::Pad(1, 2, 3, 4)));
+        assert_eq!(parse("resize=10x5"), Ok(Operation::Resize(10, 5)));
+        assert_eq!(parse("tile=2x3"), Ok(Operation::Tile(2, 3)));
+        assert_eq!(parse("crop"), Ok(Operation::Crop));
+        assert_eq!(
+            parse("paste=dir,1/glider.rle,4,-1"),
+            Ok(Operation::Paste(Pattern::new(1, 1), 4, -1, Paste::Or))
+        );
+        assert_eq!(
+            parse("paste=dir,1/glider.rle,4,5,xor"),
+            Ok(Operation::Paste(Pattern::new(1, 1), 4, 5, Paste::Xor))
+        );
+        for invalid in [
+            "rotate45",
+            "crop=1",
+            "pad=1,2",
+            "tile=2",
+            "translate=a,b",
+            "paste=x",
+        ] {
+            assert!(parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
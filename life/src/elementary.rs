@@ -0,0 +1,93 @@
+//! `life elementary`, Wolfram's one dimensional automata drawn as a board.
+
+use crate::args::{self, Opt};
+use crate::run;
+use gol::elementary::{Boundary, Elementary};
+use gol::pattern::MAX_CELLS;
+use life::format::Format;
+
+const USAGE: &str = "Usage: life elementary [options] rule generations [initial_state]
+
+Evolves the first row of initial_state, or a single live cell in the middle of
+a row of --width cells, under the elementary rule (0 to 255) and prints every
+generation as the next row of a board, the initial one first. A path of -
+reads stdin.";
+
+const OPTIONS: &[Opt] = &[
+    Opt {
+        long: "width",
+        short: Some('w'),
+        value: Some("N"),
+        help: "width of the row without initial_state, 79 by\n\
+               default",
+    },
+    Opt {
+        long: "boundary",
+        short: Some('b'),
+        value: Some("MODE"),
+        help: "what is beyond the ends of the row, dead (the\n\
+               default) or alive cells, the other end (wrap) or\n\
+               the end cell itself (reflect)",
+    },
+];
+
+fn options() -> Vec<Opt> {
+    let run = ["format", "output", "help"];
+    let run = run::OPTIONS.iter().filter(|o| run.contains(&o.long));
+    OPTIONS.iter().chain(run).copied().collect()
+}
+
+pub fn elementary(args: Vec<String>) -> Result<(), String> {
+    let opts = options();
+    let m = args::parse(&opts, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, &opts));
+        return Ok(());
+    }
+    let (rule, generations, path) = match &m.positional[..] {
+        [rule, generations] => (rule, generations, None),
+        [rule, generations, path] => (rule, generations, Some(path)),
+        _ => return Err("Error: Expected rule and generations, see --help.".to_string()),
+    };
+    let rule = rule
+        .parse::<u8>()
+        .map_err(|_| "Error: The rule is a number from 0 to 255.".to_string())?;
+    let generations = usize::try_from(run::parse_iterations(generations)?)
+        .map_err(|_| "Error: The board is too large.".to_string())?;
+
+    let (mut ca, format) = match path {
+        Some(path) => {
+            if m.value("width").is_some() {
+                return Err("Error: The width comes from initial_state.".to_string());
+            }
+            let input = run::read_input(path)?;
+            let (input_format, format) = run::formats(m.value("format"), &input)?;
+            (
+                Elementary::from_pattern(rule, &input_format.read(&input)?),
+                format,
+            )
+        }
+        None => {
+            let width = m.parse::<usize>("width")?.unwrap_or(79);
+            if width > MAX_CELLS {
+                return Err("Error: The board is too large.".to_string());
+            }
+            let format = match m.value("format") {
+                Some(value) => run::formats(Some(value), "")?.1,
+                None => Format::Plaintext,
+            };
+            let mut ca = Elementary::new(rule, width);
+            if width > 0 {
+                ca.set(width / 2, true);
+            }
+            (ca, format)
+        }
+    };
+    if ca.width() == 0 {
+        return Err("Error: The row is empty.".to_string());
+    }
+    ca.boundary = m.parse::<Boundary>("boundary")?.unwrap_or_default();
+    let board = ca.run(generations)?;
+
+    run::write_output(m.value("output"), &format.write_pattern(&board))
+}
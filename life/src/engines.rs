@@ -0,0 +1,367 @@
+//! Building any of the engines from a pattern by name.
+
+use gol::{Checkpoint, Engine, Pattern, Rule, Table, Topology};
+use std::fs;
+
+/// Names of the engines, as accepted by `build`.
+pub const ENGINES: [&str; 6] = [
+    "simple",
+    "opt01",
+    "opt02",
+    "bitboards",
+    "generations",
+    "ruletable",
+];
+
+/// Picks the engine for the rule, `ruletable` for `.rule` files, the fastest
+/// one for life-like rules and `generations` for the rest.
+fn default_engine(rule: &str) -> &'static str {
+    if rule.ends_with(".rule") {
+        "ruletable"
+    } else if rule.parse::<Rule>().is_ok() {
+        "bitboards"
+    } else {
+        "generations"
+    }
+}
+
+/// The engine and rule `build` uses for the arguments.
+pub fn resolve<'a>(
+    name: Option<&'a str>,
+    pattern: &'a Pattern,
+    rule: Option<&'a str>,
+) -> (&'a str, &'a str) {
+    let rule = rule.or(pattern.rule.as_deref()).unwrap_or("B3/S23");
+    (name.unwrap_or_else(|| default_engine(rule)), rule)
+}
+
+/// Reads the `.rule` file the `ruletable` engine runs.
+pub fn read_table(path: &str) -> Result<String, String> {
+    if !path.ends_with(".rule") {
+        return Err("Error: The ruletable engine needs a .rule file as rule.".to_string());
+    }
+    fs::read_to_string(path).map_err(|_| format!("Error: Couldn't open rule file {}", path))
+}
+
+/// Builds the engine `name`, or the default one for the rule, with the cells
+/// of `pattern`. The rule defaults to the one of the pattern's header and
+/// then to `B3/S23`, for `ruletable` it is the path of a `.rule` file.
+pub fn build(
+    name: Option<&str>,
+    pattern: &Pattern,
+    rule: Option<&str>,
+    topology: Topology,
+) -> Result<Box<dyn Engine + Send>, String> {
+    build_with_table(name, pattern, rule, None, topology)
+}
+
+/// `build`, with the text of the rule table for `ruletable` given instead
+/// of read from the file the rule names.
+pub fn build_with_table(
+    name: Option<&str>,
+    pattern: &Pattern,
+    rule: Option<&str>,
+    table: Option<&str>,
+    topology: Topology,
+) -> Result<Box<dyn Engine + Send>, String> {
+    if pattern.width == 0 || pattern.height == 0 {
+        return Err("Error: The board is empty.".to_string());
+    }
+    let (name, rule) = resolve(name, pattern, rule);
+    let mut engine: Box<dyn Engine + Send> = match name {
+        "simple" => {
+            let mut life = simple::LifeState::from(pattern);
+            life.rule = rule.parse()?;
+            life.topology = topology;
+            Box::new(life)
+        }
+        "opt01" => {
+            let mut life = opt_01::LifeState::from(pattern);
+            life.rule = rule.parse()?;
+            life.topology = topology;
+            Box::new(life)
+        }
+        "opt02" => {
+            let mut life = opt_02::LifeState::from(pattern);
+            life.rule = rule.parse()?;
+            life.topology = topology;
+            Box::new(life)
+        }
+        "bitboards" => {
+            let mut life = bitboards::LifeState::from(pattern);
+            life.rule = rule.parse()?;
+            life.topology = topology;
+            Box::new(life)
+        }
+        "generations" => {
+            let rule = generations::Rule::parse(rule)?;
+            let mut life = generations::LifeState::from_pattern(pattern, rule)?;
+            life.topology = topology;
+            Box::new(life)
+        }
+        "ruletable" => {
+            let table: Table = match table {
+                Some(table) => table.parse()?,
+                None => read_table(rule)?.parse()?,
+            };
+            let mut life = ruletable::LifeState::from_pattern(pattern, table)?;
+            life.topology = topology;
+            Box::new(life)
+        }
+        _ => {
+            return Err(format!(
+                "Error: Unknown engine {}, expected one of {}.",
+                name,
+                ENGINES.join(", ")
+            ))
+        }
+    };
+    if pattern.cells.iter().any(|&c| c as usize >= engine.states()) {
+        return Err("Error: Cell state is not valid for the rule.".to_string());
+    }
+    if let Some(mask) = &pattern.mask {
+        engine.set_mask(mask.clone())?;
+    }
+    Ok(engine)
+}
+
+/// Saves the board of the engine `name` built with `rule` by `build`, and
+/// the text of its rule table for `ruletable`.
+pub fn checkpoint(
+    life: &dyn Engine,
+    name: &str,
+    rule: &str,
+    table: Option<&str>,
+    generation: u64,
+) -> Checkpoint {
+    Checkpoint {
+        generation,
+        engine: name.to_string(),
+        rule: rule.to_string(),
+        topology: life.topology(),
+        width: life.width(),
+        height: life.height(),
+        cells: life.save_cells(),
+        mask: life.mask().cloned(),
+        table: table.map(str::to_string),
+    }
+}
+
+/// Rebuilds the engine saved in the checkpoint, with the rule table saved in
+/// it rather than the one in the file.
+pub fn resume(checkpoint: &Checkpoint) -> Result<Box<dyn Engine + Send>, String> {
+    let pattern = Pattern::try_new(checkpoint.width, checkpoint.height)?;
+    let mut life = build_with_table(
+        Some(&checkpoint.engine),
+        &pattern,
+        Some(&checkpoint.rule),
+        checkpoint.table.as_deref(),
+        checkpoint.topology,
+    )?;
+    life.load_cells(&checkpoint.cells)?;
+    if let Some(mask) = &checkpoint.mask {
+        life.set_mask(mask.clone())?;
+    }
+    Ok(life)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use gol::activity::Layer;
+    use gol::noise::Noise;
+
+    #[test]
+    fn engines_agree() {
+        let pattern = Pattern::from_plaintext(
+            "..........\n...XX.....\n..XX......\n...X......\n..........\n.......X..\n",
+        )
+        .unwrap();
+        for topology in [Topology::Bounded, Topology::Torus] {
+            for rule in ["B3/S23", "B36/S23", "B2-a/S12", "B2/S34H"] {
+                let mut expected = build(Some("simple"), &pattern, Some(rule), topology).unwrap();
+                let mut others: Vec<Box<dyn Engine + Send>> = ENGINES[1..5]
+                    .iter()
+                    .filter(|&&name| name != "generations" || !rule.contains(['-', 'H']))
+                    .map(|name| build(Some(name), &pattern, Some(rule), topology).unwrap())
+                    .collect();
+                for _ in 0..12 {
+                    expected.step();
+                    for other in others.iter_mut() {
+                        other.step();
+                        assert_eq!(other.to_pattern().cells, expected.to_pattern().cells);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn activity() {
+        let pattern =
+            Pattern::from_plaintext("........\n.XXX....\n........\n.....XX.\n.....XX.\n").unwrap();
+        let mut counters = Vec::new();
+        for name in ["opt01", "opt02", "generations"] {
+            let mut life = build(Some(name), &pattern, None, Topology::Bounded).unwrap();
+            life.track_activity().unwrap();
+            for _ in 0..5 {
+                life.step();
+            }
+            counters.push(life.activity().unwrap().clone());
+        }
+        assert!(counters.iter().all(|c| *c == counters[0]));
+        // the blinker's ends toggle each step, its centre and the block stay
+        let activity = &counters[0];
+        assert_eq!(activity.changes[8 + 1], 5);
+        assert_eq!(activity.age[8 + 2], 6);
+        assert_eq!(activity.age[4 * 8 + 6], 6);
+        assert_eq!(activity.age[2 * 8 + 2], 1);
+
+        let mut life = build(Some("bitboards"), &pattern, None, Topology::Bounded).unwrap();
+        assert!(life.track_activity().is_err());
+        assert!(life.activity().is_none());
+    }
+
+    #[test]
+    fn heat_map_obstacles() {
+        // the wall blocks a birth of the blinker, the fixed cell never
+        // changes though the rule alone would kill it
+        let pattern = Pattern::from_plaintext(".....\n.XXX.\n..#..\n....@\n").unwrap();
+        for name in ["opt01", "opt02", "generations"] {
+            let mut life = build(Some(name), &pattern, None, Topology::Bounded).unwrap();
+            life.track_activity().unwrap();
+            for _ in 0..4 {
+                life.step();
+            }
+            let activity = life.activity().unwrap();
+            let (wall, fixed) = (2 * 5 + 2, 3 * 5 + 4);
+            assert_eq!(
+                (activity.age[wall], activity.changes[wall]),
+                (0, 0),
+                "{}",
+                name
+            );
+            assert_eq!(
+                (activity.age[fixed], activity.changes[fixed]),
+                (5, 0),
+                "{}",
+                name
+            );
+            let pgm = activity.to_pgm(Layer::Age);
+            assert!(pgm.lines().nth(3 + 3).unwrap().ends_with(" 5"), "{}", name);
+        }
+    }
+
+    #[test]
+    fn obstacles() {
+        // the walls around the pentomino are edges of a bounded board even
+        // on a torus, the fixed cell outside stays alive on its own
+        let walled = Pattern::from_plaintext(
+            "#######...\n#.....#...\n#..XX.#.@.\n#.XX..#...\n#..X..#...\n#.....#...\n#######...\n",
+        )
+        .unwrap();
+        let inside = Pattern::from_plaintext(".....\n..XX.\n.XX..\n..X..\n.....\n").unwrap();
+        let mut bounded = build(Some("simple"), &inside, None, Topology::Bounded).unwrap();
+        let mut engines: Vec<_> = ENGINES[..5]
+            .iter()
+            .map(|name| build(Some(name), &walled, None, Topology::Torus).unwrap())
+            .collect();
+        for _ in 0..10 {
+            bounded.step();
+            for life in engines.iter_mut() {
+                life.step();
+                let cells = life.to_pattern();
+                for y in 0..5 {
+                    for x in 0..5 {
+                        assert_eq!(cells.get(x + 1, y + 1), bounded.get(x, y));
+                    }
+                }
+                assert_eq!(cells.get(8, 2), 1);
+                assert_eq!(life.population(), bounded.population() + 1);
+            }
+        }
+        assert_eq!(engines[3].to_pattern().mask, walled.mask);
+
+        let saved = checkpoint(&*engines[3], "bitboards", "B3/S23", None, 10);
+        let saved = Checkpoint::from_bytes(&saved.to_bytes()).unwrap();
+        assert_eq!(
+            resume(&saved).unwrap().to_pattern(),
+            engines[3].to_pattern()
+        );
+
+        let small = Pattern::from_plaintext("X.\n.X\n").unwrap();
+        let mut life = build(Some("opt02"), &small, None, Topology::Bounded).unwrap();
+        assert!(life.set_mask(walled.mask.clone().unwrap()).is_err());
+    }
+
+    #[test]
+    fn noise() {
+        // the byte engines draw the same numbers cell by cell
+        let pattern = Pattern::from_plaintext("........\n.XXX....\n........\n.....XX.\n").unwrap();
+        let noise: Noise = "flip=0.02,survival=0.9,seed=5".parse().unwrap();
+        let mut engines: Vec<_> = ["simple", "opt01", "opt02"]
+            .iter()
+            .map(|name| build(Some(name), &pattern, None, Topology::Torus).unwrap())
+            .collect();
+        for life in engines.iter_mut() {
+            life.set_noise(noise).unwrap();
+            for _ in 0..20 {
+                life.step();
+            }
+            assert_eq!(life.noise().unwrap().generation, 20);
+        }
+        assert!(engines
+            .iter()
+            .all(|l| l.to_pattern() == engines[0].to_pattern()));
+
+        let mut life = build(Some("generations"), &pattern, None, Topology::Torus).unwrap();
+        assert!(life.set_noise(noise).is_err());
+    }
+
+    #[test]
+    fn default_engines() {
+        let pattern = Pattern::from_plaintext("X.\n.X\n").unwrap();
+        let life = build(None, &pattern, None, Topology::Bounded).unwrap();
+        assert_eq!(life.rule(), "B3/S23");
+        let life = build(None, &pattern, Some("B2/S/C3"), Topology::Bounded).unwrap();
+        assert_eq!(life.states(), 3);
+
+        let mut pattern = Pattern::from_plaintext("X2\n").unwrap();
+        pattern.rule = Some("B2/S/C3".to_string());
+        assert!(build(None, &pattern, None, Topology::Bounded).is_ok());
+        assert!(build(Some("simple"), &pattern, Some("B3/S23"), Topology::Bounded).is_err());
+        assert!(build(Some("quantum"), &pattern, None, Topology::Bounded).is_err());
+        assert!(build(Some("ruletable"), &pattern, None, Topology::Bounded).is_err());
+        assert!(build(None, &Pattern::new(0, 0), None, Topology::Bounded).is_err());
+    }
+
+    #[test]
+    fn checkpoints() {
+        let pattern = Pattern::from_plaintext(
+            "..........\n...XX.....\n..XX......\n...X......\n..........\n.......X..\n",
+        )
+        .unwrap();
+        for topology in [Topology::Bounded, Topology::Torus] {
+            for (name, rule) in ENGINES[..5].iter().map(|&name| (name, "B36/S23")).chain([
+                ("generations", "B2/S/C3"),
+                ("ruletable", "../ruletable/rules/WireWorld.rule"),
+            ]) {
+                let mut life = build(Some(name), &pattern, Some(rule), topology).unwrap();
+                for _ in 0..5 {
+                    life.step();
+                }
+                let table = (name == "ruletable").then(|| read_table(rule).unwrap());
+                let bytes = checkpoint(&*life, name, rule, table.as_deref(), 5).to_bytes();
+                let saved = Checkpoint::from_bytes(&bytes).unwrap();
+                assert_eq!(saved.generation, 5);
+                let mut resumed = resume(&saved).unwrap();
+                for _ in 0..5 {
+                    life.step();
+                    resumed.step();
+                }
+                assert_eq!(resumed.to_pattern(), life.to_pattern(), "{} {}", name, rule);
+                assert_eq!(resumed.topology(), topology);
+            }
+        }
+    }
+}
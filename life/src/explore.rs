@@ -0,0 +1,267 @@
+//! `life explore`, stepping a board back and forth with commands read from
+//! stdin, on top of a `gol::History` of the run.
+
+use crate::args::{self, Opt};
+use crate::run::{self, Run};
+use gol::{Engine, History};
+use life::format::Format;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+const USAGE: &str = "Usage: life explore [options] initial_state
+
+Reads commands from stdin, one per line, and keeps the past generations so
+any of them can be gone back to:
+
+  step [N]              step N generations, 1 by default
+  back [N]              go back N generations, 1 by default
+  goto G                go to generation G, back or forward
+  print                 print the board
+  export FROM TO PATH   write the generations FROM to TO to the file,
+                        separated by empty lines
+  info                  print the generation, the ones kept and their size
+  quit                  stop, as does the end of the input
+
+The oldest generations are dropped when the history grows over its budget.";
+
+const OPTIONS: &[Opt] = &[
+    Opt {
+        long: "history-budget",
+        short: None,
+        value: Some("SIZE"),
+        help: "bytes kept for past generations, with an optional\n\
+               K, M or G suffix, 64M by default",
+    },
+    Opt {
+        long: "keyframe-every",
+        short: None,
+        value: Some("N"),
+        help: "keep a whole board every N generations and only the\n\
+               changes in between, 64 by default",
+    },
+];
+
+fn options() -> Vec<Opt> {
+    let run = ["engine", "rule", "topology", "format", "help"];
+    let run = run::OPTIONS.iter().filter(|o| run.contains(&o.long));
+    OPTIONS.iter().chain(run).copied().collect()
+}
+
+/// Parses a number of bytes like `64M`.
+fn parse_size(s: &str) -> Result<usize, String> {
+    let (number, unit) = match s.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&s[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&s[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&s[..i], 1 << 30),
+        _ => (s, 1),
+    };
+    number
+        .parse::<usize>()
+        .map(|n| n.saturating_mul(unit))
+        .map_err(|_| format!("Error: Invalid size {}.", s))
+}
+
+struct Explorer {
+    life: Box<dyn Engine>,
+    generation: u64,
+    history: History,
+    format: Format,
+}
+
+impl Explorer {
+    fn new(life: Box<dyn Engine>, keyframe_every: u64, budget: usize, format: Format) -> Self {
+        let history = History::new(0, life.save_cells(), keyframe_every, budget);
+        Explorer {
+            life,
+            generation: 0,
+            history,
+            format,
+        }
+    }
+
+    /// Moves to the generation, from the history or by stepping past the
+    /// newest one.
+    fn goto(&mut self, generation: u64) -> Result<(), String> {
+        if generation < self.history.first() {
+            return Err(format!(
+                "Error: Generation {} was dropped, the history starts at {}.",
+                generation,
+                self.history.first()
+            ));
+        }
+        let start = generation.min(self.history.last());
+        if start != self.generation {
+            let cells = self.history.get(start).unwrap();
+            self.life.load_cells(&cells)?;
+            self.generation = start;
+        }
+        while self.generation < generation {
+            self.life.step();
+            self.generation += 1;
+            if self.generation > self.history.last() {
+                self.history.push(self.life.save_cells());
+            }
+        }
+        Ok(())
+    }
+
+    fn export(&mut self, from: u64, to: u64, path: &str) -> Result<(), String> {
+        let current = self.generation;
+        let mut output = String::new();
+        for generation in from..=to {
+            self.goto(generation)?;
+            if generation > from {
+                output.push('\n');
+            }
+            output.push_str(&self.format.write(&*self.life));
+        }
+        self.goto(current)?;
+        fs::write(path, output).map_err(|_| format!("Error: Couldn't create file {}", path))
+    }
+
+    /// Runs one command, `false` to stop.
+    fn execute(&mut self, line: &str, out: &mut dyn Write) -> Result<bool, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |i: usize, default: Option<u64>| -> Result<u64, String> {
+            match words.get(i) {
+                Some(n) => n
+                    .parse()
+                    .map_err(|_| format!("Error: Invalid number {}.", n)),
+                None => default.ok_or_else(|| "Error: Missing number.".to_string()),
+            }
+        };
+        match words[..] {
+            [] => return Ok(true),
+            ["quit"] => return Ok(false),
+            ["step"] | ["step", _] => self.goto(self.generation + number(1, Some(1))?)?,
+            ["back"] | ["back", _] => {
+                self.goto(self.generation.saturating_sub(number(1, Some(1))?))?
+            }
+            ["goto", _] => self.goto(number(1, None)?)?,
+            ["print"] => {
+                let board = self.format.write(&*self.life);
+                write!(out, "{}", board).map_err(|_| "Error: Couldn't write the output.")?;
+                return Ok(true);
+            }
+            ["export", _, _, path] => self.export(number(1, None)?, number(2, None)?, path)?,
+            ["info"] => {
+                writeln!(
+                    out,
+                    "generation {}, history {} to {}, {} bytes",
+                    self.generation,
+                    self.history.first(),
+                    self.history.last(),
+                    self.history.memory()
+                )
+                .map_err(|_| "Error: Couldn't write the output.")?;
+                return Ok(true);
+            }
+            _ => {
+                return Err(format!(
+                    "Error: Unknown command {}, see --help.",
+                    line.trim()
+                ))
+            }
+        }
+        writeln!(out, "generation {}", self.generation)
+            .map_err(|_| "Error: Couldn't write the output.")?;
+        Ok(true)
+    }
+}
+
+pub fn explore(args: Vec<String>) -> Result<(), String> {
+    let opts = options();
+    let m = args::parse(&opts, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, &opts));
+        return Ok(());
+    }
+    let [path] = &m.positional[..] else {
+        return Err("Error: Expected initial_state, see --help.".to_string());
+    };
+    if path == "-" {
+        return Err("Error: The commands are read from stdin, not the board.".to_string());
+    }
+    let input = run::read_input(path)?;
+    let (in_format, out_format) = run::formats(m.value("format"), &input)?;
+    let pattern = in_format.read(&input)?;
+    let budget = match m.value("history-budget") {
+        Some(size) => parse_size(size)?,
+        None => 64 << 20,
+    };
+    let keyframe_every = m.parse::<u64>("keyframe-every")?.unwrap_or(64);
+    let run = Run::new(&m, &pattern)?;
+    let mut explorer = Explorer::new(run.life, keyframe_every, budget, out_format);
+
+    let mut out = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line.map_err(|_| "Error: Couldn't read the commands.".to_string())?;
+        match explorer.execute(&line, &mut out) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("{}", e),
+        }
+        out.flush()
+            .map_err(|_| "Error: Couldn't write the output.".to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use life::engines;
+
+    fn explorer(budget: usize) -> Explorer {
+        let glider = gol::Pattern::from_plaintext(".X.\n..X\nXXX\n").unwrap();
+        let board = glider.pad(0, 0, 12, 12);
+        let life = engines::build(Some("simple"), &board, None, gol::Topology::Torus).unwrap();
+        Explorer::new(life, 4, budget, Format::Plaintext)
+    }
+
+    fn run(explorer: &mut Explorer, commands: &str) -> Result<String, String> {
+        let mut out = Vec::new();
+        for line in commands.lines() {
+            explorer.execute(line, &mut out)?;
+        }
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn commands() {
+        let mut explorer = explorer(usize::MAX);
+        let output = run(&mut explorer, "step 10\nback 3\ninfo\nprint").unwrap();
+        assert!(output.starts_with("generation 10\ngeneration 7\ngeneration 7, history 0 to 10"));
+        assert!(output.ends_with(&explorer_at(7)));
+        assert_eq!(
+            run(&mut explorer, "goto 12\nback").unwrap(),
+            "generation 12\ngeneration 11\n"
+        );
+        assert_eq!(parse_size("2K"), Ok(2048));
+        assert!(run(&mut explorer, "rewind").is_err());
+        assert!(run(&mut explorer, "step x").is_err());
+        assert!(parse_size("M").is_err());
+    }
+
+    /// The board stepped straight to the generation.
+    fn explorer_at(generation: u64) -> String {
+        let mut explorer = explorer(usize::MAX);
+        for _ in 0..generation {
+            explorer.life.step();
+        }
+        Format::Plaintext.write(&*explorer.life)
+    }
+
+    #[test]
+    fn budget() {
+        // a bit per cell, 29 bytes a keyframe
+        let mut explorer = explorer(100);
+        run(&mut explorer, "step 20").unwrap();
+        assert!(explorer.history.first() > 0);
+        assert!(explorer.history.memory() <= 100);
+        assert!(run(&mut explorer, "goto 0").is_err());
+        let first = explorer.history.first();
+        let output = run(&mut explorer, &format!("goto {}\nprint", first)).unwrap();
+        assert!(output.ends_with(&explorer_at(first)));
+    }
+}
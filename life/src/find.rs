@@ -0,0 +1,62 @@
+//! `life find`, where a pattern occurs on a board.
+
+use crate::args::{self, Opt};
+use crate::run::{self, Run};
+use life::format::Format;
+use life::locate;
+use std::io::Write;
+
+const USAGE: &str = "Usage: life find [options] pattern board [iterations]
+
+Prints the position (x y, the top left corner of the pattern's bounding box)
+and orientation of every exact occurrence of the live cells in pattern on the
+board, surrounded by dead cells. With iterations the board is stepped first.";
+
+const OPTIONS: &[Opt] = &[Opt {
+    long: "count",
+    short: Some('c'),
+    value: None,
+    help: "only print the number of occurrences",
+}];
+
+fn options() -> Vec<Opt> {
+    let run = ["engine", "rule", "topology", "output", "help"];
+    let run = run::OPTIONS.iter().filter(|o| run.contains(&o.long));
+    OPTIONS.iter().chain(run).copied().collect()
+}
+
+pub fn find(args: Vec<String>) -> Result<(), String> {
+    let opts = options();
+    let m = args::parse(&opts, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, &opts));
+        return Ok(());
+    }
+    let (pattern, board, iterations) = match &m.positional[..] {
+        [pattern, board] => (pattern, board, 0),
+        [pattern, board, iterations] => (pattern, board, run::parse_iterations(iterations)?),
+        _ => return Err("Error: Expected pattern and board, see --help.".to_string()),
+    };
+    let input = run::read_input(pattern)?;
+    let pattern = Format::detect(&input).read(&input)?;
+    let input = run::read_input(board)?;
+    let board = Format::detect(&input).read(&input)?;
+
+    let mut run = Run::new(&m, &board)?;
+    for _ in 0..iterations {
+        run.life.step();
+    }
+    let found = locate::find(&run.life.to_bitgrid(), &pattern);
+
+    let mut out = run::open_output(m.value("output"))?;
+    let written = if m.flag("count") {
+        writeln!(out, "{}", found.len())
+    } else {
+        found
+            .iter()
+            .try_for_each(|f| writeln!(out, "{} {} {}", f.x, f.y, f.orientation))
+    };
+    written
+        .and_then(|_| out.flush())
+        .map_err(|_| "Error: Couldn't write the output.".to_string())
+}
@@ -0,0 +1,131 @@
+//! The board file formats, see `gol::pattern` and `gol::macrocell`.
+
+use gol::rule::shear;
+use gol::{macrocell, pattern, Engine, Pattern};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `.` and `X` lines, with the extra states of the multi-state rules.
+    Plaintext,
+    Rle,
+    Macrocell,
+    /// Plaintext drawn as a hexagonal grid for the `H` rules, output only.
+    Hex,
+    /// Plain PBM image, output only.
+    Pbm,
+}
+
+impl Format {
+    /// Guesses the format of the file from its contents.
+    pub fn detect(s: &str) -> Format {
+        if macrocell::is_macrocell(s) {
+            Format::Macrocell
+        } else if pattern::is_rle(s) {
+            Format::Rle
+        } else {
+            Format::Plaintext
+        }
+    }
+
+    pub fn read(&self, s: &str) -> Result<Pattern, String> {
+        Ok(match self {
+            Format::Plaintext => Pattern::from_plaintext(s)?,
+            Format::Rle => Pattern::from_rle(s)?,
+            Format::Macrocell => Pattern::from_macrocell(s)?,
+            Format::Hex | Format::Pbm => {
+                return Err(format!("Error: The {} format is output only.", self))
+            }
+        })
+    }
+
+    pub fn write(&self, life: &dyn Engine) -> String {
+        self.write_cells(&life.to_pattern(), &life.rule(), life.states() > 2)
+    }
+
+    /// Writes a pattern without an engine, with the rule of the pattern or
+    /// `B3/S23` in the header and multi-state letters if any cell needs them.
+    pub fn write_pattern(&self, pattern: &Pattern) -> String {
+        let rule = pattern.rule.as_deref().unwrap_or("B3/S23");
+        self.write_cells(pattern, rule, pattern.cells.iter().any(|&c| c > 1))
+    }
+
+    fn write_cells(&self, pattern: &Pattern, rule: &str, multi_state: bool) -> String {
+        match self {
+            Format::Plaintext => pattern.to_plaintext(),
+            Format::Rle => pattern.to_rle(rule, multi_state),
+            Format::Macrocell => pattern.to_macrocell(rule, multi_state),
+            Format::Hex => shear(&pattern.to_plaintext()),
+            Format::Pbm => pattern.to_pbm(),
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plaintext" | "txt" | "cells" => Ok(Format::Plaintext),
+            "rle" => Ok(Format::Rle),
+            "mc" | "macrocell" => Ok(Format::Macrocell),
+            "hex" => Ok(Format::Hex),
+            "pbm" => Ok(Format::Pbm),
+            _ => Err(format!(
+                "Error: Unknown format {}, expected plaintext, rle, mc, hex or pbm.",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::Plaintext => "plaintext",
+            Format::Rle => "rle",
+            Format::Macrocell => "mc",
+            Format::Hex => "hex",
+            Format::Pbm => "pbm",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engines;
+    use gol::Topology;
+
+    #[test]
+    fn detect() {
+        assert_eq!(Format::detect(".X\n"), Format::Plaintext);
+        assert_eq!(Format::detect("x = 1, y = 1\no!\n"), Format::Rle);
+        assert_eq!(Format::detect("#N glider\nx = 1, y = 1\no!\n"), Format::Rle);
+        assert_eq!(Format::detect("[M2] (golly 4.2)\n*$\n"), Format::Macrocell);
+    }
+
+    #[test]
+    fn write() {
+        let pattern = Format::Plaintext.read(".X\nXX\n").unwrap();
+        let life = engines::build(None, &pattern, Some("B2/S34H"), Topology::Bounded).unwrap();
+        assert_eq!(Format::Plaintext.write(&*life), ".X\nXX\n");
+        assert_eq!(
+            Format::Rle.write(&*life),
+            "x = 2, y = 2, rule = B2/S34H\nbo$2o!\n"
+        );
+        assert_eq!(Format::Hex.write(&*life), " . X\nX X\n");
+        let mc = Format::Macrocell.write(&*life);
+        assert_eq!(Format::Macrocell.read(&mc).unwrap().cells, pattern.cells);
+        assert!(Format::Hex.read(&Format::Hex.write(&*life)).is_err());
+        assert_eq!(Format::Pbm.write(&*life), "P1\n2 2\n01\n11\n");
+        assert_eq!(
+            Format::Rle.write_pattern(&Format::Plaintext.read(".2\nXX\n").unwrap()),
+            "x = 2, y = 2, rule = B3/S23\n.B$2A!\n"
+        );
+        assert_eq!("mc".parse(), Ok(Format::Macrocell));
+        assert!("png".parse::<Format>().is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! `life generate`, random soups written out or stepped right away.
+
+use crate::args::{self, Matches, Opt};
+use crate::run::{self, Run};
+use life::format::Format;
+use life::soup::{Region, Soup, Symmetry};
+
+const USAGE: &str = "Usage: life generate [options] width height [iterations]
+
+Fills a width x height board with random cells from the seed and prints it.
+With iterations the soup is stepped first, taking the options of life run.";
+
+pub const OPTIONS: &[Opt] = &[
+    Opt {
+        long: "seed",
+        short: Some('s'),
+        value: Some("N"),
+        help: "seed of the random numbers, 0 by default",
+    },
+    Opt {
+        long: "density",
+        short: Some('d'),
+        value: Some("P"),
+        help: "probability of a live cell, 0.5 by default",
+    },
+    Opt {
+        long: "symmetry",
+        short: None,
+        value: Some("SYM"),
+        help: "C1 (the default), C2, C4 or D8, the last two\n\
+               need a square region",
+    },
+    Opt {
+        long: "region",
+        short: None,
+        value: Some("X,Y,W,H"),
+        help: "fill only this rectangle, the rest stays dead",
+    },
+    Opt {
+        long: "format",
+        short: Some('f'),
+        value: Some("FORMAT"),
+        help: "plaintext (the default), rle, mc, hex or pbm",
+    },
+];
+
+/// The options of `life run` which make sense here too.
+fn options() -> Vec<Opt> {
+    let skip = ["format", "resume"];
+    OPTIONS
+        .iter()
+        .chain(run::OPTIONS.iter().filter(|o| !skip.contains(&o.long)))
+        .copied()
+        .collect()
+}
+
+/// The soup of the board size and the `--seed`, `--density`, `--symmetry`
+/// and `--region` options.
+pub fn soup(m: &Matches, width: &str, height: &str) -> Result<Soup, String> {
+    let size = |s: &str| {
+        s.parse::<usize>()
+            .map_err(|_| "Error: Couldn't parse the board size. Expected usize.".to_string())
+    };
+    let mut soup = Soup::new(size(width)?, size(height)?, 0);
+    soup.seed = m.parse("seed")?.unwrap_or(soup.seed);
+    soup.density = m.parse("density")?.unwrap_or(soup.density);
+    soup.symmetry = m.parse::<Symmetry>("symmetry")?.unwrap_or_default();
+    soup.region = m.parse::<Region>("region")?;
+    Ok(soup)
+}
+
+pub fn generate(args: Vec<String>) -> Result<(), String> {
+    let opts = options();
+    let m = args::parse(&opts, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, &opts));
+        return Ok(());
+    }
+    let (width, height, iterations) = match &m.positional[..] {
+        [width, height] => (width, height, None),
+        [width, height, iterations] => (width, height, Some(iterations)),
+        _ => return Err("Error: Expected width and height, see --help.".to_string()),
+    };
+    let soup = soup(&m, width, height)?;
+    let format = m.parse::<Format>("format")?.unwrap_or(Format::Plaintext);
+    let iterations = match iterations {
+        Some(iterations) => run::parse_iterations(iterations)?,
+        None => 0,
+    };
+
+    let pattern = soup.generate()?;
+    Run::new(&m, &pattern)?.simulate(&m, iterations, format)
+}
@@ -0,0 +1,9 @@
+//! All the engines behind one interface, for the `life` binary and anything
+//! else that wants to pick the engine at runtime.
+
+pub mod controller;
+pub mod engines;
+pub mod format;
+pub mod locate;
+pub mod objects;
+pub mod soup;
@@ -0,0 +1,150 @@
+//! Finding every occurrence of a pattern on a board, in all 8 orientations.
+//!
+//! The board is matched as packed rows (`gol::BitGrid`), each word of a
+//! row tests 64 positions at once against one cell of the pattern.
+
+use gol::transform::{Orientation, ORIENTATIONS};
+use gol::{BitGrid, Pattern};
+
+/// Top left corner of the pattern's bounding box on the board, in the
+/// orientation it was found in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Match {
+    pub x: usize,
+    pub y: usize,
+    pub orientation: Orientation,
+}
+
+/// Every position of the pattern on the board in one orientation.
+fn find_oriented(board: &BitGrid, pattern: &Pattern, orientation: Orientation) -> Vec<Match> {
+    let mut ret = Vec::new();
+    if pattern.width > board.width || pattern.height > board.height {
+        return ret;
+    }
+    // the pattern with its dead border, the live cells first as they rule
+    // out most positions right away
+    let mut cells = Vec::new();
+    for alive in [true, false] {
+        for y in 0..pattern.height + 2 {
+            for x in 0..pattern.width + 2 {
+                let inside = (1..=pattern.width).contains(&x) && (1..=pattern.height).contains(&y);
+                if (inside && pattern.get(x - 1, y - 1) != 0) == alive {
+                    cells.push((x as i64 - 1, y as i64 - 1, alive));
+                }
+            }
+        }
+    }
+    let positions = board.width - pattern.width + 1;
+    for y in 0..=board.height - pattern.height {
+        for base in (0..positions).step_by(64) {
+            let count = (positions - base).min(64);
+            let mut found = if count == 64 { !0 } else { (1 << count) - 1 };
+            for &(dx, dy, alive) in &cells {
+                let word = board.window(base as i64 + dx, y as i64 + dy);
+                found &= if alive { word } else { !word };
+                if found == 0 {
+                    break;
+                }
+            }
+            while found != 0 {
+                ret.push(Match {
+                    x: base + found.trailing_zeros() as usize,
+                    y,
+                    orientation,
+                });
+                found &= found - 1;
+            }
+        }
+    }
+    ret
+}
+
+/// Every position and orientation where the live cells of the pattern
+/// occur with nothing else alive around them, the cells outside the board
+/// are dead. Orientations which give the same pattern as an earlier one are
+/// left out, so a block is only found once. Sorted by row and column.
+pub fn find(board: &BitGrid, pattern: &Pattern) -> Vec<Match> {
+    let pattern = pattern.crop();
+    if pattern.width == 0 {
+        return Vec::new();
+    }
+    let mut seen: Vec<Pattern> = Vec::new();
+    let mut ret = Vec::new();
+    for orientation in ORIENTATIONS {
+        let oriented = pattern.orient(orientation);
+        if seen.contains(&oriented) {
+            continue;
+        }
+        ret.extend(find_oriented(board, &oriented, orientation));
+        seen.push(oriented);
+    }
+    ret.sort_by_key(|m| (m.y, m.x));
+    ret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn grid(s: &str) -> BitGrid {
+        BitGrid::from_pattern(&Pattern::from_plaintext(s).unwrap())
+    }
+
+    #[test]
+    fn blocks_and_blinkers() {
+        let board = grid(
+            "XX......XX\n\
+             XX..X...XX\n\
+             ....X...XX\n\
+             ....X...XX\n\
+             XXX.......\n",
+        );
+        let block = Pattern::from_plaintext("....\n.XX.\n.XX.\n").unwrap();
+        let found: Vec<_> = find(&board, &block).iter().map(|m| (m.x, m.y)).collect();
+        // the two blocks on the right touch, so neither counts
+        assert_eq!(found, [(0, 0)]);
+
+        let blinker = Pattern::from_plaintext("XXX\n").unwrap();
+        assert_eq!(
+            find(&board, &blinker),
+            [
+                Match {
+                    x: 4,
+                    y: 1,
+                    orientation: Orientation::Rotate90
+                },
+                Match {
+                    x: 0,
+                    y: 4,
+                    orientation: Orientation::Identity
+                }
+            ]
+        );
+        assert!(find(&board, &Pattern::new(2, 2)).is_empty());
+    }
+
+    #[test]
+    fn wide_board() {
+        // gliders across word boundaries in all orientations
+        let glider = Pattern::from_plaintext(".X.\n..X\nXXX\n").unwrap();
+        let mut board = BitGrid::new(200, 12);
+        let mut expected = Vec::new();
+        for (i, orientation) in ORIENTATIONS.into_iter().enumerate() {
+            let x = 1 + i * 24 + i % 3 * 2;
+            let y = 1 + i % 2 * 6;
+            let oriented = glider.orient(orientation);
+            for gy in 0..3 {
+                for gx in 0..3 {
+                    board.set(x + gx, y + gy, oriented.get(gx, gy) != 0);
+                }
+            }
+            expected.push((x, y, oriented));
+        }
+        let found = find(&board, &glider);
+        assert_eq!(found.len(), 8);
+        for m in found {
+            let (_, _, ref oriented) = expected.iter().find(|e| (e.0, e.1) == (m.x, m.y)).unwrap();
+            assert_eq!(&glider.orient(m.orientation), oriented);
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! One binary for all the engines, `life --help` lists the options.
+
+mod args;
+mod batch;
+mod census;
+mod diff;
+mod edit;
+mod elementary;
+mod explore;
+mod find;
+mod generate;
+mod predecessor;
+mod run;
+mod search;
+mod serve;
+mod three_d;
+
+use std::env;
+
+fn main() -> Result<(), String> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // run is the default command
+    match args.first().map(String::as_str) {
+        Some("3d") => three_d::three_d(args.split_off(1)),
+        Some("batch") => batch::batch(args.split_off(1)),
+        Some("census") => census::census(args.split_off(1)),
+        Some("diff") => diff::diff(args.split_off(1)),
+        Some("edit") => edit::edit(args.split_off(1)),
+        Some("elementary") => elementary::elementary(args.split_off(1)),
+        Some("explore") => explore::explore(args.split_off(1)),
+        Some("find") => find::find(args.split_off(1)),
+        Some("generate") => generate::generate(args.split_off(1)),
+        Some("predecessor") => predecessor::predecessor(args.split_off(1)),
+        Some("run") => run::run(args.split_off(1)),
+        Some("search") => search::search(args.split_off(1)),
+        Some("serve") => serve::serve(args.split_off(1)),
+        _ => run::run(args),
+    }
+}
@@ -0,0 +1,472 @@
+//! Object census of settled boards in the spirit of apgsearch. The board is
+//! split into its objects, each object is run on its own board to find out
+//! what it is and named by its [apgcode](https://conwaylife.com/wiki/Apgcode).
+
+use gol::{Engine, Pattern};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Longest period looked for, both of a settling board and of its objects.
+pub const MAX_PERIOD: usize = 60;
+
+/// Builds an engine for an object on its own board, with the rule of the
+/// board it comes from.
+pub type Builder<'a> = dyn Fn(&Pattern) -> Result<Box<dyn Engine + Send>, String> + 'a;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    StillLife,
+    Oscillator {
+        period: usize,
+    },
+    /// Moves by `dx`, `dy` every period.
+    Spaceship {
+        period: usize,
+        dx: i64,
+        dy: i64,
+    },
+    /// Doesn't repeat on its own within `MAX_PERIOD`, usually objects which
+    /// still interact, are held by the edge of the board or wrap around it.
+    Unknown,
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::StillLife => write!(f, "still life"),
+            Kind::Oscillator { period } => write!(f, "oscillator, period {}", period),
+            Kind::Spaceship { period, dx, dy } => {
+                write!(f, "spaceship, period {}, moving ({}, {})", period, dx, dy)
+            }
+            Kind::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    /// Top left corner of the object's bounding box on the board.
+    pub x: usize,
+    pub y: usize,
+    pub population: usize,
+    pub kind: Kind,
+    pub apgcode: String,
+}
+
+/// Live cells with their bounding box moved to the origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Shape {
+    width: usize,
+    height: usize,
+    /// Sorted by row and then column.
+    cells: Vec<(usize, usize)>,
+}
+
+impl Shape {
+    /// The shape of the cells and the top left corner of their bounding
+    /// box, `None` without any cells.
+    fn new(cells: &[(i64, i64)]) -> Option<(Shape, (i64, i64))> {
+        let x0 = cells.iter().map(|c| c.0).min()?;
+        let y0 = cells.iter().map(|c| c.1).min()?;
+        let x1 = cells.iter().map(|c| c.0).max()?;
+        let y1 = cells.iter().map(|c| c.1).max()?;
+        let mut cells: Vec<_> = cells
+            .iter()
+            .map(|&(x, y)| ((x - x0) as usize, (y - y0) as usize))
+            .collect();
+        cells.sort_by_key(|&(x, y)| (y, x));
+        let shape = Shape {
+            width: (x1 - x0 + 1) as usize,
+            height: (y1 - y0 + 1) as usize,
+            cells,
+        };
+        Some((shape, (x0, y0)))
+    }
+
+    /// The shape flipped horizontally (bit 0), vertically (bit 1) and then
+    /// transposed (bit 2), the 8 orientations of `0..8`.
+    fn orient(&self, orientation: u8) -> Shape {
+        let cells: Vec<_> = self
+            .cells
+            .iter()
+            .map(|&(mut x, mut y)| {
+                if orientation & 1 != 0 {
+                    x = self.width - 1 - x;
+                }
+                if orientation & 2 != 0 {
+                    y = self.height - 1 - y;
+                }
+                if orientation & 4 != 0 {
+                    (y as i64, x as i64)
+                } else {
+                    (x as i64, y as i64)
+                }
+            })
+            .collect();
+        Shape::new(&cells).unwrap().0
+    }
+
+    /// The extended Wechsler format: strips of 5 rows separated by `z`, one
+    /// character per column with the top row as the lowest bit, and runs
+    /// of empty columns shortened to `w`, `x` or `y` and their length.
+    fn wechsler(&self) -> String {
+        const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+        let mut columns = vec![vec![0u8; self.width]; self.height.div_ceil(5)];
+        for &(x, y) in &self.cells {
+            columns[y / 5][x] |= 1 << (y % 5);
+        }
+        let mut ret = String::new();
+        for (i, strip) in columns.iter().enumerate() {
+            if i > 0 {
+                ret.push('z');
+            }
+            let end = strip.iter().rposition(|&c| c != 0).map_or(0, |i| i + 1);
+            let mut zeros = 0;
+            for &column in &strip[..end] {
+                if column == 0 {
+                    zeros += 1;
+                    continue;
+                }
+                while zeros > 0 {
+                    let n = zeros.min(39);
+                    match n {
+                        1 => ret.push('0'),
+                        2 => ret.push('w'),
+                        3 => ret.push('x'),
+                        _ => {
+                            ret.push('y');
+                            ret.push(DIGITS[n - 4] as char);
+                        }
+                    }
+                    zeros -= n;
+                }
+                ret.push(DIGITS[column as usize] as char);
+            }
+        }
+        ret
+    }
+}
+
+/// The apgcode of the object from its phases, the shortest and then first
+/// code of all phases in all orientations.
+fn apgcode(kind: Kind, phases: &[Shape]) -> String {
+    let prefix = match kind {
+        Kind::StillLife => format!("xs{}", phases[0].cells.len()),
+        Kind::Oscillator { period } => format!("xp{}", period),
+        Kind::Spaceship { period, .. } => format!("xq{}", period),
+        Kind::Unknown => return "zz_UNKNOWN".to_string(),
+    };
+    let code = phases
+        .iter()
+        .flat_map(|phase| (0..8).map(|o| phase.orient(o).wechsler()))
+        .min_by(|a, b| a.len().cmp(&b.len()).then(a.cmp(b)))
+        .unwrap();
+    format!("{}_{}", prefix, code)
+}
+
+fn live_cells(life: &dyn Engine) -> Vec<(i64, i64)> {
+    let mut ret = Vec::new();
+    for y in 0..life.height() {
+        for x in 0..life.width() {
+            if life.get(x, y) != 0 {
+                ret.push((x as i64, y as i64));
+            }
+        }
+    }
+    ret
+}
+
+/// Runs the cells on their own board until they repeat, returns what they
+/// are and their apgcode.
+fn classify(shape: &Shape, build: &Builder) -> Result<(Kind, String), String> {
+    // far enough from the edge for a spaceship at light speed
+    let pad = MAX_PERIOD + 2;
+    let mut pattern = Pattern::new(shape.width + 2 * pad, shape.height + 2 * pad);
+    for &(x, y) in &shape.cells {
+        pattern.set(x + pad, y + pad, 1);
+    }
+    let mut life = build(&pattern)?;
+    let mut phases = vec![shape.clone()];
+    for period in 1..=MAX_PERIOD {
+        life.step();
+        let Some((next, (x, y))) = Shape::new(&live_cells(&*life)) else {
+            break;
+        };
+        if next == *shape {
+            let (dx, dy) = (x - pad as i64, y - pad as i64);
+            let kind = match (period, dx, dy) {
+                (1, 0, 0) => Kind::StillLife,
+                (_, 0, 0) => Kind::Oscillator { period },
+                _ => Kind::Spaceship { period, dx, dy },
+            };
+            return Ok((kind, apgcode(kind, &phases)));
+        }
+        phases.push(next);
+    }
+    Ok((Kind::Unknown, apgcode(Kind::Unknown, &phases)))
+}
+
+/// Steps the board until its population has repeated with a period of at
+/// most `MAX_PERIOD` for three periods and 20 more generations. Returns the
+/// generations stepped and the period, `None` if the board doesn't settle
+/// within `max_generations`.
+pub fn settle(life: &mut dyn Engine, max_generations: u64) -> Option<(u64, usize)> {
+    let mut populations = vec![life.population()];
+    for generation in 0..=max_generations {
+        let n = populations.len();
+        for period in 1..=MAX_PERIOD {
+            let window = 3 * period + 20;
+            if n < window + period {
+                break;
+            }
+            if (1..=window).all(|k| populations[n - k] == populations[n - k - period]) {
+                return Some((generation, period));
+            }
+        }
+        if generation < max_generations {
+            life.step();
+            populations.push(life.population());
+        }
+    }
+    None
+}
+
+/// Splits a settled board of the given period into its objects, stepping
+/// it one period further. Cells belong to the same object when they are
+/// connected in any generation of the period, so objects with disconnected
+/// phases like the pulsar stay in one piece.
+pub fn objects(
+    life: &mut dyn Engine,
+    period: usize,
+    build: &Builder,
+) -> Result<Vec<Object>, String> {
+    if life.states() != 2 {
+        return Err("Error: The census needs a two state rule.".to_string());
+    }
+    let (width, height) = (life.width(), life.height());
+    let mut footprint = vec![false; width * height];
+    for generation in 0..=period {
+        if generation > 0 {
+            life.step();
+        }
+        for (x, y) in live_cells(&*life) {
+            footprint[y as usize * width + x as usize] = true;
+        }
+    }
+
+    let mut ret = Vec::new();
+    let mut seen = vec![false; width * height];
+    for start in 0..width * height {
+        if !footprint[start] || seen[start] {
+            continue;
+        }
+        // flood fill of the 8-connected component
+        seen[start] = true;
+        let mut stack = vec![start];
+        let mut cells = Vec::new();
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            if life.get(x, y) != 0 {
+                cells.push((x as i64, y as i64));
+            }
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                    if nx < 0 || ny < 0 || nx >= width as i64 || ny >= height as i64 {
+                        continue;
+                    }
+                    let j = ny as usize * width + nx as usize;
+                    if footprint[j] && !seen[j] {
+                        seen[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+        }
+        let Some((shape, (x, y))) = Shape::new(&cells) else {
+            continue;
+        };
+        let (kind, apgcode) = classify(&shape, build)?;
+        ret.push(Object {
+            x: x as usize,
+            y: y as usize,
+            population: shape.cells.len(),
+            kind,
+            apgcode,
+        });
+    }
+    Ok(ret)
+}
+
+/// Tally of the objects of one or more boards.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Census {
+    counts: BTreeMap<String, (Kind, usize)>,
+    /// Boards which didn't settle and aren't counted.
+    pub unsettled: usize,
+}
+
+impl Census {
+    /// Counts the object, spaceships by their speed as the same apgcode
+    /// covers all directions.
+    pub fn add(&mut self, object: &Object) {
+        let kind = match object.kind {
+            Kind::Spaceship { period, dx, dy } => Kind::Spaceship {
+                period,
+                dx: dx.abs().max(dy.abs()),
+                dy: dx.abs().min(dy.abs()),
+            },
+            kind => kind,
+        };
+        self.counts
+            .entry(object.apgcode.clone())
+            .or_insert((kind, 0))
+            .1 += 1;
+    }
+
+    /// The apgcodes with their kind and count, the most common first.
+    pub fn tally(&self) -> Vec<(&str, Kind, usize)> {
+        let mut ret: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(code, &(kind, count))| (code.as_str(), kind, count))
+            .collect();
+        ret.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+        ret
+    }
+}
+
+impl fmt::Display for Census {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tally = self.tally();
+        let count_width = tally.first().map_or(1, |t| t.2.to_string().len());
+        let code_width = tally.iter().map(|t| t.0.len()).max().unwrap_or(0);
+        for (code, kind, count) in tally {
+            writeln!(
+                f,
+                "{:>cw$}  {:<w$}  {}",
+                count,
+                code,
+                kind,
+                cw = count_width,
+                w = code_width
+            )?;
+        }
+        if self.unsettled > 0 {
+            writeln!(f, "{} board(s) didn't settle", self.unsettled)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::engines;
+    use gol::Topology;
+
+    fn build(pattern: &Pattern) -> Result<Box<dyn Engine + Send>, String> {
+        engines::build(
+            Some("bitboards"),
+            pattern,
+            Some("B3/S23"),
+            Topology::Bounded,
+        )
+    }
+
+    fn code(plaintext: &str) -> (Kind, String) {
+        let pattern = Pattern::from_plaintext(plaintext).unwrap();
+        let mut cells = Vec::new();
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                if pattern.get(x, y) != 0 {
+                    cells.push((x as i64, y as i64));
+                }
+            }
+        }
+        classify(&Shape::new(&cells).unwrap().0, &build).unwrap()
+    }
+
+    #[test]
+    fn apgcodes() {
+        assert_eq!(code("XX\nXX\n"), (Kind::StillLife, "xs4_33".to_string()));
+        assert_eq!(code(".XX.\nX..X\n.XX.\n").1, "xs6_696");
+        assert_eq!(code("XX.\nX.X\n.X.\n").1, "xs5_253");
+        assert_eq!(
+            code("XXX\n"),
+            (Kind::Oscillator { period: 2 }, "xp2_7".to_string())
+        );
+        assert_eq!(code("XX..\nX...\n...X\n..XX\n").1, "xp2_318c");
+        assert_eq!(
+            code(".X.\n..X\nXXX\n"),
+            (
+                Kind::Spaceship {
+                    period: 4,
+                    dx: 1,
+                    dy: 1
+                },
+                "xq4_153".to_string()
+            )
+        );
+        assert_eq!(code(".X..X\nX....\nX...X\nXXXX.\n").1, "xq4_6frc");
+        assert_eq!(
+            code("..XXX...XXX..\n.............\nX....X.X....X\nX....X.X....X\nX....X.X....X\n..XXX...XXX..\n.............\n..XXX...XXX..\nX....X.X....X\nX....X.X....X\nX....X.X....X\n.............\n..XXX...XXX..\n").1,
+            "xp3_co9nas0san9oczgoldlo0oldlogz1047210127401"
+        );
+        // the R-pentomino doesn't settle on its own within the period
+        assert_eq!(code(".XX\nXX.\n.X.\n").0, Kind::Unknown);
+    }
+
+    #[test]
+    fn census() {
+        // a block, a blinker and a glider flying away from them
+        let mut life = build(
+            &Pattern::from_plaintext(
+                "......................\n\
+                 .XX...........X.......\n\
+                 .XX...........X.......\n\
+                 ..............X.......\n\
+                 ....X.................\n\
+                 .....X................\n\
+                 ...XXX................\n\
+                 ......................\n\
+                 ......................\n\
+                 ......................\n\
+                 ......................\n\
+                 ......................\n\
+                 ......................\n\
+                 ......................\n\
+                 ......................\n\
+                 ......................\n",
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        let (generation, period) = settle(&mut *life, 100).unwrap();
+        assert_eq!(period, 1);
+        assert!(generation < 100);
+        let objects = objects(&mut *life, period, &build).unwrap();
+        let mut census = Census::default();
+        for object in &objects {
+            census.add(object);
+        }
+        assert_eq!(
+            census.to_string(),
+            "1  xp2_7    oscillator, period 2\n\
+             1  xq4_153  spaceship, period 4, moving (1, 1)\n\
+             1  xs4_33   still life\n"
+        );
+        assert_eq!((objects[0].x, objects[0].y), (1, 1));
+        let mut glider = objects[2].clone();
+        glider.kind = Kind::Spaceship {
+            period: 4,
+            dx: -1,
+            dy: 1,
+        };
+        census.add(&glider);
+        assert_eq!(census.tally()[0], ("xq4_153", objects[2].kind, 2));
+
+        let mut life = build(&Pattern::from_plaintext(".XX\nXX.\n.X.\n").unwrap()).unwrap();
+        assert_eq!(settle(&mut *life, 10), None);
+    }
+}
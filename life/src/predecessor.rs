@@ -0,0 +1,92 @@
+//! `life predecessor`, a board which becomes the target in one generation.
+
+use crate::args::{self, Opt};
+use crate::run;
+use gol::predecessor::{self, Encoder};
+use gol::{sat, Rule, Topology};
+
+const USAGE: &str = "Usage: life predecessor [options] target
+
+Prints a board of the size of target which becomes it in one generation, or
+that there is none, as the target is a Garden of Eden within the box. Only
+two state rules work. Cell (x, y) of the parent is variable y * width + x + 1
+of the formula, the width includes the margin.";
+
+const OPTIONS: &[Opt] = &[
+    Opt {
+        long: "margin",
+        short: Some('m'),
+        value: Some("N"),
+        help: "allow the parent N more cells on each side, the\n\
+               target is surrounded by dead ones",
+    },
+    Opt {
+        long: "dimacs",
+        short: None,
+        value: Some("PATH"),
+        help: "write the formula in DIMACS format and stop, for\n\
+               an external solver",
+    },
+    Opt {
+        long: "model",
+        short: None,
+        value: Some("PATH"),
+        help: "read the parent from the output of an external\n\
+               solver instead of solving",
+    },
+];
+
+fn options() -> Vec<Opt> {
+    let run = ["rule", "topology", "format", "output", "help"];
+    let run = run::OPTIONS.iter().filter(|o| run.contains(&o.long));
+    OPTIONS.iter().chain(run).copied().collect()
+}
+
+pub fn predecessor(args: Vec<String>) -> Result<(), String> {
+    let opts = options();
+    let m = args::parse(&opts, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, &opts));
+        return Ok(());
+    }
+    let [path] = &m.positional[..] else {
+        return Err("Error: Expected target, see --help.".to_string());
+    };
+    let input = run::read_input(path)?;
+    let (in_format, out_format) = run::formats(m.value("format"), &input)?;
+    let target = in_format.read(&input)?;
+    let rule = m
+        .value("rule")
+        .or(target.rule.as_deref())
+        .unwrap_or("B3/S23");
+    let rule: Rule = rule.parse().map_err(|_| {
+        format!(
+            "Error: The predecessor search needs a two state rule, not {}.",
+            rule
+        )
+    })?;
+    let topology = m.parse::<Topology>("topology")?.unwrap_or_default();
+    let margin = m.parse::<usize>("margin")?.unwrap_or(0);
+    let target = target.pad(margin, margin, margin, margin);
+
+    let cnf = Encoder::new(&rule).encode(&target, topology);
+    let model = match (m.value("dimacs"), m.value("model")) {
+        (Some(path), None) => {
+            return run::write_output(Some(path), &cnf.to_dimacs());
+        }
+        (None, Some(path)) => sat::parse_model(&run::read_input(path)?, cnf.vars)?,
+        (None, None) => sat::solve(&cnf),
+        (Some(_), Some(_)) => {
+            return Err("Error: Either --dimacs or --model, not both.".to_string())
+        }
+    };
+    let Some(model) = model else {
+        println!("No predecessor within the box.");
+        return Ok(());
+    };
+
+    let mut parent = predecessor::decode(&model, target.width, target.height);
+    parent.rule = target.rule;
+    let output = out_format.write_pattern(&parent);
+    run::write_output(m.value("output"), &output)
+}
@@ -0,0 +1,565 @@
+//! `life run`, stepping a board with any of the engines.
+
+use crate::args::{self, Matches, Opt};
+use gol::activity::{Activity, Layer};
+use gol::diff::Diff;
+use gol::noise::Noise;
+use gol::observer::{self, Control};
+use gol::{Checkpoint, Engine, Pattern, Topology};
+use life::engines;
+use life::format::Format;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::str::FromStr;
+
+const USAGE: &str = "Usage: life [run] [options] initial_state iterations
+       life [run] [options] --resume checkpoint iterations
+
+Steps the board in initial_state for the number of iterations and prints the
+result, in the same format unless told otherwise. A path of - reads stdin.
+A resumed run continues from the generation of the checkpoint up to the
+iterations, with the engine, rule and topology it was saved with.";
+
+pub const OPTIONS: &[Opt] = &[
+    Opt {
+        long: "engine",
+        short: Some('e'),
+        value: Some("NAME"),
+        help: "simple, opt01, opt02, bitboards, generations or\n\
+               ruletable, by default bitboards for life-like\n\
+               rules, generations for B/S/C rules and ruletable\n\
+               for .rule files",
+    },
+    Opt {
+        long: "rule",
+        short: Some('r'),
+        value: Some("RULE"),
+        help: "rulestring or path of a .rule file, by default the\n\
+               rule of the RLE or macrocell header or B3/S23",
+    },
+    Opt {
+        long: "topology",
+        short: Some('t'),
+        value: Some("TOPOLOGY"),
+        help: "bounded (the default) or torus",
+    },
+    Opt {
+        long: "format",
+        short: Some('f'),
+        value: Some("IN[/OUT]"),
+        help: "plaintext, rle, mc, hex or pbm (both output only),\n\
+               the input format is detected when empty and the\n\
+               output one defaults to it",
+    },
+    Opt {
+        long: "output",
+        short: Some('o'),
+        value: Some("PATH"),
+        help: "write to the file instead of stdout",
+    },
+    Opt {
+        long: "every",
+        short: None,
+        value: Some("N"),
+        help: "also print every N-th generation, starting with\n\
+               the initial one, separated by empty lines",
+    },
+    Opt {
+        long: "checkpoint-every",
+        short: None,
+        value: Some("N"),
+        help: "save a checkpoint every N generations",
+    },
+    Opt {
+        long: "checkpoint-file",
+        short: None,
+        value: Some("PATH"),
+        help: "where to save the checkpoints, replaced each time",
+    },
+    Opt {
+        long: "resume",
+        short: None,
+        value: Some("PATH"),
+        help: "continue the run saved in the checkpoint",
+    },
+    Opt {
+        long: "diff",
+        short: None,
+        value: Some("MODE"),
+        help: "print the births and deaths of each step (or of\n\
+               every N generations) instead of the boards, as a\n\
+               list of + x y and - x y lines or as a grid",
+    },
+    Opt {
+        long: "heat",
+        short: None,
+        value: Some("MAP"),
+        help: "print a heat map instead of the boards, of the age\n\
+               (generations alive in a row) or activity (births\n\
+               and deaths so far) of the cells, with the opt01,\n\
+               opt02, generations and ruletable engines",
+    },
+    Opt {
+        long: "heat-format",
+        short: None,
+        value: Some("FORMAT"),
+        help: "pgm (the default) or ansi for 256 colour terminals",
+    },
+    Opt {
+        long: "noise",
+        short: None,
+        value: Some("SPEC"),
+        help: "make the rule stochastic with comma separated\n\
+               birth=P and survival=P, the chances that a birth\n\
+               or survival of the rule happens, flip=P, the\n\
+               chance of each cell to flip after the rule, and\n\
+               seed=N, with the two state engines but\n\
+               generations and ruletable",
+    },
+    Opt {
+        long: "quiet",
+        short: Some('q'),
+        value: None,
+        help: "don't print any board, for benchmarks",
+    },
+    Opt {
+        long: "help",
+        short: Some('h'),
+        value: None,
+        help: "print this help",
+    },
+];
+
+/// Reads the whole file, or stdin for `-`.
+pub fn read_input(path: &str) -> Result<String, String> {
+    let mut ret = String::new();
+    let read = if path == "-" {
+        io::stdin().read_to_string(&mut ret).map(|_| ())
+    } else {
+        fs::read_to_string(path).map(|s| ret = s)
+    };
+    match read {
+        Ok(()) => Ok(ret),
+        Err(_) => Err(format!("Error: Couldn't open file {}", path)),
+    }
+}
+
+/// The file of `--output`, or stdout without it.
+pub fn open_output(path: Option<&str>) -> Result<Box<dyn Write>, String> {
+    Ok(match path {
+        Some(path) => match File::create(path) {
+            Ok(file) => Box::new(BufWriter::new(file)),
+            Err(_) => return Err(format!("Error: Couldn't create file {}", path)),
+        },
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    })
+}
+
+/// Writes all of `output` to the file of `--output`, or stdout without it.
+pub fn write_output(path: Option<&str>, output: &str) -> Result<(), String> {
+    let mut out = open_output(path)?;
+    write!(out, "{}", output)
+        .and_then(|_| out.flush())
+        .map_err(|_| "Error: Couldn't write the output.".to_string())
+}
+
+/// Input and output formats of `--format IN[/OUT]`, either can be empty.
+pub fn formats(value: Option<&str>, input: &str) -> Result<(Format, Format), String> {
+    let (i, o) = match value {
+        Some(value) => value.split_once('/').unwrap_or((value, "")),
+        None => ("", ""),
+    };
+    let i = match i {
+        "" => Format::detect(input),
+        i => i.parse()?,
+    };
+    let o = match o {
+        "" => i,
+        o => o.parse()?,
+    };
+    Ok((i, o))
+}
+
+/// How the changes of `--diff` are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffMode {
+    List,
+    Grid,
+}
+
+impl FromStr for DiffMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "list" => Ok(DiffMode::List),
+            "grid" => Ok(DiffMode::Grid),
+            _ => Err(format!(
+                "Error: Unknown diff mode {}, expected list or grid.",
+                s
+            )),
+        }
+    }
+}
+
+impl DiffMode {
+    pub fn write(&self, diff: &Diff) -> String {
+        match self {
+            DiffMode::List => diff.to_list(),
+            DiffMode::Grid => diff.to_grid(),
+        }
+    }
+}
+
+/// How the heat maps of `--heat` are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatFormat {
+    Pgm,
+    Ansi,
+}
+
+impl FromStr for HeatFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pgm" => Ok(HeatFormat::Pgm),
+            "ansi" => Ok(HeatFormat::Ansi),
+            _ => Err(format!(
+                "Error: Unknown heat map format {}, expected pgm or ansi.",
+                s
+            )),
+        }
+    }
+}
+
+impl HeatFormat {
+    pub fn write(&self, activity: &Activity, layer: Layer) -> String {
+        match self {
+            HeatFormat::Pgm => activity.to_pgm(layer),
+            HeatFormat::Ansi => activity.to_ansi(layer),
+        }
+    }
+}
+
+/// Writes the checkpoint next to `path` first, so a crash while writing
+/// leaves the previous one intact.
+fn save_checkpoint(checkpoint: &Checkpoint, path: &str) -> Result<(), String> {
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, checkpoint.to_bytes())
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|_| format!("Error: Couldn't write checkpoint {}", path))
+}
+
+fn load_checkpoint(path: &str) -> Result<Checkpoint, String> {
+    match fs::read(path) {
+        Ok(data) => Ok(Checkpoint::from_bytes(&data)?),
+        Err(_) => Err(format!("Error: Couldn't open checkpoint {}", path)),
+    }
+}
+
+/// Applies the `--noise` option from `generation` on. The checkpoints don't
+/// store it, but a resumed run with the same option draws the same numbers.
+fn set_noise(m: &Matches, life: &mut dyn Engine, generation: u64) -> Result<(), String> {
+    if let Some(noise) = m.parse::<Noise>("noise")? {
+        life.set_noise(Noise {
+            generation,
+            ..noise
+        })?;
+    }
+    Ok(())
+}
+
+/// A board ready to be stepped, new or resumed from a checkpoint.
+pub struct Run {
+    pub life: Box<dyn Engine>,
+    /// Engine and rule as given to `engines::build`, for the checkpoints.
+    pub engine: String,
+    pub rule: String,
+    /// The text of the rule table of the `ruletable` engine, read once so
+    /// the checkpoints keep the table it started with.
+    pub table: Option<String>,
+    pub generation: u64,
+}
+
+impl Run {
+    /// Builds the engine for the pattern with the `--engine`, `--rule` and
+    /// `--topology` options.
+    pub fn new(m: &Matches, pattern: &Pattern) -> Result<Self, String> {
+        let topology = m.parse::<Topology>("topology")?.unwrap_or_default();
+        let (engine, rule) = engines::resolve(m.value("engine"), pattern, m.value("rule"));
+        let table = match engine {
+            "ruletable" => Some(engines::read_table(rule)?),
+            _ => None,
+        };
+        let mut life = engines::build_with_table(
+            Some(engine),
+            pattern,
+            Some(rule),
+            table.as_deref(),
+            topology,
+        )?;
+        set_noise(m, &mut *life, 0)?;
+        Ok(Run {
+            life,
+            engine: engine.to_string(),
+            rule: rule.to_string(),
+            table,
+            generation: 0,
+        })
+    }
+
+    fn resume(m: &Matches, path: &str) -> Result<Self, String> {
+        if ["engine", "rule", "topology"]
+            .iter()
+            .any(|&o| m.value(o).is_some())
+        {
+            return Err(
+                "Error: The engine, rule and topology of a resumed run come from \
+                        the checkpoint."
+                    .to_string(),
+            );
+        }
+        let checkpoint = load_checkpoint(path)?;
+        let mut life = engines::resume(&checkpoint)?;
+        set_noise(m, &mut *life, checkpoint.generation)?;
+        Ok(Run {
+            life,
+            engine: checkpoint.engine,
+            rule: checkpoint.rule,
+            table: checkpoint.table,
+            generation: checkpoint.generation,
+        })
+    }
+
+    /// Steps the board up to generation `iterations`, printing it as told by
+    /// the `--output`, `--every` and `--quiet` options and saving the
+    /// `--checkpoint-every` checkpoints.
+    pub fn simulate(mut self, m: &Matches, iterations: u64, format: Format) -> Result<(), String> {
+        if self.generation > iterations {
+            return Err(format!(
+                "Error: The checkpoint is already at generation {}.",
+                self.generation
+            ));
+        }
+        let every = m.parse::<u64>("every")?.filter(|&n| n > 0);
+        let checkpoint_every = m.parse::<u64>("checkpoint-every")?.filter(|&n| n > 0);
+        let checkpoint_file = m.value("checkpoint-file");
+        if checkpoint_every.is_some() != checkpoint_file.is_some() {
+            return Err("Error: --checkpoint-every and --checkpoint-file go together.".to_string());
+        }
+        let quiet = m.flag("quiet");
+        let diff = m.value("diff").map(DiffMode::from_str).transpose()?;
+        let heat = m.value("heat").map(Layer::from_str).transpose()?;
+        let heat_format = m.value("heat-format").map(HeatFormat::from_str);
+        let heat_format = heat_format.transpose()?.unwrap_or(HeatFormat::Pgm);
+        if heat.is_some() {
+            if diff.is_some() {
+                return Err("Error: --heat and --diff don't go together.".to_string());
+            }
+            self.life.track_activity()?;
+        }
+        // the board, or its heat map
+        let render = |life: &dyn Engine| match (heat, life.activity()) {
+            (Some(layer), Some(activity)) => heat_format.write(activity, layer),
+            _ => format.write(life),
+        };
+
+        let mut out = open_output(m.value("output"))?;
+        let mut print = |board: String, last: bool| -> Result<(), String> {
+            if quiet {
+                return Ok(());
+            }
+            let separator = if last { "" } else { "\n" };
+            write!(out, "{}{}", board, separator)
+                .and_then(|_| if last { out.flush() } else { Ok(()) })
+                .map_err(|_| "Error: Couldn't write the output.".to_string())
+        };
+        let due = |generation: u64| every.is_some_and(|n| generation.is_multiple_of(n));
+        if diff.is_none() && due(self.generation) && self.generation < iterations {
+            print(render(&*self.life), false)?;
+        }
+        // the board the next diff is against
+        let mut previous = diff.map(|_| (self.generation, self.life.to_bitgrid()));
+        let (engine, rule, table) = (&self.engine, &self.rule, self.table.as_deref());
+        let mut observe = |life: &dyn Engine, generation: u64| -> Result<(), String> {
+            if let (Some(mode), Some((from, before))) = (diff, &mut previous) {
+                if every.is_none() || due(generation) || generation == iterations {
+                    let after = life.to_bitgrid();
+                    let changes = mode.write(&Diff::new(before, &after)?);
+                    let block = format!("generation {} to {}\n{}", from, generation, changes);
+                    print(block, generation == iterations)?;
+                    *from = generation;
+                    *before = after;
+                }
+            } else if due(generation) && generation < iterations {
+                print(render(life), false)?;
+            }
+            if let (Some(n), Some(path)) = (checkpoint_every, checkpoint_file) {
+                if generation.is_multiple_of(n) {
+                    let checkpoint = engines::checkpoint(life, engine, rule, table, generation);
+                    save_checkpoint(&checkpoint, path)?;
+                }
+            }
+            Ok(())
+        };
+        // loop, stopped by the first error
+        let mut error = None;
+        let mut stop_on_error = |life: &dyn Engine, generation: u64| match observe(life, generation)
+        {
+            Ok(()) => Control::Continue,
+            Err(e) => {
+                error = Some(e);
+                Control::Stop
+            }
+        };
+        self.generation = observer::run(
+            &mut *self.life,
+            self.generation,
+            iterations,
+            &mut stop_on_error,
+        );
+        if let Some(e) = error {
+            return Err(e);
+        }
+        // with --diff the last block was the end
+        if diff.is_none() {
+            print(render(&*self.life), true)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses the iterations argument.
+pub fn parse_iterations(s: &str) -> Result<u64, String> {
+    s.parse()
+        .map_err(|_| "Error: Couldn't parse iterations argument. Expected u64.".to_string())
+}
+
+pub fn run(args: Vec<String>) -> Result<(), String> {
+    let m = args::parse(OPTIONS, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, OPTIONS));
+        return Ok(());
+    }
+    let (run, iterations, format) = match (&m.positional[..], m.value("resume")) {
+        ([path, iterations], None) => {
+            let input = read_input(path)?;
+            let (in_format, out_format) = formats(m.value("format"), &input)?;
+            let pattern = in_format.read(&input)?;
+            (Run::new(&m, &pattern)?, iterations, out_format)
+        }
+        ([iterations], Some(path)) => {
+            let (_, out_format) = formats(m.value("format"), "")?;
+            (Run::resume(&m, path)?, iterations, out_format)
+        }
+        _ => return Err("Error: Expected initial_state and iterations, see --help.".to_string()),
+    };
+    run.simulate(&m, parse_iterations(iterations)?, format)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn format_option() {
+        let rle = "x = 1, y = 1\no!\n";
+        assert_eq!(formats(None, rle), Ok((Format::Rle, Format::Rle)));
+        assert_eq!(
+            formats(Some("mc"), rle),
+            Ok((Format::Macrocell, Format::Macrocell))
+        );
+        assert_eq!(formats(Some("/hex"), rle), Ok((Format::Rle, Format::Hex)));
+        assert_eq!(
+            formats(Some("plaintext/rle"), rle),
+            Ok((Format::Plaintext, Format::Rle))
+        );
+        assert!(formats(Some("rle/png"), rle).is_err());
+    }
+
+    #[test]
+    fn iterations() {
+        assert_eq!(parse_iterations("18446744073709551615"), Ok(u64::MAX));
+        assert_eq!(
+            parse_iterations("-1"),
+            Err("Error: Couldn't parse iterations argument. Expected u64.".to_string())
+        );
+    }
+
+    #[test]
+    fn diff_mode() {
+        assert_eq!("grid".parse(), Ok(DiffMode::Grid));
+        assert!("xor".parse::<DiffMode>().is_err());
+        let board = |s| gol::BitGrid::from_pattern(&Pattern::from_plaintext(s).unwrap());
+        let diff = Diff::new(&board("X.\n"), &board(".X\n")).unwrap();
+        assert_eq!(DiffMode::List.write(&diff), "- 0 0\n+ 1 0\n");
+        assert_eq!(DiffMode::Grid.write(&diff), "-+\n");
+    }
+
+    #[test]
+    fn heat_format() {
+        assert_eq!("ansi".parse(), Ok(HeatFormat::Ansi));
+        assert!("png".parse::<HeatFormat>().is_err());
+        let activity = Activity::new(2, 1, |x, _| x == 1);
+        assert_eq!(
+            HeatFormat::Pgm.write(&activity, Layer::Age),
+            "P2\n2 1\n1\n0 1\n"
+        );
+    }
+
+    #[test]
+    fn ruletable_checkpoint() {
+        // the checkpoint keeps the table, the file can change or go away
+        let path = std::env::temp_dir().join(format!("life-{}-Wire.rule", std::process::id()));
+        fs::copy("../ruletable/rules/WireWorld.rule", &path).unwrap();
+        let args = ["-r", path.to_str().unwrap()];
+        let m = args::parse(OPTIONS, args.map(String::from)).unwrap();
+        let pattern = Pattern::from_plaintext("23X3333\n").unwrap();
+        let mut run = Run::new(&m, &pattern).unwrap();
+        assert_eq!(run.engine, "ruletable");
+        run.life.step();
+        let table = run.table.as_deref();
+        let saved = engines::checkpoint(&*run.life, &run.engine, &run.rule, table, 1);
+        let saved = Checkpoint::from_bytes(&saved.to_bytes()).unwrap();
+        fs::write(&path, "not a rule table").unwrap();
+        let mut life = engines::resume(&saved).unwrap();
+        fs::remove_file(&path).unwrap();
+        for _ in 0..3 {
+            run.life.step();
+            life.step();
+        }
+        assert_eq!(life.to_pattern(), run.life.to_pattern());
+    }
+
+    #[test]
+    fn noise() {
+        let args = ["--noise", "flip=0.05,birth=0.8,seed=3", "-e", "opt02"];
+        let m = args::parse(OPTIONS, args.map(String::from)).unwrap();
+        let pattern = Pattern::new(20, 20);
+        let mut run = Run::new(&m, &pattern).unwrap();
+        for _ in 0..10 {
+            run.life.step();
+        }
+        assert!(run.life.population() > 0);
+
+        // a resumed run continues the same way
+        let mut resumed = Run::new(&m, &pattern).unwrap();
+        for _ in 0..4 {
+            resumed.life.step();
+        }
+        let checkpoint = engines::checkpoint(&*resumed.life, "opt02", "B3/S23", None, 4);
+        let mut life = engines::resume(&checkpoint).unwrap();
+        set_noise(&m, &mut *life, 4).unwrap();
+        for _ in 4..10 {
+            life.step();
+        }
+        assert_eq!(life.to_pattern(), run.life.to_pattern());
+
+        let m = args::parse(
+            OPTIONS,
+            ["--noise=flip=0.1", "-e", "generations"].map(String::from),
+        );
+        assert!(Run::new(&m.unwrap(), &pattern).is_err());
+    }
+}
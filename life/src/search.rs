@@ -0,0 +1,113 @@
+//! `life search`, oscillators and spaceships in a box.
+
+use crate::args::{self, Opt};
+use crate::run;
+use gol::search::Search;
+use gol::Rule;
+use std::io::Write;
+
+const USAGE: &str = "Usage: life search [options] width height period [dx dy]
+
+Looks for objects which fit in the box in every generation and come back
+after the period, moved by dx, dy (0, 0 by default, negative to the left and
+up), and prints them in the plaintext format, separated by empty lines. Objects repeating earlier are
+left out, so period 1 finds still lifes. Each object is printed in one phase,
+the same object can come up again in another phase or orientation.";
+
+const OPTIONS: &[Opt] = &[
+    Opt {
+        long: "results",
+        short: Some('n'),
+        value: Some("N"),
+        help: "stop after N objects, 1 by default, 0 for all",
+    },
+    Opt {
+        long: "dimacs",
+        short: None,
+        value: Some("PATH"),
+        help: "write the formula in DIMACS format and stop, the\n\
+               first generation is variables 1 to width * height",
+    },
+];
+
+fn options() -> Vec<Opt> {
+    let run = ["rule", "output", "help"];
+    let run = run::OPTIONS.iter().filter(|o| run.contains(&o.long));
+    OPTIONS.iter().chain(run).copied().collect()
+}
+
+/// Parses a positional number.
+fn number<T: std::str::FromStr>(s: &str, name: &str) -> Result<T, String> {
+    s.parse()
+        .map_err(|_| format!("Error: Couldn't parse the {} {}.", name, s))
+}
+
+pub fn search(args: Vec<String>) -> Result<(), String> {
+    let opts = options();
+    let m = args::parse(&opts, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, &opts));
+        return Ok(());
+    }
+    let (size, shift) = match m.positional.len() {
+        3 | 5 => m.positional.split_at(3),
+        _ => return Err("Error: Expected width, height and period, see --help.".to_string()),
+    };
+    let mut search = Search::new(
+        number(&size[0], "width")?,
+        number(&size[1], "height")?,
+        number(&size[2], "period")?,
+    );
+    if search.width == 0 || search.height == 0 || search.period == 0 {
+        return Err("Error: The box and the period can't be empty.".to_string());
+    }
+    if let [dx, dy] = shift {
+        search.dx = number(dx, "dx")?;
+        search.dy = number(dy, "dy")?;
+    }
+    let rule = m.value("rule").unwrap_or("B3/S23");
+    let rule: Rule = rule
+        .parse()
+        .map_err(|_| format!("Error: The search needs a two state rule, not {}.", rule))?;
+
+    if let Some(path) = m.value("dimacs") {
+        return run::write_output(Some(path), &search.encode(&rule).to_dimacs());
+    }
+    let results = match m.parse::<usize>("results")?.unwrap_or(1) {
+        0 => usize::MAX,
+        n => n,
+    };
+    let mut out = run::open_output(m.value("output"))?;
+    let mut found = 0;
+    for pattern in search.solutions(&rule).take(results) {
+        if found > 0 {
+            writeln!(out).map_err(|_| "Error: Couldn't write the output.".to_string())?;
+        }
+        write!(out, "{}", pattern.to_plaintext())
+            .and_then(|_| out.flush())
+            .map_err(|_| "Error: Couldn't write the output.".to_string())?;
+        found += 1;
+    }
+    if found == 0 {
+        eprintln!("Nothing found in the box.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn negative_shift() {
+        // a glider moving up and to the left
+        let path = std::env::temp_dir().join(format!("life-{}-search.txt", std::process::id()));
+        let args = ["4", "4", "4", "-1", "-1", "-o", path.to_str().unwrap()];
+        search(args.map(String::from).to_vec()).unwrap();
+        let found = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(found.matches('X').count(), 5);
+        assert_eq!(found.lines().count(), 4);
+    }
+}
@@ -0,0 +1,542 @@
+//! `life serve`, a board behind a small HTTP/1.1 server on localhost, for
+//! driving it from other languages or a browser.
+
+use crate::args::{self, Opt};
+use crate::run;
+use gol::{Engine, Pattern, Topology};
+use life::engines;
+use life::format::Format;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+
+const USAGE: &str = "Usage: life serve [options] [initial_state]
+
+Serves the board on http://127.0.0.1:PORT/, an empty 64x64 one without
+initial_state. Requests are handled one at a time:
+
+  GET  /                 a page drawing the board, with buttons to step it
+  GET  /board            the board, ?format=plaintext (the default), rle,
+                         mc, hex or pbm
+  PUT  /board            replaces the board with the one in the body and
+                         restarts at generation 0, with the rule of its
+                         header or the current one
+  GET  /info             generation, size, population, rule and topology
+                         as JSON
+  POST /step             steps ?n=N generations, 1 by default, and returns
+                         the info
+  PUT  /rule             changes the rule to the one in the body
+  PUT  /topology         changes the topology to the one in the body
+
+POST works as well as PUT. A request which fails gets status 400 and the
+error in the body, the board is left as it was. Requests with a Host or
+Origin header naming another host than localhost get status 403, so other
+web pages can't drive the board, and a step request can't ask for more than
+2^30 cell updates, so it can't hold up the server for long.";
+
+const OPTIONS: &[Opt] = &[Opt {
+    long: "port",
+    short: Some('p'),
+    value: Some("PORT"),
+    help: "port to listen on, 8080 by default",
+}];
+
+fn options() -> Vec<Opt> {
+    let run = ["engine", "rule", "topology", "help"];
+    let run = run::OPTIONS.iter().filter(|o| run.contains(&o.long));
+    OPTIONS.iter().chain(run).copied().collect()
+}
+
+const PAGE: &str = include_str!("serve.html");
+
+/// Largest request body accepted, a board of 64M cells in plaintext.
+const MAX_BODY: usize = 64 << 20;
+
+/// Most generations times cells a single `/step` computes.
+const MAX_STEP_CELLS: u64 = 1 << 30;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Request {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    /// The `Host` and `Origin` headers, if they were sent.
+    host: Option<String>,
+    origin: Option<String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn query(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn text(&self) -> Result<&str, String> {
+        std::str::from_utf8(&self.body).map_err(|_| "Error: The body isn't UTF-8.".to_string())
+    }
+
+    /// Whether the request may come from another site, its `Host` or
+    /// `Origin` naming something else than this machine. Clients which
+    /// aren't browsers may leave both out.
+    fn foreign(&self) -> bool {
+        let local = |host: &str| {
+            let name = match host.strip_prefix('[') {
+                Some(rest) => rest.split(']').next().unwrap_or(""),
+                None => host.split(':').next().unwrap_or(""),
+            };
+            matches!(name, "localhost" | "127.0.0.1" | "::1")
+        };
+        let origin = self.origin.as_deref().map(|origin| {
+            origin
+                .strip_prefix("http://")
+                .or_else(|| origin.strip_prefix("https://"))
+                .is_some_and(local)
+        });
+        !self.host.as_deref().is_none_or(local) || origin == Some(false)
+    }
+}
+
+/// Reads a request, the body only with a `Content-Length`.
+fn read_request(reader: &mut impl BufRead) -> Result<Request, String> {
+    let invalid = || "Error: Invalid HTTP request.".to_string();
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|_| invalid())?;
+    let [method, target, version] = line.split_whitespace().collect::<Vec<_>>()[..] else {
+        return Err(invalid());
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(invalid());
+    }
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query = query
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (k.to_string(), v.to_string())
+        })
+        .collect();
+    let mut request = Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        query,
+        host: None,
+        origin: None,
+        body: Vec::new(),
+    };
+
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).map_err(|_| invalid())? == 0 {
+            return Err(invalid());
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':').ok_or_else(invalid)?;
+        if name.eq_ignore_ascii_case("content-length") {
+            length = value.trim().parse().map_err(|_| invalid())?;
+        } else if name.eq_ignore_ascii_case("host") {
+            request.host = Some(value.trim().to_string());
+        } else if name.eq_ignore_ascii_case("origin") {
+            request.origin = Some(value.trim().to_string());
+        }
+    }
+    if length > MAX_BODY {
+        return Err("Error: The body is too large.".to_string());
+    }
+    request.body = vec![0; length];
+    reader
+        .read_exact(&mut request.body)
+        .map_err(|_| invalid())?;
+    Ok(request)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn text(body: String) -> Self {
+        Response {
+            status: 200,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Response {
+            status,
+            ..Response::text(format!("{}\n", message))
+        }
+    }
+
+    fn write_to(&self, out: &mut impl Write) -> std::io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Error",
+        };
+        write!(
+            out,
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n\
+             Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+            self.status,
+            reason,
+            self.content_type,
+            self.body.len()
+        )?;
+        out.write_all(&self.body)?;
+        out.flush()
+    }
+}
+
+/// Quotes the string for JSON.
+fn json_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            c if (c as u32) < 0x20 => ret.push_str(&format!("\\u{:04x}", c as u32)),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+struct Server {
+    life: Box<dyn Engine>,
+    /// `--engine`, the default one for each rule without it.
+    engine: Option<String>,
+    /// The rule as given to `engines::build`, the path for `.rule` files.
+    rule: String,
+    generation: u64,
+}
+
+impl Server {
+    fn new(
+        engine: Option<&str>,
+        pattern: &Pattern,
+        rule: Option<&str>,
+        topology: Topology,
+    ) -> Result<Self, String> {
+        let life = engines::build(engine, pattern, rule, topology)?;
+        let (_, rule) = engines::resolve(engine, pattern, rule);
+        Ok(Server {
+            life,
+            engine: engine.map(str::to_string),
+            rule: rule.to_string(),
+            generation: 0,
+        })
+    }
+
+    /// Replaces the engine, the old one is kept if it can't be built.
+    fn rebuild(&mut self, pattern: &Pattern, rule: &str, topology: Topology) -> Result<(), String> {
+        self.life = engines::build(self.engine.as_deref(), pattern, Some(rule), topology)?;
+        self.rule = rule.to_string();
+        Ok(())
+    }
+
+    fn info(&self) -> Response {
+        let json = format!(
+            "{{\"generation\": {}, \"width\": {}, \"height\": {}, \"population\": {}, \
+             \"rule\": {}, \"topology\": {}}}\n",
+            self.generation,
+            self.life.width(),
+            self.life.height(),
+            self.life.population(),
+            json_string(&self.life.rule()),
+            json_string(&self.life.topology().to_string())
+        );
+        Response {
+            content_type: "application/json",
+            ..Response::text(json)
+        }
+    }
+
+    fn handle(&mut self, request: &Request) -> Response {
+        if request.foreign() {
+            return Response::error(403, "Error: Only pages on localhost may use the server.");
+        }
+        let method = request.method.as_str();
+        let update = method == "PUT" || method == "POST";
+        let response = match (request.path.as_str(), method) {
+            ("/", "GET") => Ok(Response {
+                content_type: "text/html; charset=utf-8",
+                ..Response::text(PAGE.to_string())
+            }),
+            ("/board", "GET") => {
+                let format = request.query("format").unwrap_or("plaintext");
+                format
+                    .parse::<Format>()
+                    .map(|format| Response::text(format.write(&*self.life)))
+            }
+            ("/board", _) if update => self.upload(request),
+            ("/info", "GET") => Ok(self.info()),
+            ("/step", "POST") => self.step(request),
+            ("/rule", _) if update => request.text().and_then(|rule| {
+                let pattern = self.life.to_pattern();
+                self.rebuild(&pattern, rule.trim(), self.life.topology())
+                    .map(|_| self.info())
+            }),
+            ("/topology", _) if update => request.text().and_then(|topology| {
+                let topology = topology.trim().parse::<Topology>()?;
+                let (pattern, rule) = (self.life.to_pattern(), self.rule.clone());
+                self.rebuild(&pattern, &rule, topology).map(|_| self.info())
+            }),
+            ("/" | "/board" | "/info" | "/step" | "/rule" | "/topology", _) => {
+                Ok(Response::error(405, "Error: Method not allowed."))
+            }
+            _ => Ok(Response::error(404, "Error: Not found.")),
+        };
+        response.unwrap_or_else(|e| Response::error(400, &e))
+    }
+
+    fn upload(&mut self, request: &Request) -> Result<Response, String> {
+        let input = request.text()?;
+        let pattern = Format::detect(input).read(input)?;
+        let rule = pattern.rule.clone().unwrap_or_else(|| self.rule.clone());
+        self.rebuild(&pattern, &rule, self.life.topology())?;
+        self.generation = 0;
+        Ok(self.info())
+    }
+
+    fn step(&mut self, request: &Request) -> Result<Response, String> {
+        let n = match request.query("n") {
+            Some(n) => n
+                .parse::<u64>()
+                .map_err(|_| format!("Error: Invalid number {}.", n))?,
+            None => 1,
+        };
+        let cells = (self.life.width() * self.life.height()).max(1) as u64;
+        if n > MAX_STEP_CELLS / cells {
+            return Err(format!(
+                "Error: At most {} generations of this board a request.",
+                MAX_STEP_CELLS / cells
+            ));
+        }
+        for _ in 0..n {
+            self.life.step();
+        }
+        self.generation += n;
+        Ok(self.info())
+    }
+
+    /// Answers one request on the connection and closes it.
+    fn connection(&mut self, stream: TcpStream) -> Result<(), String> {
+        // a client which stops sending doesn't hold up the others for long
+        let _ = stream.set_read_timeout(Some(Duration::from_secs(10)));
+        let response = match read_request(&mut BufReader::new(&stream)) {
+            Ok(request) => self.handle(&request),
+            Err(e) => Response::error(400, &e),
+        };
+        response
+            .write_to(&mut &stream)
+            .map_err(|_| "Error: Couldn't write the response.".to_string())
+    }
+}
+
+pub fn serve(args: Vec<String>) -> Result<(), String> {
+    let opts = options();
+    let m = args::parse(&opts, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, &opts));
+        return Ok(());
+    }
+    let pattern = match &m.positional[..] {
+        [] => Pattern::new(64, 64),
+        [path] => {
+            let input = run::read_input(path)?;
+            Format::detect(&input).read(&input)?
+        }
+        _ => return Err("Error: Expected at most one initial_state, see --help.".to_string()),
+    };
+    let topology = m.parse::<Topology>("topology")?.unwrap_or_default();
+    let mut server = Server::new(m.value("engine"), &pattern, m.value("rule"), topology)?;
+
+    let port = m.parse::<u16>("port")?.unwrap_or(8080);
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|_| format!("Error: Couldn't listen on port {}.", port))?;
+    eprintln!("Serving on http://127.0.0.1:{}/", port);
+    for stream in listener.incoming() {
+        let result = match stream {
+            Ok(stream) => server.connection(stream),
+            Err(_) => Err("Error: Couldn't accept a connection.".to_string()),
+        };
+        if let Err(e) = result {
+            eprintln!("{}", e);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+    use std::thread;
+
+    fn request(s: &str) -> Request {
+        read_request(&mut s.as_bytes()).unwrap()
+    }
+
+    fn server() -> Server {
+        let glider = Pattern::from_plaintext(".X.\n..X\nXXX\n").unwrap();
+        let board = glider.pad(0, 0, 5, 5);
+        Server::new(None, &board, None, Topology::Torus).unwrap()
+    }
+
+    fn body(response: Response) -> String {
+        String::from_utf8(response.body).unwrap()
+    }
+
+    #[test]
+    fn requests() {
+        let r = request("PUT /board?format=rle&x HTTP/1.1\r\nContent-length: 4\r\n\r\n.X\n\n");
+        assert_eq!((r.method.as_str(), r.path.as_str()), ("PUT", "/board"));
+        assert_eq!((r.query("format"), r.query("x")), (Some("rle"), Some("")));
+        assert_eq!(r.body, b".X\n\n");
+        assert_eq!(request("GET / HTTP/1.0\n\n").body, b"");
+        assert!(read_request(&mut "GET /\r\n\r\n".as_bytes()).is_err());
+        assert!(read_request(&mut "GET / HTTP/1.1\r\nHost".as_bytes()).is_err());
+        assert!(
+            read_request(&mut "POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\nX".as_bytes()).is_err()
+        );
+        assert_eq!(json_string("a\"\\\n"), "\"a\\\"\\\\\\u000a\"");
+    }
+
+    #[test]
+    fn endpoints() {
+        let mut server = server();
+        let info = body(server.handle(&request("POST /step?n=4 HTTP/1.1\r\n\r\n")));
+        assert_eq!(
+            info,
+            "{\"generation\": 4, \"width\": 8, \"height\": 8, \"population\": 5, \
+             \"rule\": \"B3/S23\", \"topology\": \"torus\"}\n"
+        );
+        let board = body(server.handle(&request("GET /board HTTP/1.1\r\n\r\n")));
+        assert_eq!(board.lines().nth(3), Some(".XXX...."));
+        let pbm = body(server.handle(&request("GET /board?format=pbm HTTP/1.1\r\n\r\n")));
+        assert!(pbm.starts_with("P1\n8 8\n00000000\n00100000\n"));
+
+        let upload = "PUT /board HTTP/1.1\r\nContent-Length: 17\r\n\r\nx = 2, y = 1\n2o!\n";
+        assert!(
+            body(server.handle(&request(upload))).starts_with("{\"generation\": 0, \"width\": 2")
+        );
+        for header in [
+            "x = 100000000, y = 100000000",
+            "x = 4294967296, y = 4294967296",
+        ] {
+            let huge = format!(
+                "PUT /board HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}\no!\n",
+                header.len() + 4,
+                header
+            );
+            assert_eq!(server.handle(&request(&huge)).status, 400);
+        }
+        assert_eq!(server.life.width(), 2);
+        server.handle(&request(
+            "PUT /rule HTTP/1.1\r\nContent-Length: 10\r\n\r\nB3/S1/C3\r\n",
+        ));
+        assert_eq!(server.life.rule(), "B3/S1/C3");
+        let r = server.handle(&request(
+            "PUT /topology HTTP/1.1\r\nContent-Length: 5\r\n\r\nknot\n",
+        ));
+        assert_eq!(r.status, 400);
+        assert_eq!(server.life.topology(), Topology::Torus);
+        server.handle(&request(
+            "POST /topology HTTP/1.1\r\nContent-Length: 7\r\n\r\nbounded",
+        ));
+        assert_eq!(server.life.topology(), Topology::Bounded);
+        assert_eq!(server.life.rule(), "B3/S1/C3");
+
+        assert_eq!(
+            server.handle(&request("GET /step HTTP/1.1\r\n\r\n")).status,
+            405
+        );
+        assert_eq!(
+            server
+                .handle(&request("GET /nothing HTTP/1.1\r\n\r\n"))
+                .status,
+            404
+        );
+        assert_eq!(
+            server
+                .handle(&request("POST /step?n=x HTTP/1.1\r\n\r\n"))
+                .status,
+            400
+        );
+        let page = server.handle(&request("GET / HTTP/1.1\r\n\r\n"));
+        assert!(page.content_type.starts_with("text/html"));
+    }
+
+    #[test]
+    fn limits() {
+        let mut server = server();
+        // 8x8 cells, 2^24 generations at most
+        let step = |n: u64| format!("POST /step?n={} HTTP/1.1\r\n\r\n", n);
+        assert_eq!(server.handle(&request(&step((1 << 24) + 1))).status, 400);
+        assert_eq!(server.handle(&request(&step(u64::MAX))).status, 400);
+        assert_eq!(server.handle(&request(&step(3))).status, 200);
+
+        for headers in [
+            "Host: localhost:8080\r\nOrigin: http://localhost:8080\r\n",
+            "Host: 127.0.0.1\r\nOrigin: http://127.0.0.1:1234\r\n",
+            "Host: [::1]:8080\r\n",
+            "",
+        ] {
+            let r = request(&format!("GET /info HTTP/1.1\r\n{}\r\n", headers));
+            assert_eq!(server.handle(&r).status, 200, "{}", headers);
+        }
+        for headers in [
+            "Host: evil.example\r\n",
+            "Host: localhost.evil.example:8080\r\n",
+            "Host: localhost\r\nOrigin: http://evil.example\r\n",
+            "Origin: null\r\n",
+        ] {
+            let r = request(&format!("POST /step HTTP/1.1\r\n{}\r\n", headers));
+            assert_eq!(server.handle(&r).status, 403, "{}", headers);
+        }
+        assert_eq!(server.generation, 3);
+    }
+
+    #[test]
+    fn tcp() {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let worker = thread::spawn(move || {
+            let mut server = server();
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                server.connection(stream).unwrap();
+            }
+        });
+        let fetch = |request: &str| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+        let response = fetch("POST /step?n=2 HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\r\n\r\n{\"generation\": 2,"));
+        let response = fetch("GET /board?format=rle HTTP/1.1\r\n\r\n");
+        assert!(response.ends_with("\r\n\r\nx = 8, y = 8, rule = B3/S23\n$2bo$obo$b2o!\n"));
+        worker.join().unwrap();
+    }
+}
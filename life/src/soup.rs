@@ -0,0 +1,244 @@
+//! Random initial states ("soups") for benchmarks and searches, the same
+//! seed always gives the same soup.
+
+use gol::random::Random;
+use gol::Pattern;
+use std::fmt;
+use std::str::FromStr;
+
+/// Symmetry of the filled region, as in apgsearch's symmetric soups.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Symmetry {
+    /// No symmetry.
+    #[default]
+    C1,
+    /// Unchanged by a half turn.
+    C2,
+    /// Unchanged by a quarter turn, needs a square region.
+    C4,
+    /// Unchanged by all rotations and reflections, needs a square region.
+    D8,
+}
+
+impl Symmetry {
+    /// The cells of the `width` x `height` region which are the same as
+    /// `(x, y)`, including itself.
+    fn orbit(&self, x: usize, y: usize, width: usize, height: usize) -> Vec<(usize, usize)> {
+        let (w, h) = (width - 1, height - 1);
+        let mut ret = match self {
+            Symmetry::C1 => vec![(x, y)],
+            Symmetry::C2 => vec![(x, y), (w - x, h - y)],
+            Symmetry::C4 | Symmetry::D8 => vec![(x, y), (w - y, x), (w - x, h - y), (y, h - x)],
+        };
+        if *self == Symmetry::D8 {
+            let transposed: Vec<_> = ret.iter().map(|&(x, y)| (y, x)).collect();
+            ret.extend(transposed);
+        }
+        ret
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "C1" => Ok(Symmetry::C1),
+            "C2" => Ok(Symmetry::C2),
+            "C4" => Ok(Symmetry::C4),
+            "D8" => Ok(Symmetry::D8),
+            _ => Err(format!(
+                "Error: Unknown symmetry {}, expected C1, C2, C4 or D8.",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The rectangle of the board which is filled, `X,Y,W,H` as text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl FromStr for Region {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numbers: Result<Vec<usize>, _> = s.split(',').map(|n| n.trim().parse()).collect();
+        match numbers.as_deref() {
+            Ok(&[x, y, width, height]) => Ok(Region {
+                x,
+                y,
+                width,
+                height,
+            }),
+            _ => Err(format!("Error: Invalid region {}, expected X,Y,W,H.", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Soup {
+    pub width: usize,
+    pub height: usize,
+    /// Probability of a live cell.
+    pub density: f64,
+    pub symmetry: Symmetry,
+    /// The filled part of the board, all of it when `None`.
+    pub region: Option<Region>,
+    pub seed: u64,
+}
+
+impl Soup {
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Soup {
+            width,
+            height,
+            density: 0.5,
+            symmetry: Symmetry::C1,
+            region: None,
+            seed,
+        }
+    }
+
+    pub fn generate(&self) -> Result<Pattern, String> {
+        if self.width == 0 || self.height == 0 {
+            return Err("Error: The board is empty.".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.density) {
+            return Err("Error: The density has to be between 0 and 1.".to_string());
+        }
+        let mut ret = Pattern::try_new(self.width, self.height)?;
+        let region = self.region.unwrap_or(Region {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        });
+        if region.width == 0
+            || region.height == 0
+            || region
+                .x
+                .checked_add(region.width)
+                .is_none_or(|r| r > self.width)
+            || region
+                .y
+                .checked_add(region.height)
+                .is_none_or(|r| r > self.height)
+        {
+            return Err("Error: The region has to be inside the board.".to_string());
+        }
+        if matches!(self.symmetry, Symmetry::C4 | Symmetry::D8) && region.width != region.height {
+            return Err(format!(
+                "Error: {} symmetry needs a square region.",
+                self.symmetry
+            ));
+        }
+
+        let mut random = Random::new(self.seed);
+        // one random draw for each orbit, in the order of its first cell
+        let mut done = vec![false; region.width * region.height];
+        for y in 0..region.height {
+            for x in 0..region.width {
+                if done[y * region.width + x] {
+                    continue;
+                }
+                let alive = random.chance(self.density) as u8;
+                for (ox, oy) in self.symmetry.orbit(x, y, region.width, region.height) {
+                    done[oy * region.width + ox] = true;
+                    ret.set(region.x + ox, region.y + oy, alive);
+                }
+            }
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cells(p: &Pattern) -> Vec<Vec<u8>> {
+        (0..p.height)
+            .map(|y| (0..p.width).map(|x| p.get(x, y)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn seeded() {
+        let soup = Soup::new(40, 30, 1);
+        let pattern = soup.generate().unwrap();
+        assert_eq!(pattern, soup.generate().unwrap());
+        assert_ne!(pattern, Soup::new(40, 30, 2).generate().unwrap());
+        let alive = pattern.cells.iter().filter(|&&c| c == 1).count();
+        assert!((500..700).contains(&alive), "{}", alive);
+
+        let mut sparse = soup.clone();
+        sparse.density = 0.0;
+        assert!(sparse.generate().unwrap().cells.iter().all(|&c| c == 0));
+        sparse.density = 1.5;
+        assert!(sparse.generate().is_err());
+    }
+
+    #[test]
+    fn symmetries() {
+        for (symmetry, size) in [
+            (Symmetry::C2, (7, 4)),
+            (Symmetry::C4, (6, 6)),
+            (Symmetry::D8, (7, 7)),
+        ] {
+            let mut soup = Soup::new(size.0, size.1, 3);
+            soup.symmetry = symmetry;
+            let c = cells(&soup.generate().unwrap());
+            let (w, h) = (size.0 - 1, size.1 - 1);
+            for y in 0..size.1 {
+                for x in 0..size.0 {
+                    assert_eq!(c[y][x], c[h - y][w - x], "{}", symmetry);
+                    if symmetry != Symmetry::C2 {
+                        assert_eq!(c[y][x], c[x][w - y], "{}", symmetry);
+                    }
+                    if symmetry == Symmetry::D8 {
+                        assert_eq!(c[y][x], c[x][y], "{}", symmetry);
+                    }
+                }
+            }
+        }
+        let mut soup = Soup::new(6, 5, 3);
+        soup.symmetry = Symmetry::C4;
+        assert!(soup.generate().is_err());
+    }
+
+    #[test]
+    fn region() {
+        let mut soup = Soup::new(10, 8, 5);
+        soup.density = 1.0;
+        soup.region = Some("2,3,4,2".parse().unwrap());
+        let c = cells(&soup.generate().unwrap());
+        for (y, row) in c.iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                let inside = (2..6).contains(&x) && (3..5).contains(&y);
+                assert_eq!(cell, inside as u8);
+            }
+        }
+        soup.region = Some("8,0,4,4".parse().unwrap());
+        assert!(soup.generate().is_err());
+        soup.region = Some("18446744073709551615,0,2,2".parse().unwrap());
+        assert!(soup.generate().is_err());
+        soup.region = None;
+        soup.width = 100_000_000;
+        soup.height = 100_000_000;
+        assert!(soup.generate().is_err());
+        assert!("1,2,3".parse::<Region>().is_err());
+        assert_eq!("d8".parse(), Ok(Symmetry::D8));
+    }
+}
@@ -0,0 +1,52 @@
+//! `life 3d`, stepping a 3D board.
+
+use crate::args::{self, Opt};
+use crate::run;
+use life3d::{LifeState, Rule};
+
+const USAGE: &str = "Usage: life 3d [options] initial_state iterations
+
+Steps the 3D board in initial_state, its slices of . and X separated by empty
+lines, and prints the result the same way. Cells beyond the faces of the
+board are dead. A path of - reads stdin.";
+
+const OPTIONS: &[Opt] = &[Opt {
+    long: "rule",
+    short: Some('r'),
+    value: Some("RULE"),
+    help: "B/S rule counting the 26 neighbours, e.g. B5/S45\n\
+           or B14-19/S13-19, or Bays' notation, 4555 (the\n\
+           default, B5/S45)",
+}];
+
+fn options() -> Vec<Opt> {
+    let run = ["output", "quiet", "help"];
+    let run = run::OPTIONS.iter().filter(|o| run.contains(&o.long));
+    OPTIONS.iter().chain(run).copied().collect()
+}
+
+pub fn three_d(args: Vec<String>) -> Result<(), String> {
+    let opts = options();
+    let m = args::parse(&opts, args)?;
+    if m.flag("help") {
+        print!("{}", args::help(USAGE, &opts));
+        return Ok(());
+    }
+    let (path, iterations) = match &m.positional[..] {
+        [path, iterations] => (path, run::parse_iterations(iterations)?),
+        _ => return Err("Error: Expected initial_state and iterations, see --help.".to_string()),
+    };
+    let mut life = LifeState::from_layers(&run::read_input(path)?)?;
+    if life.depth() == 0 {
+        return Err("Error: The board is empty.".to_string());
+    }
+    life.rule = m.parse::<Rule>("rule")?.unwrap_or_default();
+    for _ in 0..iterations {
+        life.step();
+    }
+    if m.flag("quiet") {
+        return Ok(());
+    }
+
+    run::write_output(m.value("output"), &life.to_string())
+}
@@ -0,0 +1,309 @@
+//! Life in three dimensions, every cell with the 26 neighbours of its 3x3x3
+//! cube and a totalistic B/S rule like Bays' 4555. Cells beyond the faces of
+//! the box are dead, as on a bounded 2D board.
+//!
+//! Boards are stacks of plaintext slices of `.` and `X`, all of the same size
+//! and separated by empty lines, the first slice at `z = 0`.
+
+use gol::pattern::split_boards;
+use gol::Pattern;
+use std::fmt;
+use std::str::FromStr;
+
+/// Counts of live neighbours for a birth and for a survival, bit `n` for `n`
+/// neighbours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    pub birth: u32,
+    pub survival: u32,
+}
+
+impl Default for Rule {
+    /// Bays' 4555.
+    fn default() -> Self {
+        Rule {
+            birth: 1 << 5,
+            survival: 1 << 4 | 1 << 5,
+        }
+    }
+}
+
+/// Parses the counts after `B` or `S`, single digits like `45`, or numbers
+/// and ranges separated by commas like `4,13-15`.
+fn counts(s: &str) -> Result<u32, &'static str> {
+    let err = "Error: Couldn't parse the 3D rule, expected e.g. B5/S45 or 4555.";
+    let count = |s: &str| match s.parse::<u32>() {
+        Ok(n) if n <= 26 => Ok(n),
+        _ => Err(err),
+    };
+    if !s.contains([',', '-']) {
+        return s
+            .chars()
+            .try_fold(0, |ret, c| Ok(ret | 1 << count(&c.to_string())?));
+    }
+    s.split(',').try_fold(0, |ret, item| {
+        let (low, high) = item.split_once('-').unwrap_or((item, item));
+        let (low, high) = (count(low)?, count(high)?);
+        Ok(ret | (low..=high).fold(0, |r, n| r | 1 << n))
+    })
+}
+
+/// `B5/S45` or Bays' notation `EFLU`, survival from `E` to `F` and birth
+/// from `L` to `U` live neighbours, so `4555` is `B5/S45`.
+impl FromStr for Rule {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_ascii_uppercase();
+        if let Some((b, s)) = upper.split_once('/') {
+            let (b, s) = match (b.strip_prefix('B'), s.strip_prefix('S')) {
+                (Some(b), Some(s)) => (b, s),
+                _ => match (b.strip_prefix('S'), s.strip_prefix('B')) {
+                    (Some(s), Some(b)) => (b, s),
+                    _ => return Err("Error: Couldn't parse the 3D rule, expected e.g. B5/S45."),
+                },
+            };
+            return Ok(Rule {
+                birth: counts(b)?,
+                survival: counts(s)?,
+            });
+        }
+        let digits: Vec<u32> = s.chars().filter_map(|c| c.to_digit(10)).collect();
+        match digits[..] {
+            [e, f, l, u] if s.len() == 4 && e <= f && l <= u => Ok(Rule {
+                birth: (l..=u).fold(0, |r, n| r | 1 << n),
+                survival: (e..=f).fold(0, |r, n| r | 1 << n),
+            }),
+            _ => Err("Error: Couldn't parse the 3D rule, expected e.g. B5/S45 or 4555."),
+        }
+    }
+}
+
+/// Single digits run together like `B5/S45`. Once a count has two digits
+/// they are separated by commas, and a lone one is written as a range like
+/// `B10-10` so that it doesn't read back as the digits 1 and 0.
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let list = |bits: u32| {
+            let counts: Vec<String> = (0..=26)
+                .filter(|n| bits >> n & 1 == 1)
+                .map(|n: u32| n.to_string())
+                .collect();
+            match &counts[..] {
+                _ if bits >> 10 == 0 => counts.concat(),
+                [n] => format!("{}-{}", n, n),
+                _ => counts.join(","),
+            }
+        };
+        write!(f, "B{}/S{}", list(self.birth), list(self.survival))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LifeState {
+    width: usize,
+    height: usize,
+    depth: usize,
+    pub rule: Rule,
+    /// Slice by slice and row by row, 1 for a live cell.
+    cells: Vec<u8>,
+}
+
+/// Adds to each cell its two neighbours `stride` apart along an axis of
+/// `len` cells, those beyond the ends count as dead.
+fn add_neighbours(cells: &[u8], stride: usize, len: usize) -> Vec<u8> {
+    let mut ret = cells.to_vec();
+    for (i, sum) in ret.iter_mut().enumerate() {
+        let c = i / stride % len;
+        if c > 0 {
+            *sum += cells[i - stride];
+        }
+        if c + 1 < len {
+            *sum += cells[i + stride];
+        }
+    }
+    ret
+}
+
+impl LifeState {
+    pub fn new(width: usize, height: usize, depth: usize) -> Self {
+        LifeState {
+            width,
+            height,
+            depth,
+            rule: Rule::default(),
+            cells: vec![0; width * height * depth],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.height + y) * self.width + x
+    }
+
+    pub fn get(&self, x: usize, y: usize, z: usize) -> bool {
+        self.cells[self.index(x, y, z)] == 1
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, z: usize, alive: bool) {
+        let i = self.index(x, y, z);
+        self.cells[i] = alive.into();
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.iter().filter(|&&c| c == 1).count()
+    }
+
+    /// computes the next generation. The sums of the 3x3x3 cubes are added
+    /// up one axis after another, 6 additions a cell instead of 26.
+    pub fn step(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let sums = add_neighbours(&self.cells, 1, width);
+        let sums = add_neighbours(&sums, width, height);
+        let sums = add_neighbours(&sums, width * height, self.depth);
+        for (cell, sum) in self.cells.iter_mut().zip(sums) {
+            let rule = if *cell == 1 {
+                self.rule.survival
+            } else {
+                self.rule.birth
+            };
+            *cell = (rule >> (sum - *cell) & 1) as u8;
+        }
+    }
+
+    /// Reads the slices, separated by empty lines.
+    pub fn from_layers(s: &str) -> Result<Self, &'static str> {
+        let layers = split_boards(s)
+            .iter()
+            .map(|l| Pattern::from_plaintext(l))
+            .collect::<Result<Vec<_>, _>>()?;
+        let (width, height) = layers.first().map_or((0, 0), |l| (l.width, l.height));
+        let mut ret = LifeState::new(width, height, layers.len());
+        for (z, layer) in layers.iter().enumerate() {
+            if (layer.width, layer.height) != (width, height) {
+                return Err("Error: The slices must have the same size.");
+            }
+            if layer.mask.is_some() || layer.cells.iter().any(|&c| c > 1) {
+                return Err("Error: 3D boards only have . and X cells.");
+            }
+            let start = ret.index(0, 0, z);
+            ret.cells[start..start + width * height].copy_from_slice(&layer.cells);
+        }
+        Ok(ret)
+    }
+
+    pub fn to_layers(&self) -> String {
+        self.to_string()
+    }
+}
+
+/// The slices separated by empty lines, as read by `from_layers`.
+impl fmt::Display for LifeState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let size = (self.width * self.height).max(1);
+        for (z, layer) in self.cells.chunks(size).enumerate() {
+            if z > 0 {
+                writeln!(f)?;
+            }
+            for row in layer.chunks(self.width.max(1)) {
+                let row: String = row
+                    .iter()
+                    .map(|&c| if c == 1 { 'X' } else { '.' })
+                    .collect();
+                writeln!(f, "{}", row)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use gol::random::Random;
+
+    #[test]
+    fn rules() {
+        assert_eq!("4555".parse(), Ok(Rule::default()));
+        assert_eq!("b5/s45".parse::<Rule>().unwrap().to_string(), "B5/S45");
+        let rule: Rule = "B14-15/S4,13".parse().unwrap();
+        assert_eq!(rule.birth, 1 << 14 | 1 << 15);
+        assert_eq!(rule.to_string(), "B14,15/S4,13");
+        assert_eq!("5766".parse::<Rule>().unwrap().to_string(), "B6/S567");
+        for text in ["B10-10/S4", "B/S12-12", "B10,11/S", "B4,10/S0"] {
+            let rule: Rule = text.parse().unwrap();
+            assert_eq!(rule.to_string(), text);
+            assert_eq!(rule.to_string().parse(), Ok(rule));
+        }
+        assert_eq!("B10/S4".parse::<Rule>().unwrap().to_string(), "B01/S4");
+        for bad in ["B5", "B2,27/S4", "B5a/S4", "7555", "45555", "B5/X4"] {
+            assert!(bad.parse::<Rule>().is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn neighbours() {
+        // against counting the 26 neighbours of every cell
+        let mut random = Random::new(5);
+        let mut life = LifeState::new(6, 5, 4);
+        for i in 0..life.cells.len() {
+            life.cells[i] = random.chance(0.3) as u8;
+        }
+        for rule in ["4555", "B3/S23", "B0/S"] {
+            life.rule = rule.parse().unwrap();
+            let last = life.clone();
+            life.step();
+            for z in 0..4 {
+                for y in 0..5 {
+                    for x in 0..6 {
+                        let mut n = 0;
+                        for (dx, dy, dz) in (0..27).map(|i| (i % 3, i / 3 % 3, i / 9)) {
+                            let (nx, ny, nz) = (x + dx, y + dy, z + dz);
+                            if (dx, dy, dz) != (1, 1, 1)
+                                && (1..=6).contains(&nx)
+                                && (1..=5).contains(&ny)
+                                && (1..=4).contains(&nz)
+                            {
+                                n += last.get(nx - 1, ny - 1, nz - 1) as u32;
+                            }
+                        }
+                        let bits = match last.get(x, y, z) {
+                            true => life.rule.survival,
+                            false => life.rule.birth,
+                        };
+                        assert_eq!(life.get(x, y, z), bits >> n & 1 == 1);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn layers() {
+        // a single slice with B3/S23 is 2D Life
+        let mut life = LifeState::from_layers(".....\n..X..\n..X..\n..X..\n.....\n").unwrap();
+        life.rule = "B3/S23".parse().unwrap();
+        life.step();
+        assert_eq!(life.to_layers(), ".....\n.....\n.XXX.\n.....\n.....\n");
+
+        let s = "X.\n..\n\n\n.X\nX.\n";
+        let life = LifeState::from_layers(s).unwrap();
+        assert_eq!((life.width(), life.height(), life.depth()), (2, 2, 2));
+        assert!(life.get(1, 0, 1) && life.get(0, 1, 1) && !life.get(1, 1, 1));
+        assert_eq!(life.to_layers(), "X.\n..\n\n.X\nX.\n");
+        assert_eq!(life.population(), 3);
+        assert!(LifeState::from_layers("X.\n\nX\n").is_err());
+        assert!(LifeState::from_layers("X2\n").is_err());
+    }
+}
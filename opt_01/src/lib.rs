@@ -0,0 +1,597 @@
+//! The board padded with an always dead frame, so the neighbourhood of a cell
+//! can be read without bounds checks.
+
+use gol::activity::Activity;
+use gol::mask::Mask;
+use gol::noise::Noise;
+use gol::pattern::Pattern;
+use gol::{Engine, Rule, Topology};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LifeState {
+    width: usize,
+    height: usize,
+    pub rule: Rule,
+    pub topology: Topology,
+    data: Vec<Vec<u8>>,
+    activity: Option<Activity>,
+    mask: Option<Mask>,
+    noise: Option<Noise>,
+}
+
+impl LifeState {
+    /// Returns the neighbourhood index of the cell for the rule lookup table,
+    /// see `gol::rule`.
+    fn neighbourhood(&self, x: usize, y: usize) -> usize {
+        // This is safe, because of the padding introduced
+        let (above, row, below) = (&self.data[y - 1], &self.data[y], &self.data[y + 1]);
+        (above[x - 1] as usize)
+            | (above[x] as usize) << 1
+            | (above[x + 1] as usize) << 2
+            | (row[x - 1] as usize) << 3
+            | (row[x] as usize) << 4
+            | (row[x + 1] as usize) << 5
+            | (below[x - 1] as usize) << 6
+            | (below[x] as usize) << 7
+            | (below[x + 1] as usize) << 8
+    }
+
+    /// Fills the frame with the cells beyond the edges, dead or the opposite
+    /// edge of the board on a torus.
+    fn fill_frame(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let torus = self.topology == Topology::Torus;
+        for row in self.data.iter_mut() {
+            row[0] = if torus { row[width - 2] } else { 0 };
+            row[width - 1] = if torus { row[1] } else { 0 };
+        }
+        if torus {
+            self.data[0] = self.data[height - 2].clone();
+            self.data[height - 1] = self.data[1].clone();
+        } else {
+            self.data[0].fill(0);
+            self.data[height - 1].fill(0);
+        }
+    }
+
+    /// Forces the walls and fixed cells, see `gol::mask`.
+    fn apply_mask(&mut self) {
+        if let Some(mask) = &self.mask {
+            for (x, y, state) in mask.cells() {
+                self.data[y + 1][x + 1] = state;
+            }
+        }
+    }
+
+    /// Reads a plaintext board, `#` and `@` become the walls and fixed
+    /// cells of its mask, see `gol::mask`.
+    pub fn from_plaintext(s: &str) -> Result<Self, &'static str> {
+        let pattern = Pattern::from_plaintext(s)?;
+        if pattern.cells.iter().any(|&c| c > 1) {
+            return Err("Error: Cell state is not valid for the rule.");
+        }
+        let mut ret = LifeState::from(&pattern);
+        if let Some(mask) = pattern.mask {
+            ret.set_mask(mask)?;
+        }
+        Ok(ret)
+    }
+
+    /// Parses the macrocell format, see `gol::macrocell`. The rule is taken
+    /// from the header unless it is missing.
+    pub fn from_macrocell(s: &str) -> Result<Self, &'static str> {
+        let pattern = Pattern::from_macrocell(s)?;
+        let mut ret = LifeState::from(&pattern);
+        if let Some(rule) = &pattern.rule {
+            ret.rule = rule.parse()?;
+        }
+        Ok(ret)
+    }
+
+    pub fn to_macrocell(&self) -> String {
+        self.to_pattern()
+            .to_macrocell(&self.rule.to_string(), false)
+    }
+}
+
+impl Iterator for LifeState {
+    type Item = LifeState;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.fill_frame();
+        let mut dice = self.noise.as_mut().map(Noise::step);
+        let mut new = self.clone();
+        for y in 1..(self.height - 1) {
+            for x in 1..(self.width - 1) {
+                let mut new_val = self.rule.alive(self.neighbourhood(x, y));
+                if let Some(dice) = &mut dice {
+                    new_val = dice.cell(self.data[y][x] == 1, new_val);
+                }
+                new.data[y][x] = new_val.into();
+                if let Some(activity) = &mut new.activity {
+                    // counted in the state the mask keeps the cell in
+                    let masked = self.mask.as_ref().and_then(|m| m.get(x - 1, y - 1));
+                    let alive = masked.map_or(new_val, |state| state == 1);
+                    let i = (y - 1) * (self.width - 2) + x - 1;
+                    activity.update(i, self.data[y][x] == 1, alive);
+                }
+            }
+        }
+        new.apply_mask();
+        Some(new)
+    }
+}
+
+impl From<&str> for LifeState {
+    fn from(s: &str) -> Self {
+        let lines = s.split('\n');
+        let mut ret = LifeState {
+            width: 0,
+            height: 0,
+            rule: Rule::default(),
+            topology: Topology::Bounded,
+            data: Vec::with_capacity(lines.size_hint().0 * 100),
+            activity: None,
+            mask: None,
+            noise: None,
+        };
+        for line in lines {
+            let mut line_bools: Vec<u8> =
+                line.chars().map(|c| if c == 'X' { 1 } else { 0 }).collect();
+            if !line_bools.is_empty() {
+                ret.height += 1;
+                line_bools.insert(0, 0);
+                line_bools.push(0);
+                ret.width = line_bools.len();
+                ret.data.push(line_bools)
+            }
+        }
+        ret.data.insert(0, vec![0; ret.width]);
+        ret.data.push(vec![0; ret.width]);
+        ret.height += 2;
+        ret
+    }
+}
+
+impl From<&Pattern> for LifeState {
+    fn from(pattern: &Pattern) -> Self {
+        let mut data = vec![vec![0; pattern.width + 2]; pattern.height + 2];
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                data[y + 1][x + 1] = (pattern.get(x, y) == 1).into();
+            }
+        }
+        LifeState {
+            width: pattern.width + 2,
+            height: pattern.height + 2,
+            rule: Rule::default(),
+            topology: Topology::Bounded,
+            data,
+            activity: None,
+            mask: None,
+            noise: None,
+        }
+    }
+}
+
+impl Engine for LifeState {
+    fn width(&self) -> usize {
+        self.width - 2
+    }
+
+    fn height(&self) -> usize {
+        self.height - 2
+    }
+
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.data[y + 1][x + 1]
+    }
+
+    fn set(&mut self, x: usize, y: usize, state: u8) {
+        self.data[y + 1][x + 1] = (state != 0).into();
+    }
+
+    fn step(&mut self) {
+        *self = self.next().unwrap();
+    }
+
+    fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    fn track_activity(&mut self) -> Result<(), &'static str> {
+        let (width, height) = (self.width(), self.height());
+        self.activity = Some(Activity::new(width, height, |x, y| self.get(x, y) == 1));
+        Ok(())
+    }
+
+    fn activity(&self) -> Option<&Activity> {
+        self.activity.as_ref()
+    }
+
+    fn set_mask(&mut self, mask: Mask) -> Result<(), &'static str> {
+        mask.check(self.width(), self.height())?;
+        self.mask = Some(mask);
+        self.apply_mask();
+        Ok(())
+    }
+
+    fn mask(&self) -> Option<&Mask> {
+        self.mask.as_ref()
+    }
+
+    fn set_noise(&mut self, noise: Noise) -> Result<(), &'static str> {
+        self.noise = Some(noise);
+        Ok(())
+    }
+
+    fn noise(&self) -> Option<&Noise> {
+        self.noise.as_ref()
+    }
+}
+
+/// Prints the board without the frame.
+impl fmt::Display for LifeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ret = String::new();
+        for line in &self.data[1..self.height - 1] {
+            for cell in &line[1..self.width - 1] {
+                ret.push(if *cell == 1 { 'X' } else { '.' });
+            }
+            ret.push('\n');
+        }
+        write!(f, "{}", ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use gol::rule;
+
+    impl LifeState {
+        /// The live neighbours of the cell, counted from its neighbourhood.
+        fn neighbours_count(&self, x: usize, y: usize) -> u32 {
+            (self.neighbourhood(x, y) & rule::NEIGHBOURS).count_ones()
+        }
+    }
+
+    #[test]
+    fn neighbours_count() {
+        let mut life = LifeState::from("...\n...\n...");
+
+        // No neighbours
+        for y in 1..4 {
+            for x in 1..4 {
+                assert_eq!(life.neighbours_count(x, y), 0)
+            }
+        }
+
+        // One
+        life.data[1][1] = 1;
+        assert_eq!(life.neighbours_count(2, 2), 1);
+
+        // Two
+        life.data[1][2] = 1;
+        assert_eq!(life.neighbours_count(2, 2), 2);
+
+        // Three
+        life.data[1][3] = 1;
+        assert_eq!(life.neighbours_count(2, 2), 3);
+
+        // Four
+        life.data[2][1] = 1;
+        assert_eq!(life.neighbours_count(2, 2), 4);
+
+        // Four (shouldn't consider self)
+        life.data[2][2] = 1;
+        assert_eq!(life.neighbours_count(2, 2), 4);
+
+        // Five
+        life.data[2][3] = 1;
+        assert_eq!(life.neighbours_count(2, 2), 5);
+
+        // Six
+        life.data[3][1] = 1;
+        assert_eq!(life.neighbours_count(2, 2), 6);
+
+        // Seven
+        life.data[3][2] = 1;
+        assert_eq!(life.neighbours_count(2, 2), 7);
+
+        // Eight
+        life.data[3][3] = 1;
+        assert_eq!(life.neighbours_count(2, 2), 8);
+    }
+
+    #[test]
+    fn neighbourhood() {
+        let mut life = LifeState::from("...\n...\n...");
+
+        // No neighbours
+        for y in 1..4 {
+            for x in 1..4 {
+                assert_eq!(life.neighbourhood(x, y), 0)
+            }
+        }
+
+        // Every neighbour sets its own bit, the cell itself the centre one
+        let mut expected = 0;
+        for i in 0..9 {
+            life.data[i / 3 + 1][i % 3 + 1] = 1;
+            expected |= 1 << i;
+            assert_eq!(life.neighbourhood(2, 2), expected);
+        }
+        assert_eq!(
+            life.neighbourhood(2, 2) & rule::NEIGHBOURS,
+            rule::NEIGHBOURS
+        );
+
+        // Corner only sees the cells inside the board
+        assert_eq!(
+            life.neighbourhood(1, 1),
+            rule::CENTRE | rule::bit(1, 0) | rule::bit(0, 1) | rule::bit(1, 1)
+        );
+    }
+
+    #[test]
+    fn non_square() {
+        let mut life = LifeState::from("....\nXXX.");
+        let life = life.next().unwrap();
+        assert_eq!(life, LifeState::from(".X..\n.X.."));
+    }
+
+    #[test]
+    fn hensel_rule() {
+        // X..
+        // X..
+        // ...
+        let mut life = LifeState::from("X..\nX..\n...");
+        life.rule = "B2/S".parse().unwrap();
+        let life = life.next().unwrap();
+        assert_eq!(life.data[1][2], 1);
+        assert_eq!(life.data[2][2], 1);
+
+        // B2-a doesn't give birth to cells seeing a corner with adjacent edge
+        let mut life = LifeState::from("X..\nX..\n...");
+        life.rule = "B2-a/S".parse().unwrap();
+        let mut expected = LifeState::from("...\n...\n...");
+        expected.rule = life.rule.clone();
+        assert_eq!(life.next().unwrap(), expected);
+
+        // 2i above and 2c below the middle are still born
+        let mut life = LifeState::from("X.X\n...\n...");
+        life.rule = "B2-a/S".parse().unwrap();
+        let life = life.next().unwrap();
+        assert_eq!(life.data[1][2], 1);
+        assert_eq!(life.data[2][2], 1);
+    }
+
+    #[test]
+    fn von_neumann() {
+        let mut life = LifeState::from("...\n.X.\n...");
+        life.rule = "B1/S0V".parse().unwrap();
+        let life = life.next().unwrap();
+        let mut expected = LifeState::from(".X.\nXXX\n.X.");
+        expected.rule = life.rule.clone();
+        assert_eq!(life, expected);
+    }
+
+    #[test]
+    fn hexagonal() {
+        let mut life = LifeState::from("...\n.X.\n...");
+        life.rule = "B1/S0H".parse().unwrap();
+        let life = life.next().unwrap();
+        let mut expected = LifeState::from("XX.\nXXX\n.XX");
+        expected.rule = life.rule.clone();
+        assert_eq!(life, expected);
+    }
+
+    #[test]
+    fn rule_s2() {
+        // ...
+        // XXX
+        // ...
+        let mut life = LifeState::from("...\nXXX\n...");
+
+        let init_state = life.clone();
+
+        // .X.
+        // .X.
+        // .X.
+        let life2 = LifeState::from(".X.\n.X.\n.X.");
+        let mut life = life.next().unwrap();
+        assert_eq!(life.data[2][2], 1);
+        assert_eq!(life, life2);
+
+        // with next iteration the life should return to previous state
+        let life = life.next().unwrap();
+        assert_eq!(life, init_state);
+    }
+
+    #[test]
+    fn rule_s3() {
+        // ...
+        // XXX
+        // X..
+        let init_state = LifeState::from("...\nXXX\nX..");
+
+        let mut life = init_state.clone();
+
+        // .X.
+        // XX.
+        // X..
+        let life_next = LifeState::from(".X.\nXX.\nX..");
+        let mut life = life.next().unwrap();
+        assert_eq!(life.data[2][2], 1); // S3
+        assert_eq!(life, life_next);
+
+        // XX.
+        // XX.
+        // XX.
+        let life_next = LifeState::from("XX.\nXX.\nXX.");
+        let life = life.next().unwrap();
+        assert_eq!(life, life_next);
+    }
+
+    #[test]
+    fn rule_b3() {
+        // X..
+        // ...
+        // X.X
+        let init_state = LifeState::from("X..\n...\nX.X");
+
+        let mut life = init_state.clone();
+        // ...
+        // .X.
+        // ...
+        let life_next = LifeState::from("...\n.X.\n...");
+        let life = life.next().unwrap();
+        assert_eq!(life.data[2][2], 1); // B3
+        assert_eq!(life, life_next);
+    }
+
+    /// Tests that the results outside of ruleset work
+    /// L0, L1, L4, L5, L6, L7, L8 -> D
+    /// D0-2, D4-8 -> D
+    #[test]
+    fn no_rule() {
+        // L0 -> D
+        // ...
+        // .X.
+        // ...
+        let mut life = LifeState::from("...\n.X.\n...");
+
+        let life = life.next().unwrap();
+        assert_eq!(life.data[2][2], 0); // L0
+
+        // L1 -> D
+        // X..
+        // .X.
+        // ...
+        let mut life = LifeState::from("X..\n.X.\n...");
+
+        let life = life.next().unwrap();
+        assert_eq!(life.data[2][2], 0); // L1
+
+        // L4 -> D
+        // XXX
+        // XX.
+        // ...
+        let mut life = LifeState::from("XXX\nXX.\n...");
+
+        let life = life.next().unwrap();
+        assert_eq!(life.data[2][2], 0); // L4
+
+        // L5 -> D
+        // XXX
+        // XXX
+        // ...
+        let mut life = LifeState::from("XXX\nXXX\n...");
+
+        let life = life.next().unwrap();
+        assert_eq!(life.data[2][2], 0); // L5
+
+        // L6 -> D
+        // XXX
+        // XXX
+        // X..
+        let mut life = LifeState::from("XXX\nXXX\nX..");
+
+        let life = life.next().unwrap();
+        assert_eq!(life.data[2][2], 0); // L6
+
+        // L7 -> D
+        // XXX
+        // XXX
+        // XX.
+        let mut life = LifeState::from("XXX\nXXX\nXX.");
+
+        let life = life.next().unwrap();
+        assert_eq!(life.data[2][2], 0); // L7
+
+        // L8 -> D
+        // XXX
+        // XXX
+        // XXX
+        let mut life = LifeState::from("XXX\nXXX\nXXX");
+
+        let life = life.next().unwrap();
+        assert_eq!(life.data[2][2], 0); // L8
+
+        // Dead stays dead loop
+        // ...
+        // ...
+        // ...
+        let mut init_state = LifeState::from("...\n...\n...");
+
+        for i in 0..9 {
+            let x = i % 3 + 1;
+            let y = i / 3 + 1;
+            if !(x == 2 && y == 2) {
+                init_state.data[x][y] = 1;
+            }
+            let life = init_state.next().unwrap();
+            // the cell should remain dead if i != 2 aka neighbours_count is != 3
+            if i != 2 {
+                assert_eq!(life.data[2][2], 0);
+            } else {
+                // we can check the rule here, why not
+                assert_eq!(life.data[2][2], 1);
+            }
+        }
+    }
+
+    #[test]
+    fn macrocell() {
+        let mut life =
+            LifeState::from_macrocell("[M2]\n#R B36/S23\n.*$..*$***$\n4 0 0 0 1\n").unwrap();
+        assert_eq!(life.rule, "B36/S23".parse().unwrap());
+        assert_eq!(
+            life,
+            LifeState {
+                rule: life.rule.clone(),
+                topology: Topology::Bounded,
+                ..LifeState::from(".X.\n..X\nXXX")
+            }
+        );
+        let life = life.next().unwrap();
+        assert_eq!(
+            LifeState::from_macrocell(&life.to_macrocell()).unwrap(),
+            life
+        );
+    }
+
+    #[test]
+    fn torus() {
+        // the glider wraps around and is back after 4 generations per cell
+        let start = ".X...\n..X..\nXXX..\n.....\n.....\n";
+        let mut life = LifeState::from(start);
+        life.topology = Topology::Torus;
+        for i in 1..=20 {
+            life = life.next().unwrap();
+            assert_eq!(life.to_string() == start, i == 20);
+        }
+
+        // while on the bounded board it becomes a block in the corner
+        life.topology = Topology::Bounded;
+        for _ in 0..20 {
+            life = life.next().unwrap();
+        }
+        assert_eq!(life.to_string(), ".....\n.....\n.....\n...XX\n...XX\n");
+    }
+
+    #[test]
+    fn plaintext_obstacles() {
+        let mut life = LifeState::from_plaintext(".X.@\n.X.#\n.X..\n").unwrap();
+        life.step();
+        assert_eq!(life.to_pattern().to_plaintext(), "..X@\nXX.#\n....\n");
+        assert!(LifeState::from_plaintext("..\n...\n").is_err());
+        assert!(LifeState::from_plaintext(".2\n").is_err());
+    }
+}
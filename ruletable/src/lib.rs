@@ -0,0 +1,303 @@
+//! Arbitrary multi-state rules loaded from Golly `.rule` files with a
+//! `@TABLE` section, e.g. WireWorld or Langton's loops. The table is compiled
+//! by `gol::table`, the engine only gathers the neighbourhood of every cell.
+//! As for the other byte engines a cell counts as alive for the activity in
+//! state 1, e.g. the electron heads of WireWorld.
+
+use gol::activity::Activity;
+use gol::mask::Mask;
+use gol::pattern::Pattern;
+use gol::{Engine, Table, Topology};
+use std::fmt;
+
+/// Double buffered state padded with always dead (state 0) frame, same as in
+/// `generations`. `width` and `height` are the real dimensions without the
+/// frame.
+#[derive(Debug, Clone)]
+pub struct LifeState {
+    width: usize,
+    height: usize,
+    pub table: Table,
+    pub topology: Topology,
+    last: Vec<u8>,
+    current: Vec<u8>,
+    activity: Option<Activity>,
+    mask: Option<Mask>,
+}
+
+impl LifeState {
+    pub fn new(width: usize, height: usize, table: Table) -> Self {
+        let size = (width + 2) * (height + 2);
+        LifeState {
+            width,
+            height,
+            table,
+            topology: Topology::Bounded,
+            last: vec![0; size],
+            current: vec![0; size],
+            activity: None,
+            mask: None,
+        }
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        (y + 1) * (self.width + 2) + x + 1
+    }
+
+    /// Fills the frame with the cells beyond the edges, dead or the opposite
+    /// edge of the board on a torus.
+    fn fill_frame(&mut self) {
+        let (width, height) = (self.width, self.height);
+        let stride = width + 2;
+        let torus = self.topology == Topology::Torus;
+        for y in 1..=height {
+            let row = y * stride;
+            self.last[row] = if torus { self.last[row + width] } else { 0 };
+            self.last[row + width + 1] = if torus { self.last[row + 1] } else { 0 };
+        }
+        for x in 0..stride {
+            let (top, bottom) = (x, (height + 1) * stride + x);
+            self.last[top] = if torus { self.last[bottom - stride] } else { 0 };
+            self.last[bottom] = if torus { self.last[top + stride] } else { 0 };
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.fill_frame();
+        let stride = (self.width + 2) as i64;
+        // This is safe, because of the padding introduced
+        let offsets: Vec<i64> = self
+            .table
+            .offsets()
+            .iter()
+            .map(|&(dx, dy)| dy * stride + dx)
+            .collect();
+        let mut neighbours = vec![0; offsets.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = self.index(x, y);
+                for (n, offset) in neighbours.iter_mut().zip(&offsets) {
+                    *n = self.last[(i as i64 + offset) as usize];
+                }
+                self.current[i] = self.table.next(self.last[i], &neighbours);
+                if let Some(activity) = &mut self.activity {
+                    // counted in the state the mask keeps the cell in
+                    let masked = self.mask.as_ref().and_then(|m| m.get(x, y));
+                    let alive = masked.unwrap_or(self.current[i]) == 1;
+                    activity.update(y * self.width + x, self.last[i] == 1, alive);
+                }
+            }
+        }
+        std::mem::swap(&mut self.last, &mut self.current);
+        self.apply_mask();
+    }
+
+    /// Forces the walls and fixed cells, see `gol::mask`.
+    fn apply_mask(&mut self) {
+        if let Some(mask) = &self.mask {
+            for (x, y, state) in mask.cells() {
+                let i = self.index(x, y);
+                self.last[i] = state;
+            }
+        }
+    }
+
+    pub fn from_pattern(pattern: &Pattern, table: Table) -> Result<Self, &'static str> {
+        let mut ret = LifeState::new(pattern.width, pattern.height, table);
+        for y in 0..pattern.height {
+            for x in 0..pattern.width {
+                let state = pattern.get(x, y);
+                if state as usize >= ret.table.states() {
+                    return Err("Error: Cell state is not valid for the rule.");
+                }
+                ret.set(x, y, state);
+            }
+        }
+        Ok(ret)
+    }
+
+    pub fn to_rle(&self) -> String {
+        self.to_pattern()
+            .to_rle(self.table.name(), self.table.states() > 2)
+    }
+
+    pub fn to_macrocell(&self) -> String {
+        self.to_pattern()
+            .to_macrocell(self.table.name(), self.table.states() > 2)
+    }
+}
+
+impl Engine for LifeState {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.last[self.index(x, y)]
+    }
+
+    fn set(&mut self, x: usize, y: usize, state: u8) {
+        let i = self.index(x, y);
+        self.last[i] = state;
+    }
+
+    fn step(&mut self) {
+        self.next();
+    }
+
+    fn rule(&self) -> String {
+        self.table.name().to_string()
+    }
+
+    fn states(&self) -> usize {
+        self.table.states()
+    }
+
+    fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    fn track_activity(&mut self) -> Result<(), &'static str> {
+        let (width, height) = (self.width, self.height);
+        self.activity = Some(Activity::new(width, height, |x, y| self.get(x, y) == 1));
+        Ok(())
+    }
+
+    fn activity(&self) -> Option<&Activity> {
+        self.activity.as_ref()
+    }
+
+    fn set_mask(&mut self, mask: Mask) -> Result<(), &'static str> {
+        mask.check(self.width, self.height)?;
+        self.mask = Some(mask);
+        self.apply_mask();
+        Ok(())
+    }
+
+    fn mask(&self) -> Option<&Mask> {
+        self.mask.as_ref()
+    }
+}
+
+impl fmt::Display for LifeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_pattern().to_plaintext())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const WIREWORLD: &str = "@RULE WireWorld
+@TABLE
+n_states:4
+neighborhood:Moore
+symmetries:permute
+var a={0,1,2,3}
+var b={a}
+var c={a}
+var d={a}
+var e={a}
+var f={a}
+var g={a}
+var h={a}
+var i={0,2,3}
+var j={i}
+var k={i}
+var l={i}
+var m={i}
+var n={i}
+var o={i}
+1,a,b,c,d,e,f,g,h,2
+2,a,b,c,d,e,f,g,h,3
+3,1,i,j,k,l,m,n,o,1
+3,1,1,i,j,k,l,m,n,1
+";
+
+    fn life(table: &str, plaintext: &str) -> LifeState {
+        let pattern = Pattern::from_plaintext(plaintext).unwrap();
+        LifeState::from_pattern(&pattern, table.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn wireworld_wire() {
+        // electron travelling along a wire to the right
+        let mut life = life(WIREWORLD, "2X3333\n");
+        life.next();
+        assert_eq!(life.to_string(), "32X333\n");
+        life.next();
+        assert_eq!(life.to_string(), "332X33\n");
+        for _ in 0..3 {
+            life.next();
+        }
+        assert_eq!(life.to_string(), "333332\n");
+    }
+
+    #[test]
+    fn von_neumann_table() {
+        // every cell next to a live one becomes alive, a growing diamond
+        let table = "@TABLE
+n_states:2
+neighborhood:vonNeumann
+symmetries:permute
+var a={0,1}
+var b={a}
+var c={a}
+0,1,a,b,c,1
+";
+        let mut life = life(table, ".....\n.....\n..X..\n.....\n.....\n");
+        life.next();
+        life.next();
+        assert_eq!(life.to_string(), "..X..\n.XXX.\nXXXXX\n.XXX.\n..X..\n");
+    }
+
+    #[test]
+    fn invalid_states() {
+        let pattern = Pattern::from_plaintext("X4\n").unwrap();
+        assert!(LifeState::from_pattern(&pattern, WIREWORLD.parse().unwrap()).is_err());
+    }
+
+    #[test]
+    fn output_formats() {
+        let life = life(WIREWORLD, "2X33\n");
+        assert_eq!(life.to_rle(), "x = 4, y = 1, rule = WireWorld\nBA2C!\n");
+        assert_eq!(
+            life.to_macrocell(),
+            "[M2] (gol)\n#R WireWorld\n#C x = 4, y = 1\n1 2 1 0 0\n1 3 3 0 0\n2 1 2 0 0\n3 0 0 0 3\n"
+        );
+    }
+
+    #[test]
+    fn torus() {
+        // the electron goes round the ring
+        let start = "......\n2X3333\n......\n";
+        let mut life = life(WIREWORLD, start);
+        life.topology = Topology::Torus;
+        for i in 1..=6 {
+            life.next();
+            assert_eq!(life.to_string() == start, i == 6);
+        }
+    }
+
+    #[test]
+    fn activity() {
+        // the electron head is the live state, it is born and dies along
+        // the wire until the wall stops it
+        let pattern = Pattern::from_plaintext("X333#3\n").unwrap();
+        let mut life = LifeState::from_pattern(&pattern, WIREWORLD.parse().unwrap()).unwrap();
+        life.set_mask(pattern.mask.unwrap()).unwrap();
+        life.track_activity().unwrap();
+        for _ in 0..4 {
+            life.next();
+        }
+        let activity = life.activity().unwrap();
+        assert_eq!(activity.changes, [1, 2, 2, 2, 0, 0]);
+        assert_eq!(activity.age, [0; 6]);
+        assert_eq!(life.to_string(), "3332#3\n");
+    }
+}
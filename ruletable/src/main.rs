@@ -0,0 +1,59 @@
+use gol::macrocell;
+use gol::pattern::{self, Pattern, PLAINTEXT_MAX_STATES};
+use gol::Table;
+use ruletable::LifeState;
+use std::env;
+use std::fs;
+
+fn main() -> Result<(), String> {
+    // args check
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 4 {
+        println!("Usage: ./{} rule_file initial_state iterations", &args[0]);
+        return Err("Error: Expected 3 arguments.".to_string());
+    }
+
+    // iterations extraction
+    let iterations = match args[3].parse::<usize>() {
+        Ok(n) => n,
+        Err(_) => {
+            return Err("Error: Couldn't parse iterations argument. Expected usize.".to_string())
+        }
+    };
+    // files open
+    let table: Table = match fs::read_to_string(&args[1]) {
+        Ok(s) => s.parse()?,
+        Err(_) => return Err("Error: Couldn't open rule file".to_string()),
+    };
+    let input = match fs::read_to_string(&args[2]) {
+        Ok(s) => s,
+        Err(_) => return Err("Error: Couldn't open file".to_string()),
+    };
+    // create init state, the rule of a RLE or macrocell header is ignored
+    let mc = macrocell::is_macrocell(&input);
+    let rle = !mc && pattern::is_rle(&input);
+    if !rle && !mc && table.states() > PLAINTEXT_MAX_STATES {
+        return Err("Error: Plaintext supports at most 36 states, use RLE input.".to_string());
+    }
+    let pattern = if mc {
+        Pattern::from_macrocell(&input)?
+    } else if rle {
+        Pattern::from_rle(&input)?
+    } else {
+        Pattern::from_plaintext(&input)?
+    };
+    let mut life = LifeState::from_pattern(&pattern, table)?;
+    // loop
+    for _ in 0..iterations {
+        life.next();
+    }
+    // print result in the same format as the input
+    if mc {
+        print!("{}", life.to_macrocell());
+    } else if rle {
+        print!("{}", life.to_rle());
+    } else {
+        print!("{}", life);
+    }
+    Ok(())
+}
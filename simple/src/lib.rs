@@ -0,0 +1,647 @@
+//! The most basic implementation, the board is one `Vec<bool>` and every
+//! cell looks up its neighbours with bounds checks.
+
+use gol::mask::Mask;
+use gol::noise::Noise;
+use gol::pattern::Pattern;
+use gol::rule::{self, Rule};
+use gol::{Engine, Topology};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct LifeState {
+    width: usize,
+    height: usize,
+    pub rule: Rule,
+    pub topology: Topology,
+    data: Vec<bool>,
+    mask: Option<Mask>,
+    noise: Option<Noise>,
+}
+
+impl LifeState {
+    /// Returns the neighbourhood index of the cell for the rule lookup table,
+    /// see `gol::rule`. Only the neighbours of the rule's neighbourhood are set.
+    fn neighbourhood(&self, i: usize) -> usize {
+        let x = i % self.width;
+        let y = i / self.width;
+        let mut ret = if self.data[i] { rule::CENTRE } else { 0 };
+        for &(dx, dy) in self.rule.neighbourhood().offsets() {
+            let nx = self.topology.offset(x, dx, self.width);
+            let ny = self.topology.offset(y, dy, self.height);
+            if let (Some(nx), Some(ny)) = (nx, ny) {
+                if self.data[nx + ny * self.width] {
+                    ret |= rule::bit(dx, dy);
+                }
+            }
+        }
+        ret
+    }
+
+    /// Reads a plaintext board, `#` and `@` become the walls and fixed
+    /// cells of its mask, see `gol::mask`.
+    pub fn from_plaintext(s: &str) -> Result<Self, &'static str> {
+        let pattern = Pattern::from_plaintext(s)?;
+        if pattern.cells.iter().any(|&c| c > 1) {
+            return Err("Error: Cell state is not valid for the rule.");
+        }
+        let mut ret = LifeState::from(&pattern);
+        if let Some(mask) = pattern.mask {
+            ret.set_mask(mask)?;
+        }
+        Ok(ret)
+    }
+
+    /// Parses the macrocell format, see `gol::macrocell`. The rule is taken
+    /// from the header unless it is missing.
+    pub fn from_macrocell(s: &str) -> Result<Self, &'static str> {
+        let pattern = Pattern::from_macrocell(s)?;
+        let mut ret = LifeState::from(&pattern);
+        if let Some(rule) = &pattern.rule {
+            ret.rule = rule.parse()?;
+        }
+        Ok(ret)
+    }
+
+    /// Forces the walls and fixed cells, see `gol::mask`.
+    fn apply_mask(&mut self) {
+        if let Some(mask) = &self.mask {
+            for (x, y, state) in mask.cells() {
+                self.data[y * self.width + x] = state != 0;
+            }
+        }
+    }
+
+    pub fn to_macrocell(&self) -> String {
+        self.to_pattern()
+            .to_macrocell(&self.rule.to_string(), false)
+    }
+}
+
+impl Iterator for LifeState {
+    type Item = LifeState;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut dice = self.noise.as_mut().map(Noise::step);
+        let mut new = LifeState {
+            width: self.width,
+            height: self.height,
+            rule: self.rule.clone(),
+            topology: self.topology,
+            data: Vec::with_capacity(self.data.len()),
+            mask: self.mask.clone(),
+            noise: self.noise,
+        };
+        for i in 0..self.data.len() {
+            let mut new_val = self.rule.alive(self.neighbourhood(i));
+            if let Some(dice) = &mut dice {
+                new_val = dice.cell(self.data[i], new_val);
+            }
+            new.data.push(new_val);
+        }
+        new.apply_mask();
+        Some(new)
+    }
+}
+
+impl From<&Pattern> for LifeState {
+    fn from(pattern: &Pattern) -> Self {
+        LifeState {
+            width: pattern.width,
+            height: pattern.height,
+            rule: Rule::default(),
+            topology: Topology::Bounded,
+            data: pattern.cells.iter().map(|&c| c == 1).collect(),
+            mask: None,
+            noise: None,
+        }
+    }
+}
+
+impl Engine for LifeState {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn get(&self, x: usize, y: usize) -> u8 {
+        self.data[y * self.width + x].into()
+    }
+
+    fn set(&mut self, x: usize, y: usize, state: u8) {
+        self.data[y * self.width + x] = state != 0;
+    }
+
+    fn step(&mut self) {
+        *self = self.next().unwrap();
+    }
+
+    fn rule(&self) -> String {
+        self.rule.to_string()
+    }
+
+    fn topology(&self) -> Topology {
+        self.topology
+    }
+
+    fn set_mask(&mut self, mask: Mask) -> Result<(), &'static str> {
+        mask.check(self.width, self.height)?;
+        self.mask = Some(mask);
+        self.apply_mask();
+        Ok(())
+    }
+
+    fn mask(&self) -> Option<&Mask> {
+        self.mask.as_ref()
+    }
+
+    fn set_noise(&mut self, noise: Noise) -> Result<(), &'static str> {
+        self.noise = Some(noise);
+        Ok(())
+    }
+
+    fn noise(&self) -> Option<&Noise> {
+        self.noise.as_ref()
+    }
+}
+
+impl fmt::Display for LifeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut ret = String::new();
+        for (i, cell) in self.data.iter().enumerate() {
+            match *cell {
+                true => ret.push('X'),
+                false => ret.push('.'),
+            }
+            if i % self.width == self.width - 1 {
+                ret.push('\n');
+            }
+        }
+        write!(f, "{}", ret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    impl LifeState {
+        /// A bounded board with the cells of `data`, row by row.
+        fn with_data(width: usize, height: usize, data: Vec<bool>, rule: Rule) -> Self {
+            LifeState {
+                width,
+                height,
+                rule,
+                topology: Topology::Bounded,
+                data,
+                mask: None,
+                noise: None,
+            }
+        }
+
+        /// The live neighbours of the cell, counted from its neighbourhood.
+        fn neighbours_count(&self, i: usize) -> u32 {
+            (self.neighbourhood(i) & rule::NEIGHBOURS).count_ones()
+        }
+    }
+
+    #[test]
+    fn neighbours_count() {
+        let mut life = LifeState::with_data(3, 3, vec![false; 9], Rule::default());
+
+        // No neighbours
+        for i in 0..9 {
+            assert_eq!(life.neighbours_count(i), 0)
+        }
+
+        // One
+        life.data[0] = true;
+        assert_eq!(life.neighbours_count(4), 1);
+
+        // Two
+        life.data[1] = true;
+        assert_eq!(life.neighbours_count(4), 2);
+
+        // Three
+        life.data[2] = true;
+        assert_eq!(life.neighbours_count(4), 3);
+
+        // Four
+        life.data[3] = true;
+        assert_eq!(life.neighbours_count(4), 4);
+
+        // Four (shouldn't consider self)
+        life.data[4] = true;
+        assert_eq!(life.neighbours_count(4), 4);
+
+        // Five
+        life.data[5] = true;
+        assert_eq!(life.neighbours_count(4), 5);
+
+        // Six
+        life.data[6] = true;
+        assert_eq!(life.neighbours_count(4), 6);
+
+        // Seven
+        life.data[7] = true;
+        assert_eq!(life.neighbours_count(4), 7);
+
+        // Eight
+        life.data[8] = true;
+        assert_eq!(life.neighbours_count(4), 8);
+    }
+
+    #[test]
+    fn neighbourhood() {
+        let mut life = LifeState::with_data(3, 3, vec![false; 9], Rule::default());
+
+        // No neighbours
+        for i in 0..9 {
+            assert_eq!(life.neighbourhood(i), 0)
+        }
+
+        // Every neighbour sets its own bit, the cell itself the centre one
+        let mut expected = 0;
+        for i in 0..9 {
+            life.data[i] = true;
+            expected |= 1 << i;
+            assert_eq!(life.neighbourhood(4), expected);
+        }
+        assert_eq!(life.neighbourhood(4) & rule::NEIGHBOURS, rule::NEIGHBOURS);
+
+        // Corner only sees the cells inside the board
+        assert_eq!(
+            life.neighbourhood(0),
+            rule::CENTRE | rule::bit(1, 0) | rule::bit(0, 1) | rule::bit(1, 1)
+        );
+    }
+
+    #[test]
+    fn non_square() {
+        // ....
+        // XXX.
+        let mut life = LifeState::with_data(
+            4,
+            2,
+            vec![false, false, false, false, true, true, true, false],
+            Rule::default(),
+        );
+        life = life.next().unwrap();
+        assert_eq!(
+            life.data,
+            vec![false, true, false, false, false, true, false, false]
+        );
+    }
+
+    #[test]
+    fn hensel_rule() {
+        // X..
+        // X..
+        // ...
+        let init_state = LifeState::with_data(
+            3,
+            3,
+            vec![true, false, false, true, false, false, false, false, false],
+            "B2/S".parse().unwrap(),
+        );
+
+        // B2 gives birth to both cells with two neighbours
+        let mut life = init_state.clone();
+        let life = life.next().unwrap();
+        assert!(life.data[1]);
+        assert!(life.data[4]);
+
+        // B2-a doesn't, both see a corner with adjacent edge (2a)
+        let mut life = init_state.clone();
+        life.rule = "B2-a/S".parse().unwrap();
+        let life = life.next().unwrap();
+        assert_eq!(life.data, vec![false; 9]);
+
+        // X.X
+        // ...
+        // ...
+        // 2i above and 2c below the middle are still born
+        let mut life = LifeState::with_data(
+            3,
+            3,
+            vec![true, false, true, false, false, false, false, false, false],
+            "B2-a/S".parse().unwrap(),
+        );
+        let life = life.next().unwrap();
+        assert!(life.data[1]);
+        assert!(life.data[4]);
+    }
+
+    #[test]
+    fn von_neumann() {
+        // ...
+        // .X.
+        // ...
+        let mut life = LifeState::with_data(
+            3,
+            3,
+            vec![false, false, false, false, true, false, false, false, false],
+            "B1/S0V".parse().unwrap(),
+        );
+        assert_eq!(life.neighbourhood(0), 0);
+        assert_eq!(life.neighbourhood(1), rule::bit(0, 1));
+
+        // .X.
+        // XXX
+        // .X.
+        let life = life.next().unwrap();
+        assert_eq!(
+            life.data,
+            vec![false, true, false, true, true, true, false, true, false]
+        );
+    }
+
+    #[test]
+    fn hexagonal() {
+        // ...
+        // .X.
+        // ...
+        let mut life = LifeState::with_data(
+            3,
+            3,
+            vec![false, false, false, false, true, false, false, false, false],
+            "B1/S0H".parse().unwrap(),
+        );
+
+        // XX.
+        // XXX
+        // .XX
+        let life = life.next().unwrap();
+        assert_eq!(
+            life.data,
+            vec![true, true, false, true, true, true, false, true, true]
+        );
+    }
+
+    #[test]
+    fn rule_s2() {
+        // ...
+        // XXX
+        // ...
+        let mut life = LifeState::with_data(3, 3, vec![false; 9], Rule::default());
+
+        life.data[4] = true;
+        life.data[3] = true;
+        life.data[5] = true;
+
+        let init_state = life.clone();
+
+        // .X.
+        // .X.
+        // .X.
+        let life2 = LifeState::with_data(
+            3,
+            3,
+            vec![false, true, false, false, true, false, false, true, false],
+            Rule::default(),
+        );
+        let mut life = life.next().unwrap();
+        assert!(life.data[4]);
+        assert_eq!(life, life2);
+
+        // with next iteration the life should return to previous state
+        let life = life.next().unwrap();
+        assert_eq!(life, init_state);
+    }
+
+    #[test]
+    fn rule_s3() {
+        // ...
+        // XXX
+        // X..
+        let init_state = LifeState::with_data(
+            3,
+            3,
+            vec![false, false, false, true, true, true, true, false, false],
+            Rule::default(),
+        );
+
+        let mut life = init_state.clone();
+
+        // .X.
+        // XX.
+        // X..
+        let life_next = LifeState::with_data(
+            3,
+            3,
+            vec![false, true, false, true, true, false, true, false, false],
+            Rule::default(),
+        );
+        let mut life = life.next().unwrap();
+        assert!(life.data[4]); // S3
+        assert_eq!(life, life_next);
+
+        // XX.
+        // XX.
+        // XX.
+        let life_next = LifeState::with_data(
+            3,
+            3,
+            vec![true, true, false, true, true, false, true, true, false],
+            Rule::default(),
+        );
+        let life = life.next().unwrap();
+        assert_eq!(life, life_next);
+    }
+
+    #[test]
+    fn rule_b3() {
+        // X..
+        // ...
+        // X.X
+        let init_state = LifeState::with_data(
+            3,
+            3,
+            vec![true, false, false, false, false, false, true, false, true],
+            Rule::default(),
+        );
+
+        let mut life = init_state.clone();
+        // ...
+        // .X.
+        // ...
+        let life_next = LifeState::with_data(
+            3,
+            3,
+            vec![false, false, false, false, true, false, false, false, false],
+            Rule::default(),
+        );
+        let life = life.next().unwrap();
+        assert!(life.data[4]); // B3
+        assert_eq!(life, life_next);
+    }
+
+    /// Tests that the results outside of ruleset work
+    /// L0, L1, L4, L5, L6, L7, L8 -> D
+    /// D0-2, D4-8 -> D
+    #[test]
+    fn no_rule() {
+        // L0 -> D
+        // ...
+        // .X.
+        // ...
+        let mut life = LifeState::with_data(
+            3,
+            3,
+            vec![false, false, false, false, true, false, false, false, false],
+            Rule::default(),
+        );
+
+        let life = life.next().unwrap();
+        assert!(!life.data[4]); // L0
+
+        // L1 -> D
+        // X..
+        // .X.
+        // ...
+        let mut life = LifeState::with_data(
+            3,
+            3,
+            vec![true, false, false, false, true, false, false, false, false],
+            Rule::default(),
+        );
+
+        let life = life.next().unwrap();
+        assert!(!life.data[4]); // L1
+
+        // L4 -> D
+        // XXX
+        // XX.
+        // ...
+        let mut life = LifeState::with_data(
+            3,
+            3,
+            vec![true, true, true, true, true, false, false, false, false],
+            Rule::default(),
+        );
+
+        let life = life.next().unwrap();
+        assert!(!life.data[4]); // L4
+
+        // L5 -> D
+        // XXX
+        // XXX
+        // ...
+        let mut life = LifeState::with_data(
+            3,
+            3,
+            vec![true, true, true, true, true, true, false, false, false],
+            Rule::default(),
+        );
+
+        let life = life.next().unwrap();
+        assert!(!life.data[4]); // L5
+
+        // L6 -> D
+        // XXX
+        // XXX
+        // X..
+        let mut life = LifeState::with_data(
+            3,
+            3,
+            vec![true, true, true, true, true, true, true, false, false],
+            Rule::default(),
+        );
+
+        let life = life.next().unwrap();
+        assert!(!life.data[4]); // L6
+
+        // L7 -> D
+        // XXX
+        // XXX
+        // XX.
+        let mut life = LifeState::with_data(
+            3,
+            3,
+            vec![true, true, true, true, true, true, true, true, false],
+            Rule::default(),
+        );
+
+        let life = life.next().unwrap();
+        assert!(!life.data[4]); // L7
+
+        // L8 -> D
+        // XXX
+        // XXX
+        // XXX
+        let mut life = LifeState::with_data(
+            3,
+            3,
+            vec![true, true, true, true, true, true, true, true, true],
+            Rule::default(),
+        );
+
+        let life = life.next().unwrap();
+        assert!(!life.data[4]); // L8
+
+        // Dead stays dead loop
+        // ...
+        // ...
+        // ...
+        let mut init_state = LifeState::with_data(3, 3, vec![false; 9], Rule::default());
+
+        for i in 0..9 {
+            if i != 4 {
+                init_state.data[i] = true;
+            }
+            let life = init_state.next().unwrap();
+            // the cell should remain dead if i != 2 aka neighbours_count is != 3
+            if i != 2 {
+                assert!(!life.data[4]);
+            } else {
+                // we can check the rule here, why not
+                assert!(life.data[4]);
+            }
+        }
+    }
+
+    #[test]
+    fn macrocell() {
+        let mut life =
+            LifeState::from_macrocell("[M2]\n#R B36/S23\n.*$..*$***$\n4 0 0 0 1\n").unwrap();
+        assert_eq!(life.rule, "B36/S23".parse().unwrap());
+        assert_eq!(life.to_string(), ".X.\n..X\nXXX\n");
+        let life = life.next().unwrap();
+        assert_eq!(
+            LifeState::from_macrocell(&life.to_macrocell()).unwrap(),
+            life
+        );
+    }
+
+    #[test]
+    fn torus() {
+        // the glider wraps around and is back after 4 generations per cell
+        let pattern = Pattern::from_plaintext(".X...\n..X..\nXXX..\n.....\n.....\n").unwrap();
+        let mut life = LifeState::from(&pattern);
+        life.topology = Topology::Torus;
+        let start = life.clone();
+        for _ in 0..20 {
+            life.step();
+        }
+        assert_eq!(life, start);
+
+        // while on the bounded board it becomes a block in the corner
+        life.topology = Topology::Bounded;
+        for _ in 0..20 {
+            life.step();
+        }
+        assert_eq!(life.to_string(), ".....\n.....\n.....\n...XX\n...XX\n");
+    }
+
+    #[test]
+    fn plaintext_obstacles() {
+        let mut life = LifeState::from_plaintext(".X.@\n.X.#\n.X..\n").unwrap();
+        life.step();
+        assert_eq!(life.to_pattern().to_plaintext(), "..X@\nXX.#\n....\n");
+        assert!(LifeState::from_plaintext("..\n...\n").is_err());
+        assert!(LifeState::from_plaintext(".2\n").is_err());
+    }
+}